@@ -88,6 +88,190 @@ pub struct Tui {
     /// `space` still work. This allows the user to select text in the TUI
     /// using the mouse without needing to hold down a modifier key.
     pub disable_mouse_capture: bool,
+
+    /// Which Enter combination submits a message from the composer.
+    #[serde(default)]
+    pub submit_mode: SubmitMode,
+
+    /// What pressing Esc does to the text in the composer.
+    #[serde(default)]
+    pub esc_behavior: EscBehavior,
+
+    /// Keys that recall earlier messages in the composer.
+    #[serde(default)]
+    pub history_nav_keys: HistoryNavKeys,
+
+    /// What typing `.` into the empty composer repeats.
+    #[serde(default)]
+    pub dot_repeat: DotRepeat,
+
+    /// Enable Vim-style Normal/Insert modal editing in the composer.
+    #[serde(default)]
+    pub vim_mode: bool,
+
+    /// Jump to the first / last line of a long draft with `gg` / `G`
+    /// outside of Vim mode.
+    #[serde(default)]
+    pub jump_keys: bool,
+
+    /// Highlight code between ``` fences while it is being composed.
+    #[serde(default)]
+    pub highlight_code_blocks: bool,
+
+    /// Keep the indentation of the current line when inserting a newline
+    /// inside a ``` fence in the composer.
+    #[serde(default)]
+    pub auto_indent: bool,
+
+    /// Start a newline inserted after a `- `, `* ` or `1. ` list item with
+    /// the next marker.
+    #[serde(default)]
+    pub continue_lists: bool,
+
+    /// Number of spaces Tab inserts in the composer. Unset for 4; 0 inserts
+    /// a tab character instead.
+    #[serde(default)]
+    pub tab_width: Option<usize>,
+
+    /// Fewest rows of text the composer shows. Unset for 1.
+    #[serde(default)]
+    pub min_composer_rows: Option<usize>,
+
+    /// Most rows of text the composer grows to before scrolling. Unset for
+    /// 10.
+    #[serde(default)]
+    pub max_composer_rows: Option<usize>,
+
+    /// Model names offered when completing the argument of `/model`, in
+    /// addition to the model of the current session.
+    #[serde(default)]
+    pub models: Vec<String>,
+
+    /// Pastes longer than this many lines are sent as an attachment and
+    /// shown as a placeholder in the composer. Unset to always inline.
+    #[serde(default)]
+    pub paste_attachment_lines: Option<usize>,
+
+    /// Turn curly quotes and dashes in pasted text into ASCII.
+    #[serde(default)]
+    pub ascii_paste_punctuation: bool,
+
+    /// Watch the workspace for added or removed files so `@path`
+    /// completions need not rescan it every time.
+    #[serde(default)]
+    pub watch_files: bool,
+
+    /// Longest message, in characters, that the composer submits as is.
+    /// Unset for no limit.
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+
+    /// What submitting a message longer than `max_chars` does.
+    #[serde(default)]
+    pub over_limit: OverLimitBehavior,
+
+    /// Where completion popups are drawn relative to the composer text.
+    #[serde(default)]
+    pub popup_placement: PopupPlacement,
+
+    /// Show the word count and an estimate of the tokens of the draft in
+    /// the composer border.
+    #[serde(default)]
+    pub show_word_count: bool,
+
+    /// Characters per token assumed by that estimate. Unset for 4.
+    #[serde(default)]
+    pub chars_per_token: Option<usize>,
+
+    /// Split each submitted message into prose, `@path` mentions and fenced
+    /// code blocks, and log the parts.
+    #[serde(default)]
+    pub parsed_submissions: bool,
+
+    /// Ignore the submit key in the composer while the agent is working,
+    /// instead of queueing the message.
+    #[serde(default)]
+    pub disable_input_while_busy: bool,
+
+    /// Values of the `:name:` tokens expanded in messages when they are
+    /// submitted, keyed by `name`.
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+}
+
+/// Key binding used to submit a message from the TUI composer.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SubmitMode {
+    /// Enter submits; Shift+Enter / Ctrl+J insert a newline.
+    #[default]
+    EnterSends,
+    /// Enter inserts a newline; Ctrl+Enter submits.
+    EnterNewlineCtrlEnterSends,
+    /// Enter inserts a newline, except on a blank last line, where it
+    /// submits; so does Ctrl+Enter.
+    BlankLineSends,
+}
+
+/// Effect of Esc on the text in the TUI composer.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum EscBehavior {
+    /// Two consecutive presses clear the text.
+    #[default]
+    DoubleClear,
+    /// A single press clears the text.
+    SingleClear,
+    /// Esc never clears the text.
+    Disabled,
+}
+
+/// What `.` typed into the empty TUI composer repeats.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum DotRepeat {
+    /// `.` is typed like any other character.
+    #[default]
+    Disabled,
+    /// Run the last slash command again, with the same arguments.
+    Command,
+    /// Submit the last message again.
+    Message,
+}
+
+/// Keys that step through message history in the TUI composer.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HistoryNavKeys {
+    /// Up / Down, when the cursor is at the start / on the last row.
+    #[default]
+    Arrows,
+    /// Ctrl+Up / Ctrl+Down, leaving Up / Down to move the cursor.
+    CtrlArrows,
+    /// Alt+P / Alt+N, leaving Up / Down to move the cursor.
+    AltPN,
+}
+
+/// Handling of a TUI message longer than the configured `max_chars`.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverLimitBehavior {
+    /// Refuse to submit and keep the text in the composer.
+    #[default]
+    Block,
+    /// Send the text as several messages of at most `max_chars` each.
+    Split,
+}
+
+/// Where the TUI composer draws its completion popups.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PopupPlacement {
+    /// Above the text, for a composer at the bottom of the screen.
+    #[default]
+    Above,
+    /// Below the text.
+    Below,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
@@ -0,0 +1,40 @@
+#![allow(clippy::expect_used)]
+
+use regex_lite::Regex;
+
+// This is defined in its own file so we can limit the scope of
+// `allow(clippy::expect_used)` because we cannot scope it to the `lazy_static!`
+// macro.
+lazy_static::lazy_static! {
+    /// Regular expression that matches OpenAI API keys such as
+    /// `sk-proj-…`, which are long enough not to occur in prose by accident.
+    static ref API_KEY_REGEX: Regex = Regex::new(
+        r"\bsk-[A-Za-z0-9_-]{32,}"
+    ).expect("failed to compile API key regex");
+}
+
+/// Submit filter that keeps messages containing what looks like an API key
+/// from being sent to the model.
+pub(crate) fn reject_api_keys(text: String) -> Result<String, String> {
+    if API_KEY_REGEX.is_match(&text) {
+        Err("The message looks like it contains an API key; remove it before sending.".to_string())
+    } else {
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_with_api_keys_are_rejected() {
+        assert!(
+            reject_api_keys("use sk-proj-0123456789abcdefghijABCDEFGHIJ_-xyz here".into()).is_err()
+        );
+        assert_eq!(
+            reject_api_keys("set the sk-... key in the env".into()),
+            Ok("set the sk-... key in the env".to_string())
+        );
+    }
+}
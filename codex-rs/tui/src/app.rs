@@ -1,6 +1,7 @@
 use crate::app_event::AppEvent;
 use crate::app_event_sender::AppEventSender;
 use crate::chatwidget::ChatWidget;
+use crate::external_editor;
 use crate::git_warning_screen::GitWarningOutcome;
 use crate::git_warning_screen::GitWarningScreen;
 use crate::login_screen::LoginScreen;
@@ -17,8 +18,12 @@ use crossterm::event::KeyEvent;
 use crossterm::event::MouseEvent;
 use crossterm::event::MouseEventKind;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::channel;
+use std::time::Duration;
 
 /// Top-level application state: which full-screen view is currently active.
 #[allow(clippy::large_enum_variant)]
@@ -46,6 +51,10 @@ pub(crate) struct App<'a> {
     /// Stored parameters needed to instantiate the ChatWidget later, e.g.,
     /// after dismissing the Git-repo warning.
     chat_args: Option<ChatWidgetArgs>,
+
+    /// Set while another program (the external editor) owns the terminal,
+    /// so the input thread leaves its key presses alone.
+    input_paused: Arc<AtomicBool>,
 }
 
 /// Aggregate parameters needed to create a `ChatWidget`, as creation may be
@@ -70,11 +79,27 @@ impl<'a> App<'a> {
         let scroll_event_helper = ScrollEventHelper::new(app_event_tx.clone());
 
         // Spawn a dedicated thread for reading the crossterm event loop and
-        // re-publishing the events as AppEvents, as appropriate.
+        // re-publishing the events as AppEvents, as appropriate. It polls so
+        // that it can stop reading while `input_paused` is set.
+        let input_paused = Arc::new(AtomicBool::new(false));
         {
             let app_event_tx = app_event_tx.clone();
+            let input_paused = input_paused.clone();
             std::thread::spawn(move || {
-                while let Ok(event) = crossterm::event::read() {
+                loop {
+                    match crossterm::event::poll(Duration::from_millis(100)) {
+                        Ok(true) if !input_paused.load(Ordering::SeqCst) => {}
+                        Ok(_) => {
+                            if input_paused.load(Ordering::SeqCst) {
+                                std::thread::sleep(Duration::from_millis(50));
+                            }
+                            continue;
+                        }
+                        Err(_) => break,
+                    }
+                    let Ok(event) = crossterm::event::read() else {
+                        break;
+                    };
                     match event {
                         crossterm::event::Event::Key(key_event) => {
                             app_event_tx.send(AppEvent::KeyEvent(key_event));
@@ -95,21 +120,7 @@ impl<'a> App<'a> {
                             scroll_event_helper.scroll_down();
                         }
                         crossterm::event::Event::Paste(pasted) => {
-                            use crossterm::event::KeyModifiers;
-
-                            for ch in pasted.chars() {
-                                let key_event = match ch {
-                                    '\n' | '\r' => {
-                                        // Represent newline as <Shift+Enter> so that the bottom
-                                        // pane treats it as a literal newline instead of a submit
-                                        // action (submission is only triggered on Enter *without*
-                                        // any modifiers).
-                                        KeyEvent::new(KeyCode::Enter, KeyModifiers::SHIFT)
-                                    }
-                                    _ => KeyEvent::new(KeyCode::Char(ch), KeyModifiers::empty()),
-                                };
-                                app_event_tx.send(AppEvent::KeyEvent(key_event));
-                            }
+                            app_event_tx.send(AppEvent::Paste(pasted));
                         }
                         _ => {
                             // Ignore any other events.
@@ -162,6 +173,7 @@ impl<'a> App<'a> {
             app_state,
             config,
             chat_args,
+            input_paused,
         }
     }
 
@@ -192,10 +204,11 @@ impl<'a> App<'a> {
                             modifiers: crossterm::event::KeyModifiers::CONTROL,
                             ..
                         } => {
-                            // Forward interrupt to ChatWidget when active.
+                            // Forward to ChatWidget when active, which either
+                            // copies from the composer or interrupts.
                             match &mut self.app_state {
                                 AppState::Chat { widget } => {
-                                    widget.submit_op(Op::Interrupt);
+                                    widget.handle_ctrl_c();
                                 }
                                 AppState::Login { .. } | AppState::GitWarning { .. } => {
                                     // No-op.
@@ -206,17 +219,27 @@ impl<'a> App<'a> {
                             code: KeyCode::Char('d'),
                             modifiers: crossterm::event::KeyModifiers::CONTROL,
                             ..
-                        } => {
-                            self.app_event_tx.send(AppEvent::ExitRequest);
-                        }
+                        } => match &mut self.app_state {
+                            AppState::Chat { widget } => widget.handle_ctrl_d(key_event),
+                            AppState::Login { .. } | AppState::GitWarning { .. } => {
+                                self.app_event_tx.send(AppEvent::ExitRequest);
+                            }
+                        },
                         _ => {
                             self.dispatch_key_event(key_event);
                         }
                     };
                 }
+                AppEvent::Paste(pasted) => {
+                    self.dispatch_paste_event(pasted);
+                }
                 AppEvent::Scroll(scroll_delta) => {
                     self.dispatch_scroll_event(scroll_delta);
                 }
+                AppEvent::ScrollTranscript(direction) => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.scroll_transcript(direction),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
                 AppEvent::CodexEvent(event) => {
                     self.dispatch_codex_event(event);
                 }
@@ -231,7 +254,78 @@ impl<'a> App<'a> {
                     AppState::Chat { widget } => widget.update_latest_log(line),
                     AppState::Login { .. } | AppState::GitWarning { .. } => {}
                 },
-                AppEvent::DispatchCommand(command) => match command {
+                AppEvent::SelectionResponse(selection) => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.on_selection_response(selection),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::OpenExternalEditor(text) => {
+                    let edited = self.with_terminal_suspended(terminal, mouse_capture, || {
+                        external_editor::edit_text(&text)
+                    })?;
+                    match &mut self.app_state {
+                        AppState::Chat { widget } => match edited {
+                            Ok(text) => widget.on_external_editor_response(text),
+                            Err(e) => widget.show_notice(format!("Could not run the editor: {e}")),
+                        },
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    }
+                    self.app_event_tx.send(AppEvent::Redraw);
+                }
+                AppEvent::RequestFocus => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.focus_composer(),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::InterruptAgent => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.submit_op(Op::Interrupt),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::Notice(message) => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.show_notice(message),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::AttachImage(path) => {
+                    tracing::info!("attached image {}", path.display());
+                }
+                AppEvent::AttachFile(path) => {
+                    tracing::info!("attached file {}", path.display());
+                }
+                AppEvent::QueueMessage {
+                    text,
+                    image_paths,
+                    file_paths,
+                    mode,
+                } => match &mut self.app_state {
+                    AppState::Chat { widget } => {
+                        widget.queue_user_message(text, image_paths, file_paths, mode)
+                    }
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::AttachText {
+                    content,
+                    suggested_name,
+                    placeholder,
+                } => match &mut self.app_state {
+                    AppState::Chat { widget } => {
+                        widget.attach_pasted_text(content, suggested_name, placeholder)
+                    }
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::DetachText { suggested_name } => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.detach_pasted_text(&suggested_name),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::RecordHistory(text) => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.record_history(text),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::ComposerTextChanged { chars, lines } => {
+                    tracing::trace!("composer text changed: {chars} chars, {lines} lines");
+                }
+                AppEvent::ShowCommandHelp(command) => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.show_command_help(Some(command)),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
+                AppEvent::DispatchCommand { command, args } => match command {
                     SlashCommand::New => {
                         let new_widget = Box::new(ChatWidget::new(
                             self.config.clone(),
@@ -242,11 +336,34 @@ impl<'a> App<'a> {
                         self.app_state = AppState::Chat { widget: new_widget };
                         self.app_event_tx.send(AppEvent::Redraw);
                     }
+                    SlashCommand::Model => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.show_current_model(&args),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
+                    SlashCommand::Help => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.show_command_help(None),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
+                    SlashCommand::Selection => {
+                        // Reading the selection runs a helper program, so do
+                        // it off the UI thread and insert it when it arrives.
+                        let tx = self.app_event_tx.clone();
+                        std::thread::spawn(move || {
+                            tx.send(AppEvent::SelectionResponse(
+                                crate::selection::current_selection(),
+                            ));
+                        });
+                    }
                     SlashCommand::ToggleMouseMode => {
                         if let Err(e) = mouse_capture.toggle() {
                             tracing::error!("Failed to toggle mouse mode: {e}");
                         }
                     }
+                    SlashCommand::Ask | SlashCommand::Edit | SlashCommand::Code => {
+                        // Mode prefixes are submitted with the prompt and
+                        // `/code` is expanded in place by the composer; none
+                        // of them is dispatched.
+                    }
                     SlashCommand::Quit => {
                         break;
                     }
@@ -258,6 +375,30 @@ impl<'a> App<'a> {
         Ok(())
     }
 
+    /// Run `f` with the terminal handed back to the shell: input reading
+    /// and mouse capture are paused and the alternate screen is left.
+    fn with_terminal_suspended<T>(
+        &mut self,
+        terminal: &mut tui::Tui,
+        mouse_capture: &mut MouseCapture,
+        f: impl FnOnce() -> T,
+    ) -> Result<T> {
+        self.input_paused.store(true, Ordering::SeqCst);
+        let result = (|| {
+            let capture_was_active = mouse_capture.is_active();
+            mouse_capture.set_active(false)?;
+            tui::suspend()?;
+            let result = f();
+            tui::resume(terminal)?;
+            mouse_capture.set_active(capture_was_active)?;
+            Ok(result)
+        })();
+        // Resume reading input even if the terminal could not be switched,
+        // or no key would ever reach the app again.
+        self.input_paused.store(false, Ordering::SeqCst);
+        result
+    }
+
     fn draw_next_frame(&mut self, terminal: &mut tui::Tui) -> Result<()> {
         match &mut self.app_state {
             AppState::Chat { widget } => {
@@ -308,6 +449,13 @@ impl<'a> App<'a> {
         }
     }
 
+    fn dispatch_paste_event(&mut self, pasted: String) {
+        match &mut self.app_state {
+            AppState::Chat { widget } => widget.handle_paste(pasted),
+            AppState::Login { .. } | AppState::GitWarning { .. } => {}
+        }
+    }
+
     fn dispatch_scroll_event(&mut self, scroll_delta: i32) {
         match &mut self.app_state {
             AppState::Chat { widget } => widget.handle_scroll_delta(scroll_delta),
@@ -3,6 +3,13 @@ use crossterm::event::KeyEvent;
 
 use crate::slash_command::SlashCommand;
 
+/// Direction in which to scroll the conversation transcript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScrollDirection {
+    Up,
+    Down,
+}
+
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum AppEvent {
     CodexEvent(Event),
@@ -11,6 +18,49 @@ pub(crate) enum AppEvent {
 
     KeyEvent(KeyEvent),
 
+    /// Text received via bracketed paste. Delivered as a single event so that
+    /// embedded newlines are inserted literally rather than being replayed as
+    /// Enter key presses.
+    Paste(String),
+
+    /// An image was attached to the message being composed (e.g. by pasting
+    /// or dragging in its path).
+    AttachImage(std::path::PathBuf),
+
+    /// A non-image file was attached to the message being composed.
+    AttachFile(std::path::PathBuf),
+
+    /// A message submitted while the agent was busy. It is sent once the
+    /// current task finishes.
+    QueueMessage {
+        text: String,
+        image_paths: Vec<std::path::PathBuf>,
+        file_paths: Vec<std::path::PathBuf>,
+        mode: crate::slash_command::PromptMode,
+    },
+
+    /// A non-empty message was submitted from the composer and should be
+    /// appended to the persistent cross-session history.
+    RecordHistory(String),
+
+    /// A large paste was replaced in the composer by `placeholder`. The
+    /// content is sent alongside the next message.
+    AttachText {
+        content: String,
+        suggested_name: String,
+        placeholder: String,
+    },
+
+    /// The placeholder of the paste attached as `suggested_name` left the
+    /// composer (e.g. Ctrl+Z inlined it), so the paste is not to be sent.
+    DetachText {
+        suggested_name: String,
+    },
+
+    /// Scroll the transcript by a page without moving focus away from the
+    /// composer (Ctrl+P / Ctrl+N).
+    ScrollTranscript(ScrollDirection),
+
     /// Scroll event with a value representing the "scroll delta" as the net
     /// scroll up/down events within a short time window.
     Scroll(i32),
@@ -22,10 +72,43 @@ pub(crate) enum AppEvent {
     /// bubbling channels through layers of widgets.
     CodexOp(codex_core::protocol::Op),
 
+    /// Short informational message to show the user, e.g. confirming an
+    /// action that can be undone.
+    Notice(String),
+
     /// Latest formatted log line emitted by `tracing`.
     LatestLog(String),
 
+    /// Size of the text in the composer changed. Debounced while typing so
+    /// that e.g. a character counter can be kept up to date cheaply.
+    ComposerTextChanged {
+        chars: usize,
+        lines: usize,
+    },
+
+    /// The on-screen selection requested with `/selection`, to be inserted
+    /// into the composer. `None` when there was none to read.
+    SelectionResponse(Option<String>),
+
+    /// Edit the given composer text in the external editor (Ctrl+X Ctrl+E).
+    /// The result replaces the composer contents.
+    OpenExternalEditor(String),
+
+    /// Text was typed into the composer while it did not have input focus;
+    /// the host should give it focus. The key itself was still handled.
+    RequestFocus,
+
+    /// Stop the task the agent is working on (Esc while it is busy).
+    InterruptAgent,
+
+    /// Show detailed help for a command, requested with `/help <command>`.
+    ShowCommandHelp(SlashCommand),
+
     /// Dispatch a recognized slash command from the UI (composer) to the app
-    /// layer so it can be handled centrally.
-    DispatchCommand(SlashCommand),
+    /// layer so it can be handled centrally. `args` is whatever was typed
+    /// after the command, trimmed; empty when nothing was.
+    DispatchCommand {
+        command: SlashCommand,
+        args: String,
+    },
 }
@@ -19,4 +19,17 @@ impl AppEventSender {
             tracing::error!("failed to send event: {e}");
         }
     }
+
+    /// Send an event, reporting whether it was delivered. Fails only once
+    /// the app has stopped receiving events, e.g. while shutting down, so
+    /// the failure is logged at debug level.
+    pub(crate) fn try_send(&self, event: AppEvent) -> bool {
+        match self.app_event_tx.send(event) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::debug!("failed to send event: {e}");
+                false
+            }
+        }
+    }
 }
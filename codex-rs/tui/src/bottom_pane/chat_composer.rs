@@ -1,7 +1,21 @@
+use std::cell::Cell;
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use codex_core::config_types::DotRepeat;
+use codex_core::config_types::EscBehavior;
+use codex_core::config_types::HistoryNavKeys;
+use codex_core::config_types::OverLimitBehavior;
+use codex_core::config_types::PopupPlacement;
+use codex_core::config_types::SubmitMode;
+use codex_core::protocol::Op;
 use crossterm::event::KeyEvent;
 use ratatui::buffer::Buffer;
 use ratatui::layout::Alignment;
 use ratatui::layout::Rect;
+use ratatui::style::Modifier;
 use ratatui::style::Style;
 use ratatui::style::Stylize;
 use ratatui::text::Line;
@@ -9,47 +23,621 @@ use ratatui::widgets::BorderType;
 use ratatui::widgets::Borders;
 use ratatui::widgets::Widget;
 use ratatui::widgets::WidgetRef;
+use serde::Deserialize;
+use serde::Serialize;
+use tui_textarea::CursorMove;
 use tui_textarea::Input;
 use tui_textarea::Key;
 use tui_textarea::TextArea;
+use unicode_width::UnicodeWidthStr;
 
+use super::chat_composer_column::ColumnEdit;
+use super::chat_composer_draft::ChatComposerDraft;
+use super::chat_composer_highlight::FenceContext;
+use super::chat_composer_highlight::command_token;
+use super::chat_composer_highlight::fence_contexts;
+use super::chat_composer_highlight::is_inside_code_block;
+use super::chat_composer_highlight::line_mentions;
+use super::chat_composer_highlight::line_styles;
 use super::chat_composer_history::ChatComposerHistory;
+use super::chat_composer_jump::Jump;
+use super::chat_composer_jump::JumpKeys;
+use super::chat_composer_line_cache::LineCache;
+use super::chat_composer_template::TemplateResolver;
+use super::chat_composer_template::expand_templates;
+use super::chat_composer_text_changed::TextChangedNotifier;
+use super::chat_composer_theme::ComposerTheme;
+use super::chat_composer_undo::ChatComposerUndo;
+use super::chat_composer_undo::ComposerSnapshot;
+use super::chat_composer_wrap::WrapCache;
+use super::chat_composer_wrap::WrappedRow;
+use super::chat_composer_wrap::cursor_position;
+use super::chat_composer_wrap::graphemes;
+use super::chat_composer_wrap::next_word_end;
+use super::chat_composer_wrap::prev_word_start;
+use super::clipboard::Clipboard;
+use super::clipboard::TerminalClipboard;
 use super::command_popup::CommandPopup;
+use super::file_search_popup::FileSearchPopup;
+use super::file_watcher::FileWatcher;
+use super::image_paste::pasted_file_path;
+use super::image_paste::pasted_image_path;
+use super::parsed_prompt::ParsedPrompt;
+use super::parsed_prompt::parse_segments;
+use super::paste_sanitize::ascii_punctuation;
+use super::paste_sanitize::sanitize_pasted_text;
+use super::vim_mode::VimMode;
+use super::vim_mode::VimOutcome;
+use super::vim_mode::VimState;
 
 use crate::app_event::AppEvent;
+use crate::app_event::ScrollDirection;
 use crate::app_event_sender::AppEventSender;
+use crate::slash_command::PromptMode;
+use crate::slash_command::SlashCommand;
+use crate::slash_command::parse_prompt_mode;
+use crate::slash_command::split_command_line;
 
-/// Minimum number of visible text rows inside the textarea.
-const MIN_TEXTAREA_ROWS: usize = 1;
+/// Default minimum number of visible text rows inside the textarea.
+pub(super) const MIN_TEXTAREA_ROWS: usize = 1;
+/// Default maximum number of visible text rows; taller content scrolls inside
+/// the textarea instead of growing the composer.
+pub(super) const MAX_TEXTAREA_ROWS: usize = 10;
+/// Spaces inserted by Tab unless `ChatComposerConfig::tab_width` says
+/// otherwise.
+const DEFAULT_TAB_WIDTH: usize = 4;
 /// Rows consumed by the border.
 const BORDER_LINES: u16 = 2;
 
+/// Whether `input` is the key combination that submits in `mode`.
+fn is_submit_input(mode: SubmitMode, input: &Input) -> bool {
+    let Input {
+        key: Key::Enter,
+        shift: false,
+        alt: false,
+        ctrl,
+    } = *input
+    else {
+        return false;
+    };
+    match mode {
+        SubmitMode::EnterSends => !ctrl,
+        SubmitMode::EnterNewlineCtrlEnterSends | SubmitMode::BlankLineSends => ctrl,
+    }
+}
+
+/// Direction of a step through message history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryStep {
+    Older,
+    Newer,
+}
+
+/// The history step `input` is bound to under `keys`, if any.
+fn history_step(keys: HistoryNavKeys, input: &Input) -> Option<HistoryStep> {
+    let step = match (keys, input) {
+        (
+            HistoryNavKeys::Arrows,
+            Input {
+                key: Key::Up,
+                shift: false,
+                ..
+            },
+        )
+        | (
+            HistoryNavKeys::CtrlArrows,
+            Input {
+                key: Key::Up,
+                ctrl: true,
+                alt: false,
+                shift: false,
+            },
+        )
+        | (
+            HistoryNavKeys::AltPN,
+            Input {
+                key: Key::Char('p'),
+                ctrl: false,
+                alt: true,
+                shift: false,
+            },
+        ) => HistoryStep::Older,
+        (
+            HistoryNavKeys::Arrows,
+            Input {
+                key: Key::Down,
+                shift: false,
+                ..
+            },
+        )
+        | (
+            HistoryNavKeys::CtrlArrows,
+            Input {
+                key: Key::Down,
+                ctrl: true,
+                alt: false,
+                shift: false,
+            },
+        )
+        | (
+            HistoryNavKeys::AltPN,
+            Input {
+                key: Key::Char('n'),
+                ctrl: false,
+                alt: true,
+                shift: false,
+            },
+        ) => HistoryStep::Newer,
+        _ => return None,
+    };
+    Some(step)
+}
+
+/// Whether `input` is the submit key of `mode` with Alt held, which sends
+/// immediately even if the agent is busy.
+fn is_send_now_input(mode: SubmitMode, input: &Input) -> bool {
+    input.alt
+        && is_submit_input(
+            mode,
+            &Input {
+                alt: false,
+                ..input.clone()
+            },
+        )
+}
+
+/// Whether `input` sends the message but keeps it in the composer: Ctrl+Enter
+/// when Enter sends, Ctrl+Shift+Enter when Ctrl+Enter does.
+fn is_send_and_keep_input(mode: SubmitMode, input: &Input) -> bool {
+    let Input {
+        key: Key::Enter,
+        ctrl: true,
+        alt: false,
+        shift,
+    } = *input
+    else {
+        return false;
+    };
+    match mode {
+        SubmitMode::EnterSends => !shift,
+        SubmitMode::EnterNewlineCtrlEnterSends | SubmitMode::BlankLineSends => shift,
+    }
+}
+
+/// Key hint shown in the bottom border of the composer for `mode` and `esc`.
+fn submit_hint(mode: SubmitMode, esc: EscBehavior) -> String {
+    let (send, newline) = match mode {
+        SubmitMode::EnterSends => ("Enter to send", "Shift+Enter / Ctrl+J for newline"),
+        SubmitMode::EnterNewlineCtrlEnterSends => ("Ctrl+Enter to send", "Enter for newline"),
+        SubmitMode::BlankLineSends => ("Enter on a blank last line to send", "Enter for newline"),
+    };
+    let clear = match esc {
+        EscBehavior::DoubleClear => " | Esc Esc to clear",
+        EscBehavior::SingleClear => " | Esc to clear",
+        EscBehavior::Disabled => "",
+    };
+    format!("{send}{clear} | Ctrl+D to quit | {newline}")
+}
+
+/// Length in chars of the list marker `line` starts with, indentation and
+/// the following space included, and the marker the next item starts with:
+/// `"  - a"` gives `(4, "  - ")` and `"9. b"` gives `(3, "10. ")`.
+fn list_marker(line: &str) -> Option<(usize, String)> {
+    let indent: String = line
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let rest = &line[indent.len()..];
+    let next = if rest.starts_with("- ") || rest.starts_with("* ") {
+        rest[..2].to_string()
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || digits > 9 || !rest[digits..].starts_with(". ") {
+            return None;
+        }
+        let n: u32 = rest[..digits].parse().ok()?;
+        format!("{}. ", n + 1)
+    };
+    let len = indent.chars().count() + rest.find(' ').map_or(0, |space| space + 1);
+    Some((len, format!("{indent}{next}")))
+}
+
+/// Text of `lines` as submitted: trailing whitespace is dropped from every
+/// line outside ``` fences (where it may be meaningful) and trailing blank
+/// lines are dropped altogether.
+fn normalize_submission(lines: &[String]) -> String {
+    let mut in_fence = false;
+    let mut out: Vec<&str> = lines
+        .iter()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                line.trim_end()
+            } else if in_fence {
+                line.as_str()
+            } else {
+                line.trim_end()
+            }
+        })
+        .collect();
+    while out.last().is_some_and(|line| line.trim().is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// Text of `lines` from char position `start` up to (excluding) `end`, both
+/// given as `(row, col)`.
+fn text_between(lines: &[String], start: (usize, usize), end: (usize, usize)) -> String {
+    (start.0..=end.0)
+        .map(|row| {
+            let from = if row == start.0 { start.1 } else { 0 };
+            let line = &lines[row];
+            let to = if row == end.0 {
+                end.1
+            } else {
+                line.chars().count()
+            };
+            line.chars().skip(from).take(to - from).collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split `text` into parts of at most `max` chars, breaking after the last
+/// whitespace that fits when there is one. Whitespace at the breaks is
+/// dropped.
+fn split_message(text: &str, max: usize) -> Vec<String> {
+    let max = max.max(1);
+    let mut parts = Vec::new();
+    let mut rest: Vec<char> = text.chars().collect();
+    while rest.len() > max {
+        let brk = rest[..=max]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .filter(|&i| i > 0)
+            .unwrap_or(max);
+        let part: String = rest[..brk].iter().collect();
+        parts.push(part.trim_end().to_string());
+        let next = rest[brk..]
+            .iter()
+            .position(|c| !c.is_whitespace())
+            .map_or(rest.len(), |n| brk + n);
+        rest.drain(..next);
+    }
+    if !rest.is_empty() {
+        parts.push(rest.into_iter().collect());
+    }
+    parts
+}
+
+/// Text typed after the command token of `text`, e.g. `"gpt-4o"` for
+/// `"/model gpt-4o"`, without surrounding whitespace.
+fn command_args(text: &str) -> &str {
+    split_command_line(text.trim_start())
+        .and_then(|(_, args)| args)
+        .map_or("", str::trim)
+}
+
+/// Compact form of [`submit_hint`] for narrow terminals.
+fn short_submit_hint(mode: SubmitMode) -> &'static str {
+    match mode {
+        SubmitMode::EnterSends => "Enter send · Ctrl+D quit",
+        SubmitMode::EnterNewlineCtrlEnterSends => "Ctrl+Enter send · Ctrl+D quit",
+        SubmitMode::BlankLineSends => "Enter twice send · Ctrl+D quit",
+    }
+}
+
+/// Behavioral options for [`ChatComposer`] chosen by the embedding view.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChatComposerConfig {
+    pub(crate) submit_mode: SubmitMode,
+    /// Whether Esc clears the text, and after how many presses.
+    pub(crate) esc_behavior: EscBehavior,
+    /// Keys that step through message history.
+    pub(crate) history_nav_keys: HistoryNavKeys,
+    /// What `.` typed into the empty composer repeats.
+    pub(crate) dot_repeat: DotRepeat,
+    /// Directory searched when completing `@path` mentions. `None` disables
+    /// the file popup.
+    pub(crate) file_search_root: Option<PathBuf>,
+    /// File used to persist the unsent draft across restarts. `None`
+    /// disables draft persistence.
+    pub(crate) draft_path: Option<PathBuf>,
+    /// Enable Vim-style Normal/Insert modal editing.
+    pub(crate) vim_mode: bool,
+    /// Enable the `gg` / `G` jumps through long text outside of Vim mode.
+    pub(crate) jump_keys: bool,
+    /// Syntax-highlight code between ``` fences. Off by default since it
+    /// re-scans the whole text on every render.
+    pub(crate) highlight_code_blocks: bool,
+    /// Copy the current line's indentation onto a newline inserted inside a
+    /// ``` fence.
+    pub(crate) auto_indent: bool,
+    /// Continue `- `, `* ` and `1. ` lists on a newline outside ``` fences.
+    pub(crate) continue_lists: bool,
+    /// Spaces inserted by Tab while no popup is open; 0 inserts a tab
+    /// character. `None` uses `DEFAULT_TAB_WIDTH`.
+    pub(crate) tab_width: Option<usize>,
+    /// Pastes with more lines than this become an attachment represented by
+    /// a placeholder. `None` always inlines pastes.
+    pub(crate) paste_attachment_lines: Option<usize>,
+    /// Replace typographic quotes and dashes in pastes with ASCII.
+    pub(crate) ascii_paste_punctuation: bool,
+    /// Keep the listing used for `@path` completions between popups and
+    /// refresh it when the files under `file_search_root` change.
+    pub(crate) watch_files: bool,
+    /// Longest message, in chars, submitted as is. `None` means no limit.
+    pub(crate) max_chars: Option<usize>,
+    /// What submitting a message over `max_chars` does.
+    pub(crate) over_limit: OverLimitBehavior,
+    /// Show the word and estimated token counts of the draft in the border.
+    pub(crate) show_word_count: bool,
+    /// Model names offered when completing the argument of `/model`.
+    pub(crate) models: Vec<String>,
+    /// Report submissions as `InputResult::SubmittedParsed` instead of
+    /// `InputResult::Submitted`.
+    pub(crate) parsed_submissions: bool,
+    /// Side of the textarea the command and file popups open on.
+    pub(crate) popup_placement: PopupPlacement,
+}
+
 /// Result returned when the user interacts with the text area.
 pub enum InputResult {
-    Submitted(String),
+    /// The message text and any images and files attached to it. A mode
+    /// prefix such as `/ask` is stripped from `text` and reported as `mode`.
+    Submitted {
+        text: String,
+        image_paths: Vec<PathBuf>,
+        file_paths: Vec<PathBuf>,
+        mode: PromptMode,
+        /// The text was also left in the composer to be reused.
+        keep_draft: bool,
+    },
+    /// Same as `Submitted`, with the text also split into segments. Only
+    /// returned when `ChatComposerConfig::parsed_submissions` is set.
+    SubmittedParsed(ParsedPrompt),
+    /// The agent was busy, so the message was handed to the app (via
+    /// `AppEvent::QueueMessage`) to be sent once the current task finishes.
+    Queued(String),
+    /// The action needed an event to reach the app, which is no longer
+    /// receiving them (e.g. during shutdown). The composer is left as it was
+    /// so nothing is lost.
+    Undelivered,
     None,
 }
 
+/// What `ChatComposer::snapshot` captures, so an unsent message can be
+/// saved (as the draft that survives restarts) and put back by
+/// `ChatComposer::restore`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ComposerState {
+    pub text: String,
+    /// Cursor as `(line, column)`, in chars.
+    pub cursor: (usize, usize),
+    /// Whether one Esc had already been pressed towards clearing the text.
+    pub escape_armed: bool,
+    /// Whether a popup was showing. Popups follow from the text, so this
+    /// only matters when one had been dismissed.
+    pub popup_open: bool,
+}
+
+/// Check or rewrite a message before it is submitted; see
+/// [`ChatComposer::set_submit_filter`].
+pub(crate) type SubmitFilter = Box<dyn Fn(String) -> Result<String, String>>;
+
+/// Count the tokens of a draft for the border; see
+/// [`ChatComposer::set_token_estimator`].
+pub(crate) type TokenEstimator = Box<dyn Fn(&str) -> usize>;
+
+/// The buffer hidden by Ctrl+G, with the undo history and attachments that
+/// belong to it.
+struct StashedBuffer {
+    snapshot: ComposerSnapshot,
+    undo: ChatComposerUndo,
+    attachments: Vec<Attachment>,
+}
+
+/// A large paste replaced in the text by its placeholder.
+struct PastedText {
+    placeholder: String,
+    suggested_name: String,
+    content: String,
+    /// Whether the app holds it as an attachment, i.e. the placeholder was
+    /// in the text when last checked.
+    attached: bool,
+}
+
+/// What rendering needs to know about one line of the text. It only depends
+/// on the line and its [`FenceContext`] (the settings that enable styling
+/// are fixed for the composer's lifetime), so it is cached per line.
+struct LineRender {
+    chars: usize,
+    words: usize,
+    /// Syntax highlighting, empty when disabled or outside a fence.
+    styles: Vec<Style>,
+    /// `@path` mentions, empty when mentions are disabled or in a fence.
+    mentions: Vec<(usize, usize)>,
+}
+
+/// A file pasted (or dragged in) as an attachment for the next message.
+#[derive(Clone)]
+enum Attachment {
+    Image(PathBuf),
+    File(PathBuf),
+}
+
 pub(crate) struct ChatComposer<'a> {
     textarea: TextArea<'a>,
     command_popup: Option<CommandPopup>,
+    /// Slash commands registered by the host; `None` offers the built-in
+    /// ones.
+    commands: Option<Vec<SlashCommand>>,
+    file_popup: Option<FileSearchPopup>,
+    /// Watches `file_search_root` when `watch_files` is enabled.
+    file_watcher: Option<FileWatcher>,
+    /// Listing of `file_search_root` kept from the last file popup while
+    /// the root is watched.
+    file_index: Option<Vec<String>>,
+    clipboard: Box<dyn Clipboard>,
+    submit_filter: Option<SubmitFilter>,
+    /// Counts tokens for `show_word_count`; about one per four chars without
+    /// one.
+    token_estimator: Option<TokenEstimator>,
+    /// Expands `:name:` tokens on submit; none are expanded without one.
+    template_resolver: Option<Box<dyn TemplateResolver>>,
     app_event_tx: AppEventSender,
     history: ChatComposerHistory,
+    config: ChatComposerConfig,
+    theme: ComposerTheme,
+    undo: ChatComposerUndo,
+    draft: Option<ChatComposerDraft>,
+    /// Attachments for the next message, in the order they were added.
+    attachments: Vec<Attachment>,
+    /// Number of pastes turned into attachments, used to name them.
+    pasted_text_count: usize,
+    /// Large pastes not yet sent with a message.
+    pasted_texts: Vec<PastedText>,
+    text_changed: TextChangedNotifier,
+    /// Present when Vim keybindings are enabled.
+    vim: Option<VimState>,
+    /// Active Ctrl+V column edit, if any.
+    column_edit: Option<ColumnEdit>,
+    /// Whether the last key was Ctrl+X, the first half of Ctrl+X Ctrl+E.
+    ctrl_x_pending: bool,
+    has_input_focus: bool,
+    /// When false (e.g. while the agent is responding) text can still be
+    /// edited but not submitted.
+    enabled: bool,
+    /// Whether the agent is working on a task. Submitting while busy queues
+    /// the message; Alt plus the submit key interrupts and sends instead.
+    busy: bool,
+    /// Number of messages waiting to be sent, shown in the border.
+    queued_messages: usize,
+    /// Set after a first Esc press; a second consecutive Esc clears the
+    /// textarea.
+    escape_armed: bool,
+    /// Text from before a Ctrl+R history search began. `Some` while the
+    /// search is active; restored if the search is cancelled.
+    search_original: Option<ComposerSnapshot>,
+    /// `gg` / `G` jumps for long text outside of Vim mode.
+    jump_keys: JumpKeys,
+    /// Whether the last key typed a character, which keeps `g` and `G`
+    /// from starting a jump in the middle of a word.
+    last_input_typed: bool,
+    /// Text at the moment Esc dismissed the popups. They stay hidden until
+    /// the text changes.
+    popups_dismissed_for: Option<Vec<String>>,
+    /// Set when the text was started with `//`, which leaves a single `/`
+    /// that does not start a command. Cleared once the text no longer starts
+    /// with `/`.
+    literal_slash: bool,
+    /// Last command dispatched from the popup, with its arguments, for
+    /// `DotRepeat::Command`.
+    last_command: Option<(SlashCommand, String)>,
+    /// The buffer Ctrl+G swaps in; `None` until the first swap, when it is
+    /// empty.
+    stash: Option<StashedBuffer>,
+    /// Whether the scratch buffer rather than the draft is shown.
+    showing_scratch: bool,
+    /// First visual row shown in the textarea. Updated while rendering so
+    /// the cursor stays visible when the content is taller than the area.
+    scroll_top: Cell<usize>,
+    /// Width the text was last wrapped to when rendering; 0 before the first
+    /// render. Used to find the last visual row for history navigation.
+    wrap_width: Cell<u16>,
+    /// Rows of the text as last wrapped, so that an edit only wraps the
+    /// lines it changed again.
+    wrap_cache: RefCell<WrapCache>,
+    /// Per-line counts and styles as last rendered, so that an edit only
+    /// scans the lines it changed again.
+    line_cache: RefCell<LineCache<FenceContext, LineRender>>,
+    /// Text rows visible in the textarea at the last render. PageUp /
+    /// PageDown move by this many rows.
+    viewport_rows: Cell<u16>,
+    /// Area and result of the last `calculate_required_height` call.
+    measured_height: Cell<Option<(Rect, u16)>>,
+    /// Fewest and most text rows the composer shows; see `set_row_limits`.
+    min_rows: usize,
+    max_rows: usize,
 }
 
 impl ChatComposer<'_> {
-    pub fn new(has_input_focus: bool, app_event_tx: AppEventSender) -> Self {
+    pub fn new(
+        has_input_focus: bool,
+        app_event_tx: AppEventSender,
+        config: ChatComposerConfig,
+        theme: ComposerTheme,
+    ) -> Self {
         let mut textarea = TextArea::default();
         textarea.set_placeholder_text("send a message");
         textarea.set_cursor_line_style(ratatui::style::Style::default());
 
+        let draft = config.draft_path.clone().map(ChatComposerDraft::new);
+        let restored = draft.as_ref().and_then(ChatComposerDraft::restored);
+
+        let file_watcher = match &config.file_search_root {
+            Some(root) if config.watch_files => Some(FileWatcher::spawn(root.clone())),
+            _ => None,
+        };
+
         let mut this = Self {
             textarea,
             command_popup: None,
+            commands: None,
+            file_popup: None,
+            file_watcher,
+            file_index: None,
+            clipboard: Box::new(TerminalClipboard),
+            submit_filter: None,
+            token_estimator: None,
+            template_resolver: None,
+            text_changed: TextChangedNotifier::new(app_event_tx.clone()),
             app_event_tx,
             history: ChatComposerHistory::new(),
+            config,
+            theme,
+            undo: ChatComposerUndo::new(),
+            draft,
+            attachments: Vec::new(),
+            pasted_text_count: 0,
+            pasted_texts: Vec::new(),
+            vim: None,
+            column_edit: None,
+            ctrl_x_pending: false,
+            has_input_focus,
+            enabled: true,
+            busy: false,
+            queued_messages: 0,
+            escape_armed: false,
+            search_original: None,
+            jump_keys: JumpKeys::new(),
+            last_input_typed: false,
+            popups_dismissed_for: None,
+            literal_slash: false,
+            last_command: None,
+            stash: None,
+            showing_scratch: false,
+            scroll_top: Cell::new(0),
+            wrap_width: Cell::new(0),
+            wrap_cache: RefCell::new(WrapCache::default()),
+            line_cache: RefCell::new(LineCache::default()),
+            viewport_rows: Cell::new(MAX_TEXTAREA_ROWS as u16),
+            measured_height: Cell::new(None),
+            min_rows: MIN_TEXTAREA_ROWS,
+            max_rows: MAX_TEXTAREA_ROWS,
         };
-        this.update_border(has_input_focus);
+        if this.config.vim_mode {
+            this.vim = Some(VimState::new());
+        }
+        this.update_cursor_style();
+        this.update_border();
+        if let Some(state) = restored {
+            // Restoring must not rewrite the draft it is restored from.
+            let draft = this.draft.take();
+            this.restore(state);
+            this.draft = draft;
+        }
         this
     }
 
@@ -59,6 +647,36 @@ impl ChatComposer<'_> {
         self.history.set_metadata(log_id, entry_count);
     }
 
+    /// Replace the text with what was saved in the external editor opened by
+    /// Ctrl+X Ctrl+E. The replacement can be undone. Returns true when the
+    /// text changed.
+    pub(crate) fn on_external_editor_response(&mut self, text: String) -> bool {
+        if text == self.text() {
+            return false;
+        }
+        self.undo.push(&self.textarea);
+        self.set_text(&text);
+        true
+    }
+
+    /// Integrate the response to a `/selection` request: the selected text
+    /// is inserted at the cursor, or a notice explains that there was none.
+    /// Returns true when the text changed.
+    pub(crate) fn on_selection_response(&mut self, selection: Option<String>) -> bool {
+        let Some(text) = selection else {
+            self.app_event_tx.send(AppEvent::Notice(
+                "No selection to insert (reading it needs wl-paste, xclip or xsel).".to_string(),
+            ));
+            return false;
+        };
+        self.undo.push(&self.textarea);
+        self.textarea.insert_str(text);
+        self.escape_armed = false;
+        self.sync_popups();
+        self.on_text_updated();
+        true
+    }
+
     /// Integrate an asynchronous response to an on-demand history lookup. If
     /// the entry is present and the offset matches the current cursor we
     /// immediately populate the textarea.
@@ -68,27 +686,385 @@ impl ChatComposer<'_> {
         offset: usize,
         entry: Option<String>,
     ) -> bool {
-        self.history
-            .on_entry_response(log_id, offset, entry, &mut self.textarea)
+        let mut updated = self
+            .history
+            .on_entry_response(log_id, offset, entry, &mut self.textarea);
+        if self.history.is_searching() {
+            updated |= self.history.on_search_entry_response(
+                offset,
+                &mut self.textarea,
+                &self.app_event_tx,
+            );
+            self.update_border();
+        }
+        if updated {
+            self.on_text_updated();
+        }
+        updated
+    }
+
+    /// Text shown while the composer is empty. Does not affect typed text.
+    pub(crate) fn set_placeholder(&mut self, text: impl Into<String>) {
+        self.textarea.set_placeholder_text(text);
+    }
+
+    /// Run `filter` on the text of every non-empty message before it is
+    /// submitted, mode prefix included. `Ok` submits the returned text
+    /// instead; `Err` shows the message as a notice and keeps the text in
+    /// the composer.
+    pub(crate) fn set_submit_filter(&mut self, filter: SubmitFilter) {
+        self.submit_filter = Some(filter);
+    }
+
+    /// Count tokens with `estimator`, e.g. the model's tokenizer, instead of
+    /// estimating them from the length of the draft. It runs on every render
+    /// while the counts are shown, so it should be cheap.
+    pub(crate) fn set_token_estimator(&mut self, estimator: TokenEstimator) {
+        self.token_estimator = Some(estimator);
+    }
+
+    /// Expand `:name:` tokens with `resolver` when a message is submitted,
+    /// before the submit filter runs. Tokens it does not know are left as
+    /// typed, with a notice.
+    pub(crate) fn set_template_resolver(&mut self, resolver: Box<dyn TemplateResolver>) {
+        self.template_resolver = Some(resolver);
+    }
+
+    /// Bound the number of text rows the composer shows: it grows with its
+    /// content from `min` rows up to `max`, then scrolls. `max` is raised to
+    /// `min` if smaller, and both are at least 1.
+    pub(crate) fn set_row_limits(&mut self, min: usize, max: usize) {
+        self.min_rows = min.max(1);
+        self.max_rows = max.max(self.min_rows);
+    }
+
+    /// Replace the slash commands offered by the popup, e.g. with commands
+    /// discovered at startup. Commands that are not registered are neither
+    /// completed nor dispatched; typing them submits the text as a prompt.
+    pub(crate) fn set_commands(&mut self, commands: Vec<SlashCommand>) {
+        self.commands = Some(commands);
+        // Recreate a visible popup with the new commands.
+        self.command_popup = None;
+        self.sync_popups();
     }
 
     pub fn set_input_focus(&mut self, has_focus: bool) {
-        self.update_border(has_focus);
+        self.has_input_focus = has_focus;
+        self.update_cursor_style();
+        self.update_border();
+    }
+
+    /// Only draw the cursor while focused, so that an unfocused composer
+    /// does not look like it is accepting input.
+    fn update_cursor_style(&mut self) {
+        let style = if self.has_input_focus {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        self.textarea.set_cursor_style(style);
+    }
+
+    /// Allow or block submissions. A disabled composer keeps accepting edits
+    /// (and Esc) but ignores the submit key, and is drawn dimmed. Unless
+    /// configured otherwise, a running task leaves the composer enabled and
+    /// queues messages instead; see [`Self::set_busy`].
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.update_border();
+    }
+
+    /// Tell the composer whether the agent is working on a task.
+    pub(crate) fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+    }
+
+    /// Number of queued messages to indicate in the border title.
+    pub(crate) fn set_queued_messages(&mut self, count: usize) {
+        self.queued_messages = count;
+        self.update_border();
     }
 
     /// Handle a key event coming from the main UI.
     pub fn handle_key_event(&mut self, key_event: KeyEvent) -> (InputResult, bool) {
-        let result = match self.command_popup {
-            Some(_) => self.handle_key_event_with_popup(key_event),
-            None => self.handle_key_event_without_popup(key_event),
+        let typed = matches!(
+            key_event.into(),
+            Input {
+                key: Key::Char(_),
+                ctrl: false,
+                alt: false,
+                ..
+            }
+        );
+        if typed && !self.has_input_focus {
+            self.app_event_tx.send(AppEvent::RequestFocus);
+        }
+        let can_start_jump = !std::mem::replace(&mut self.last_input_typed, typed);
+        self.dispatch_key_event(key_event, can_start_jump)
+    }
+
+    fn dispatch_key_event(
+        &mut self,
+        key_event: KeyEvent,
+        can_start_jump: bool,
+    ) -> (InputResult, bool) {
+        if let Some(result) = self.handle_key_event_with_search(key_event.into()) {
+            self.update_border();
+            self.on_text_updated();
+            return result;
+        }
+
+        if let Some(result) = self
+            .handle_external_editor_keys(key_event.into())
+            .or_else(|| self.handle_undo_redo(key_event.into()))
+            .or_else(|| self.handle_column_edit(key_event.into()))
+            .or_else(|| self.handle_vim_input(key_event.into()))
+            .or_else(|| self.handle_jump_keys(key_event.into(), can_start_jump))
+        {
+            self.sync_popups();
+            self.on_text_updated();
+            return result;
+        }
+
+        let result = if self.command_popup.is_some() {
+            self.handle_key_event_with_popup(key_event)
+        } else if self.file_popup.is_some() {
+            self.handle_key_event_with_file_popup(key_event)
+        } else {
+            self.handle_key_event_without_popup(key_event)
         };
 
-        // Update (or hide/show) popup after processing the key.
-        self.sync_command_popup();
+        // Update (or hide/show) popups after processing the key.
+        self.sync_popups();
+        self.on_text_updated();
 
         result
     }
 
+    /// Capture the text, cursor and transient key state for
+    /// [`ChatComposer::restore`]. Attachments are not included.
+    pub(crate) fn snapshot(&self) -> ComposerState {
+        ComposerState {
+            text: self.text(),
+            cursor: self.textarea.cursor(),
+            escape_armed: self.escape_armed,
+            popup_open: self.is_popup_visible(),
+        }
+    }
+
+    /// Put back the state captured by [`ChatComposer::snapshot`]. A cursor
+    /// beyond the text is clamped to it.
+    pub(crate) fn restore(&mut self, state: ComposerState) {
+        self.set_text(&state.text);
+        let (line, col) = state.cursor;
+        self.textarea
+            .move_cursor(CursorMove::Jump(line as u16, col as u16));
+        self.sync_popups();
+        if !state.popup_open && self.is_popup_visible() {
+            self.dismiss_popups();
+        }
+        self.escape_armed = state.escape_armed;
+    }
+
+    /// Whether the composer holds text that has not been sent, ignoring
+    /// whitespace. Lets the app ask before quitting over a draft.
+    pub(crate) fn has_unsent_content(&self) -> bool {
+        self.textarea
+            .lines()
+            .iter()
+            .any(|line| !line.trim().is_empty())
+    }
+
+    /// Current contents of the composer, lines joined with `\n`.
+    pub(crate) fn text(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    /// Replace the contents of the composer with `text`, leaving the cursor
+    /// at the end, and show or hide popups to match the new text.
+    pub(crate) fn set_text(&mut self, text: &str) {
+        self.textarea.select_all();
+        self.textarea.cut();
+        let _ = self.textarea.insert_str(text);
+        self.escape_armed = false;
+        self.literal_slash = false;
+        self.sync_popups();
+        self.on_text_updated();
+    }
+
+    /// Insert the payload of a bracketed paste verbatim. Newlines are kept as
+    /// literal line breaks and never trigger a submission. Returns true when a
+    /// redraw is needed.
+    pub fn handle_paste(&mut self, pasted: String) -> bool {
+        if pasted.is_empty() {
+            return false;
+        }
+        if let Some(path) = pasted_image_path(&pasted) {
+            self.app_event_tx.send(AppEvent::AttachImage(path.clone()));
+            self.attach(Attachment::Image(path));
+            return true;
+        }
+        if let Some(path) = pasted_file_path(&pasted) {
+            self.app_event_tx.send(AppEvent::AttachFile(path.clone()));
+            self.attach(Attachment::File(path));
+            return true;
+        }
+        let mut normalized =
+            sanitize_pasted_text(&pasted.replace("\r\n", "\n").replace('\r', "\n"));
+        if self.config.ascii_paste_punctuation {
+            normalized = ascii_punctuation(&normalized);
+        }
+        if normalized.is_empty() {
+            return false;
+        }
+        if self.textarea.is_selecting() {
+            // Pasted text replaces the selection, undoably.
+            self.undo.push(&self.textarea);
+            self.textarea.cut();
+        }
+        let line_count = normalized.lines().count();
+        if self
+            .config
+            .paste_attachment_lines
+            .is_some_and(|max| line_count > max)
+        {
+            self.attach_pasted_text(normalized, line_count);
+            return true;
+        }
+        self.escape_armed = false;
+        self.last_input_typed = false;
+        let _ = self.textarea.insert_str(normalized);
+        self.sync_popups();
+        self.on_text_updated();
+        true
+    }
+
+    /// Handle keys while a Ctrl+R history search is active. Printable keys
+    /// edit the query, Ctrl+R finds an older match, Enter accepts and Esc
+    /// cancels. Any other key accepts the match and returns `None` so it is
+    /// handled as usual.
+    fn handle_key_event_with_search(&mut self, input: Input) -> Option<(InputResult, bool)> {
+        let original = self.search_original.as_ref()?;
+        match input {
+            Input {
+                key: Key::Char('r'),
+                ctrl: true,
+                alt: false,
+                ..
+            } => {
+                self.history
+                    .search_older(&mut self.textarea, &self.app_event_tx);
+            }
+            Input {
+                key: Key::Char(c),
+                ctrl: false,
+                alt: false,
+                ..
+            } => {
+                let mut query = self.history.search_query().unwrap_or_default().to_string();
+                query.push(c);
+                self.history
+                    .set_search_query(query, &mut self.textarea, &self.app_event_tx);
+            }
+            Input {
+                key: Key::Backspace,
+                ..
+            } => {
+                let mut query = self.history.search_query().unwrap_or_default().to_string();
+                query.pop();
+                if query.is_empty() {
+                    original.apply(&mut self.textarea);
+                }
+                self.history
+                    .set_search_query(query, &mut self.textarea, &self.app_event_tx);
+            }
+            Input { key: Key::Esc, .. } => {
+                original.apply(&mut self.textarea);
+                self.history.cancel_search();
+                self.search_original = None;
+            }
+            Input {
+                key: Key::Enter, ..
+            } => self.accept_history_search(),
+            _ => {
+                self.accept_history_search();
+                self.update_border();
+                return None;
+            }
+        }
+        Some((InputResult::None, true))
+    }
+
+    /// Keep the previewed history entry, making the search undoable.
+    fn accept_history_search(&mut self) {
+        self.history.accept_search();
+        if let Some(original) = self.search_original.take() {
+            if original.lines() != self.textarea.lines() {
+                self.undo.push_snapshot(original);
+            }
+        }
+    }
+
+    /// Attach a file to the next submission and show it as a chip in the
+    /// border.
+    fn attach(&mut self, attachment: Attachment) {
+        self.attachments.push(attachment);
+        self.update_border();
+    }
+
+    /// Insert a placeholder for a large paste, which is sent as an
+    /// attachment while the placeholder is in the text. Ctrl+Z first inlines
+    /// the paste instead, then goes back to the text from before it.
+    fn attach_pasted_text(&mut self, content: String, line_count: usize) {
+        let before = ComposerSnapshot::capture(&self.textarea);
+        let _ = self.textarea.insert_str(&content);
+        let inlined = ComposerSnapshot::capture(&self.textarea);
+        before.apply(&mut self.textarea);
+
+        let placeholder = format!("[pasted {line_count} lines]");
+        let _ = self.textarea.insert_str(&placeholder);
+        self.undo.push_snapshot(before);
+        self.undo.push_snapshot(inlined);
+
+        self.pasted_text_count += 1;
+        self.pasted_texts.push(PastedText {
+            placeholder,
+            suggested_name: format!("paste-{}.txt", self.pasted_text_count),
+            content,
+            attached: false,
+        });
+        self.escape_armed = false;
+        self.sync_popups();
+        self.on_text_updated();
+    }
+
+    /// Attach the pastes whose placeholder is in the text and withdraw those
+    /// whose placeholder is gone, e.g. because Ctrl+Z inlined it.
+    fn sync_pasted_texts(&mut self) {
+        if self.pasted_texts.is_empty() {
+            return;
+        }
+        let text = self.text();
+        for pasted in &mut self.pasted_texts {
+            let present = text.contains(&pasted.placeholder);
+            if present == pasted.attached {
+                continue;
+            }
+            pasted.attached = present;
+            if present {
+                self.app_event_tx.send(AppEvent::AttachText {
+                    content: pasted.content.clone(),
+                    suggested_name: pasted.suggested_name.clone(),
+                    placeholder: pasted.placeholder.clone(),
+                });
+            } else {
+                self.app_event_tx.send(AppEvent::DetachText {
+                    suggested_name: pasted.suggested_name.clone(),
+                });
+            }
+        }
+    }
+
     /// Handle key event when the slash-command popup is visible.
     fn handle_key_event_with_popup(&mut self, key_event: KeyEvent) -> (InputResult, bool) {
         let Some(popup) = self.command_popup.as_mut() else {
@@ -97,6 +1073,10 @@ impl ChatComposer<'_> {
         };
 
         match key_event.into() {
+            Input { key: Key::Esc, .. } => {
+                self.dismiss_popups();
+                (InputResult::None, true)
+            }
             Input { key: Key::Up, .. } => {
                 popup.move_up();
                 (InputResult::None, true)
@@ -106,7 +1086,22 @@ impl ChatComposer<'_> {
                 (InputResult::None, true)
             }
             Input { key: Key::Tab, .. } => {
-                if let Some(cmd) = popup.selected_command() {
+                if let (Some(cmd), Some(arg)) =
+                    (popup.selected_command(), popup.selected_argument())
+                {
+                    let completed = format!("/{} {arg}", cmd.command());
+                    if completed != self.text() {
+                        self.undo.push(&self.textarea);
+                        self.set_text(&completed);
+                    }
+                } else if let Some(prefix) = popup.common_prefix() {
+                    // Like shell completion: extend to what all matches
+                    // share first, then cycle on subsequent presses.
+                    self.undo.push(&self.textarea);
+                    self.set_text(&format!("/{prefix}"));
+                } else if popup.is_ambiguous() {
+                    popup.move_down();
+                } else if let Some(cmd) = popup.selected_command() {
                     let first_line = self
                         .textarea
                         .lines()
@@ -114,14 +1109,13 @@ impl ChatComposer<'_> {
                         .map(|s| s.as_str())
                         .unwrap_or("");
 
-                    let starts_with_cmd = first_line
-                        .trim_start()
-                        .starts_with(&format!("/{}", cmd.command()));
+                    let starts_with_cmd = split_command_line(first_line.trim_start())
+                        .is_some_and(|(name, _)| name == cmd.command());
 
                     if !starts_with_cmd {
-                        self.textarea.select_all();
-                        self.textarea.cut();
-                        let _ = self.textarea.insert_str(format!("/{} ", cmd.command()));
+                        let completed = format!("/{} ", cmd.command());
+                        self.undo.push(&self.textarea);
+                        self.set_text(&completed);
                     }
                 }
                 (InputResult::None, true)
@@ -132,191 +1126,4331 @@ impl ChatComposer<'_> {
                 alt: false,
                 ctrl: false,
             } => {
-                if let Some(cmd) = popup.selected_command() {
+                if let Some(mode_cmd) = popup
+                    .selected_command()
+                    .filter(|cmd| cmd.prompt_mode().is_some())
+                {
+                    // Mode prefixes are submitted with the prompt that
+                    // follows them; without one, complete the prefix.
+                    let completed = format!("/{} ", mode_cmd.command());
+                    if parse_prompt_mode(&self.text()).0 != PromptMode::Default {
+                        self.command_popup = None;
+                        return self.submit(false);
+                    }
+                    if completed != self.text() {
+                        self.undo.push(&self.textarea);
+                        self.set_text(&completed);
+                    }
+                    return (InputResult::None, true);
+                }
+                // The highlighted completion, if any, stands for the argument
+                // being typed.
+                let args = popup.selected_argument().map_or_else(
+                    || command_args(&self.textarea.lines().join("\n")).to_string(),
+                    str::to_string,
+                );
+                if popup.selected_command() == Some(&SlashCommand::Help) {
+                    // `/help <command>` shows help instead of running the
+                    // command; plain `/help` is dispatched below.
+                    let name = args.split_whitespace().next();
+                    if let Some(cmd) = name.and_then(SlashCommand::lookup) {
+                        if !self.app_event_tx.try_send(AppEvent::ShowCommandHelp(cmd)) {
+                            return (InputResult::Undelivered, true);
+                        }
+                        self.set_text("");
+                        self.command_popup = None;
+                        return (InputResult::None, true);
+                    }
+                }
+                if popup.selected_command() == Some(&SlashCommand::Code) {
+                    let language = args.split_whitespace().next().unwrap_or_default();
+                    self.insert_code_fence(language);
+                    return (InputResult::None, true);
+                }
+                if let Some(&command) = popup.selected_command() {
                     // Send command to the app layer.
-                    self.app_event_tx.send(AppEvent::DispatchCommand(*cmd));
+                    self.last_command = Some((command, args.clone()));
+                    let event = AppEvent::DispatchCommand { command, args };
+                    if !self.app_event_tx.try_send(event) {
+                        return (InputResult::Undelivered, true);
+                    }
 
                     // Clear textarea so no residual text remains.
-                    self.textarea.select_all();
-                    self.textarea.cut();
+                    self.set_text("");
 
                     // Hide popup since the command has been dispatched.
                     self.command_popup = None;
                     return (InputResult::None, true);
                 }
-                // Fallback to default newline handling if no command selected.
-                self.handle_key_event_without_popup(key_event)
+                // Nothing matches, so this is not a command: send the text
+                // like any other message.
+                self.command_popup = None;
+                self.submit(false)
             }
             input => self.handle_input_basic(input),
         }
     }
 
-    /// Handle key event when no popup is visible.
-    fn handle_key_event_without_popup(&mut self, key_event: KeyEvent) -> (InputResult, bool) {
-        let input: Input = key_event.into();
-        match input {
-            // -------------------------------------------------------------
-            // History navigation (Up / Down) – only when the composer is not
-            // empty or when the cursor is at the correct position, to avoid
-            // interfering with normal cursor movement.
-            // -------------------------------------------------------------
+    /// Replace the `/code` command with an empty fenced block for
+    /// `language`, leaving the cursor on the blank line inside it.
+    fn insert_code_fence(&mut self, language: &str) {
+        self.undo.push(&self.textarea);
+        self.textarea.select_all();
+        self.textarea.cut();
+        self.textarea.insert_str(format!("```{language}\n\n```"));
+        self.textarea.move_cursor(CursorMove::Jump(1, 0));
+        self.escape_armed = false;
+        self.sync_popups();
+        self.on_text_updated();
+    }
+
+    /// Handle key event when the `@path` completion popup is visible.
+    fn handle_key_event_with_file_popup(&mut self, key_event: KeyEvent) -> (InputResult, bool) {
+        let Some(popup) = self.file_popup.as_mut() else {
+            tracing::error!("handle_key_event_with_file_popup called without an active popup");
+            return (InputResult::None, false);
+        };
+
+        match key_event.into() {
+            Input { key: Key::Esc, .. } => {
+                self.dismiss_popups();
+                (InputResult::None, true)
+            }
             Input { key: Key::Up, .. } => {
-                if self.history.should_handle_navigation(&self.textarea) {
-                    let consumed = self
-                        .history
-                        .navigate_up(&mut self.textarea, &self.app_event_tx);
-                    if consumed {
-                        return (InputResult::None, true);
-                    }
-                }
-                self.handle_input_basic(input)
+                popup.move_up();
+                (InputResult::None, true)
             }
             Input { key: Key::Down, .. } => {
-                if self.history.should_handle_navigation(&self.textarea) {
-                    let consumed = self
-                        .history
-                        .navigate_down(&mut self.textarea, &self.app_event_tx);
-                    if consumed {
-                        return (InputResult::None, true);
-                    }
-                }
-                self.handle_input_basic(input)
+                popup.move_down();
+                (InputResult::None, true)
             }
-            Input {
+            Input { key: Key::Tab, .. }
+            | Input {
                 key: Key::Enter,
                 shift: false,
                 alt: false,
                 ctrl: false,
-            } => {
-                let text = self.textarea.lines().join("\n");
-                self.textarea.select_all();
-                self.textarea.cut();
-
-                if text.is_empty() {
-                    (InputResult::None, true)
-                } else {
-                    self.history.record_local_submission(&text);
-                    (InputResult::Submitted(text), true)
-                }
-            }
-            Input {
-                key: Key::Enter, ..
-            }
-            | Input {
-                key: Key::Char('j'),
-                ctrl: true,
-                alt: false,
-                shift: false,
-            } => {
-                self.textarea.insert_newline();
+            } if popup.selected_path().is_some() => {
+                let path = popup.selected_path().unwrap_or_default().to_string();
+                self.insert_mention(&path);
+                self.close_file_popup();
                 (InputResult::None, true)
             }
-            input => self.handle_input_basic(input),
+            _ => self.handle_key_event_without_popup(key_event),
         }
     }
 
-    /// Handle generic Input events that modify the textarea content.
-    fn handle_input_basic(&mut self, input: Input) -> (InputResult, bool) {
-        self.textarea.input(input);
-        (InputResult::None, true)
+    /// Replace the `@` token under the cursor with `@{path} `.
+    fn insert_mention(&mut self, path: &str) {
+        let Some((start, end)) = self.mention_token_range() else {
+            return;
+        };
+        self.undo.push(&self.textarea);
+        let (row, _) = self.textarea.cursor();
+        self.textarea
+            .move_cursor(CursorMove::Jump(row as u16, start as u16));
+        self.textarea.delete_str(end - start);
+        let _ = self.textarea.insert_str(format!("@{path} "));
     }
 
-    /// Synchronize `self.command_popup` with the current text in the
-    /// textarea. This must be called after every modification that can change
-    /// the text so the popup is shown/updated/hidden as appropriate.
-    fn sync_command_popup(&mut self) {
-        // Inspect only the first line to decide whether to show the popup. In
-        // the common case (no leading slash) we avoid copying the entire
-        // textarea contents.
-        let first_line = self
-            .textarea
+    /// Char range `[start, end)` on the cursor row of the whitespace-delimited
+    /// token under the cursor, if that token starts with `@`.
+    fn mention_token_range(&self) -> Option<(usize, usize)> {
+        let (row, col) = self.textarea.cursor();
+        let line: Vec<char> = self.textarea.lines().get(row)?.chars().collect();
+        let start = line[..col]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |idx| idx + 1);
+        if line.get(start) != Some(&'@') {
+            return None;
+        }
+        let end = line[col..]
+            .iter()
+            .position(|c| c.is_whitespace())
+            .map_or(line.len(), |idx| col + idx);
+        Some((start, end))
+    }
+
+    /// Handle key event when no popup is visible.
+    fn handle_key_event_without_popup(&mut self, key_event: KeyEvent) -> (InputResult, bool) {
+        let input: Input = key_event.into();
+        if !matches!(input, Input { key: Key::Esc, .. }) {
+            self.escape_armed = false;
+        }
+        match input {
+            // -------------------------------------------------------------
+            // Ctrl+P / Ctrl+N page through the transcript while the draft
+            // stays in the composer.
+            // -------------------------------------------------------------
+            Input {
+                key: Key::Char(c @ ('p' | 'n')),
+                ctrl: true,
+                alt: false,
+                ..
+            } => {
+                let direction = if c == 'p' {
+                    ScrollDirection::Up
+                } else {
+                    ScrollDirection::Down
+                };
+                self.app_event_tx
+                    .send(AppEvent::ScrollTranscript(direction));
+                (InputResult::None, false)
+            }
+            // -------------------------------------------------------------
+            // Ctrl+G swaps the draft with a scratch buffer, e.g. to send a
+            // quick message without losing a longer one.
+            // -------------------------------------------------------------
+            Input {
+                key: Key::Char('g'),
+                ctrl: true,
+                alt: false,
+                ..
+            } => {
+                self.swap_scratch();
+                (InputResult::None, true)
+            }
+            // -------------------------------------------------------------
+            // `.` in the empty composer repeats the last command or message
+            // when `dot_repeat` is set.
+            // -------------------------------------------------------------
+            Input {
+                key: Key::Char('.'),
+                ctrl: false,
+                alt: false,
+                ..
+            } if self.can_repeat() => self.repeat_last_action(),
+            // -------------------------------------------------------------
+            // Alt+Up recalls the last message sent in this session in one
+            // step, replacing the text undoably.
+            // -------------------------------------------------------------
+            Input {
+                key: Key::Up,
+                ctrl: false,
+                alt: true,
+                shift: false,
+            } => {
+                let Some(last) = self.history.last_local_submission().map(str::to_string) else {
+                    return (InputResult::None, false);
+                };
+                if last != self.text() {
+                    self.undo.push(&self.textarea);
+                    self.set_text(&last);
+                }
+                self.history.stop_browsing();
+                (InputResult::None, true)
+            }
+            // -------------------------------------------------------------
+            // Esc interrupts the agent while it is working. Otherwise Esc
+            // (or Esc Esc, depending on `esc_behavior`) clears the textarea.
+            // The cleared text can be restored with Ctrl+Z.
+            // -------------------------------------------------------------
+            Input { key: Key::Esc, .. } if self.busy => {
+                self.escape_armed = false;
+                self.app_event_tx.send(AppEvent::InterruptAgent);
+                (InputResult::None, false)
+            }
+            Input { key: Key::Esc, .. } => match self.config.esc_behavior {
+                EscBehavior::Disabled => self.handle_input_basic(input),
+                EscBehavior::SingleClear => {
+                    self.clear_undoably();
+                    (InputResult::None, true)
+                }
+                EscBehavior::DoubleClear => {
+                    if self.escape_armed {
+                        self.escape_armed = false;
+                        self.clear_undoably();
+                    } else {
+                        self.escape_armed = true;
+                    }
+                    (InputResult::None, true)
+                }
+            },
+            // -------------------------------------------------------------
+            // History navigation. With bare Up / Down, Up only works at the
+            // very start of the text and Down only on its last visual row,
+            // so moving the cursor through a multi-line draft is never
+            // interrupted. Dedicated keys work anywhere.
+            // -------------------------------------------------------------
+            input
+                if history_step(self.config.history_nav_keys, &input)
+                    == Some(HistoryStep::Older) =>
+            {
+                let at_edge = self.config.history_nav_keys != HistoryNavKeys::Arrows
+                    || self.textarea.cursor() == (0, 0);
+                if at_edge && self.history.should_handle_navigation(&self.textarea) {
+                    let before = ComposerSnapshot::capture(&self.textarea);
+                    let consumed = self
+                        .history
+                        .navigate_up(&mut self.textarea, &self.app_event_tx);
+                    if consumed {
+                        self.undo.push_snapshot(before);
+                        return (InputResult::None, true);
+                    }
+                }
+                self.handle_input_basic(input)
+            }
+            input
+                if history_step(self.config.history_nav_keys, &input)
+                    == Some(HistoryStep::Newer) =>
+            {
+                let at_edge = self.config.history_nav_keys != HistoryNavKeys::Arrows
+                    || self.cursor_on_last_visual_row();
+                if at_edge && self.history.should_handle_navigation(&self.textarea) {
+                    let before = ComposerSnapshot::capture(&self.textarea);
+                    let consumed = self
+                        .history
+                        .navigate_down(&mut self.textarea, &self.app_event_tx);
+                    if consumed {
+                        self.undo.push_snapshot(before);
+                        return (InputResult::None, true);
+                    }
+                }
+                self.handle_input_basic(input)
+            }
+            Input {
+                key: Key::Char('r'),
+                ctrl: true,
+                alt: false,
+                ..
+            } => {
+                self.search_original = Some(ComposerSnapshot::capture(&self.textarea));
+                self.history.start_search();
+                self.update_border();
+                (InputResult::None, true)
+            }
+            // Tab indents. With a popup open it completes instead, which is
+            // handled before we get here.
+            Input {
+                key: Key::Tab,
+                ctrl: false,
+                alt: false,
+                shift: false,
+            } => {
+                let indent = match self.config.tab_width.unwrap_or(DEFAULT_TAB_WIDTH) {
+                    0 => "\t".to_string(),
+                    width => " ".repeat(width),
+                };
+                self.textarea.insert_str(indent);
+                (InputResult::None, true)
+            }
+            // PageUp / PageDown move a full viewport and Home / End jump to
+            // either end of the text. Unlike Up / Down at the edges, they
+            // never navigate history.
+            Input {
+                key: Key::PageUp,
+                shift,
+                ..
+            } => {
+                self.page(false, shift);
+                (InputResult::None, true)
+            }
+            Input {
+                key: Key::PageDown,
+                shift,
+                ..
+            } => {
+                self.page(true, shift);
+                (InputResult::None, true)
+            }
+            Input {
+                key: Key::Home,
+                shift,
+                ..
+            } => {
+                self.jump_with_selection(CursorMove::Jump(0, 0), shift);
+                (InputResult::None, true)
+            }
+            Input {
+                key: Key::End,
+                shift,
+                ..
+            } => {
+                let lines = self.textarea.lines();
+                let last = lines.len().saturating_sub(1);
+                let len = lines.get(last).map_or(0, |l| l.chars().count());
+                self.jump_with_selection(CursorMove::Jump(last as u16, len as u16), shift);
+                (InputResult::None, true)
+            }
+            // Backspace in an empty composer removes the last attachment.
+            Input {
+                key: Key::Backspace,
+                ..
+            } if self.textarea.is_empty() && !self.attachments.is_empty() => {
+                self.attachments.pop();
+                self.update_border();
+                (InputResult::None, true)
+            }
+            input if is_send_and_keep_input(self.config.submit_mode, &input) => {
+                self.submit_with(false, true)
+            }
+            input
+                if is_submit_input(self.config.submit_mode, &input)
+                    || is_send_now_input(self.config.submit_mode, &input) =>
+            {
+                self.submit(input.alt)
+            }
+            Input {
+                key: Key::Enter,
+                ctrl: false,
+                alt: false,
+                shift: false,
+            } if self.config.submit_mode == SubmitMode::BlankLineSends
+                && self.on_trailing_blank_line() =>
+            {
+                // The blank line is dropped from the submitted text.
+                self.submit(false)
+            }
+            // Shift+Enter is listed explicitly since terminals that report
+            // it distinctly should never have it treated as a submit.
+            Input {
+                key: Key::Enter,
+                shift: true,
+                ..
+            }
+            | Input {
+                key: Key::Enter, ..
+            }
+            | Input {
+                key: Key::Char('j'),
+                ctrl: true,
+                alt: false,
+                shift: false,
+            } => {
+                if self.continue_list() {
+                    return (InputResult::None, true);
+                }
+                let indent = self.newline_indent();
+                self.textarea.insert_newline();
+                self.textarea.insert_str(indent);
+                (InputResult::None, true)
+            }
+            input => self.handle_input_basic(input),
+        }
+    }
+
+    /// Insert a newline starting with the next list marker when the cursor
+    /// is past the marker of a list item, or remove the marker of an empty
+    /// item. Returns false, leaving the text alone, anywhere else.
+    fn continue_list(&mut self) -> bool {
+        if !self.config.continue_lists {
+            return false;
+        }
+        let (row, col) = self.textarea.cursor();
+        let lines = self.textarea.lines();
+        if is_inside_code_block(lines, row) {
+            return false;
+        }
+        let line = &lines[row];
+        let Some((marker_len, next)) = list_marker(line) else {
+            return false;
+        };
+        if col < marker_len {
+            return false;
+        }
+        let content_is_empty = line.chars().skip(marker_len).all(char::is_whitespace);
+        if content_is_empty {
+            // An empty item ends the list.
+            self.undo.push(&self.textarea);
+            self.textarea.move_cursor(CursorMove::Head);
+            self.textarea.delete_line_by_end();
+        } else {
+            self.textarea.insert_newline();
+            self.textarea.insert_str(next);
+        }
+        true
+    }
+
+    /// Whether the cursor is on a blank last line below some other line, where
+    /// Enter submits in `SubmitMode::BlankLineSends`.
+    fn on_trailing_blank_line(&self) -> bool {
+        let (row, _) = self.textarea.cursor();
+        let lines = self.textarea.lines();
+        row > 0 && row + 1 == lines.len() && lines[row].trim().is_empty()
+    }
+
+    /// Indentation for a newline inserted at the cursor: the leading
+    /// whitespace of the current line (up to the cursor) when auto-indent is
+    /// enabled and the cursor is inside a code fence, nothing otherwise.
+    fn newline_indent(&self) -> String {
+        if !self.config.auto_indent {
+            return String::new();
+        }
+        let (row, col) = self.textarea.cursor();
+        let lines = self.textarea.lines();
+        if !is_inside_code_block(lines, row) {
+            return String::new();
+        }
+        lines[row]
+            .chars()
+            .take(col)
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect()
+    }
+
+    /// Copy the selection (extended with Shift+arrows) to the clipboard, or
+    /// the cursor's line when nothing is selected and no task is running.
+    /// Returns false when there was nothing to copy, leaving Ctrl+C to
+    /// interrupt the agent. Clipboard errors are logged and otherwise
+    /// ignored.
+    pub(crate) fn copy_to_clipboard(&mut self) -> bool {
+        let lines = self.textarea.lines();
+        let text = match self.textarea.selection_range() {
+            Some((start, end)) => text_between(lines, start, end),
+            None if !self.busy => lines[self.textarea.cursor().0].clone(),
+            None => return false,
+        };
+        if text.is_empty() {
+            return false;
+        }
+        if let Err(e) = self.clipboard.set_text(&text) {
+            tracing::debug!("failed to copy to clipboard: {e}");
+        }
+        true
+    }
+
+    /// Clear the textarea, keeping the old text on the undo stack.
+    fn clear_undoably(&mut self) {
+        if self.textarea.is_empty() {
+            return;
+        }
+        // Clearing only whitespace is not worth a notice.
+        let trivial = self.text().trim().is_empty();
+        self.undo.push(&self.textarea);
+        self.set_text("");
+        if !trivial {
+            self.app_event_tx.send(AppEvent::Notice(
+                "Input cleared — Ctrl+Z to undo".to_string(),
+            ));
+        }
+    }
+
+    /// Whether the cursor is on the last row of the text as last wrapped for
+    /// display.
+    fn cursor_on_last_visual_row(&self) -> bool {
+        let lines = self.textarea.lines();
+        let rows = self.wrapped_rows(self.wrap_width.get());
+        let (row, _) = cursor_position(lines, &rows, self.textarea.cursor());
+        row + 1 >= rows.len()
+    }
+
+    /// Move the cursor one viewport of visual rows down (or up), keeping its
+    /// offset within the row where possible. The scroll position follows the
+    /// cursor on the next render.
+    fn page(&mut self, down: bool, shift: bool) {
+        let lines = self.textarea.lines();
+        let rows = self.wrapped_rows(self.wrap_width.get());
+        let (line, col) = self.textarea.cursor();
+        let (row, _) = cursor_position(lines, &rows, (line, col));
+        let Some(current) = rows.get(row) else {
+            return;
+        };
+        let step = usize::from(self.viewport_rows.get().max(1));
+        let target_idx = if down {
+            (row + step).min(rows.len() - 1)
+        } else {
+            row.saturating_sub(step)
+        };
+        let target = &rows[target_idx];
+        let target_col = (target.start + (col - current.start)).min(target.end);
+        self.jump_with_selection(
+            CursorMove::Jump(target.line as u16, target_col as u16),
+            shift,
+        );
+    }
+
+    /// Apply `motion`, extending the selection when `shift` is held and
+    /// dropping it otherwise.
+    fn jump_with_selection(&mut self, motion: CursorMove, shift: bool) {
+        if !shift {
+            self.textarea.cancel_selection();
+        } else if !self.textarea.is_selecting() {
+            self.textarea.start_selection();
+        }
+        self.textarea.move_cursor(motion);
+    }
+
+    /// Take the text and attachments as a message. While the agent is busy
+    /// the message is queued unless `send_now` is set, in which case the
+    /// running task is interrupted.
+    fn submit(&mut self, send_now: bool) -> (InputResult, bool) {
+        self.submit_with(send_now, false)
+    }
+
+    /// Number of ``` fence lines in the text.
+    fn count_fences(&self) -> usize {
+        self.textarea
             .lines()
-            .first()
-            .map(|s| s.as_str())
-            .unwrap_or("");
+            .iter()
+            .filter(|line| line.trim_start().starts_with("```"))
+            .count()
+    }
 
-        if first_line.starts_with('/') {
-            // Create popup lazily when the user starts a slash command.
-            let popup = self.command_popup.get_or_insert_with(CommandPopup::new);
+    /// [`Self::submit`], optionally leaving the text in the composer (with
+    /// the cursor at its start) so it can be reused for the next message.
+    fn submit_with(&mut self, send_now: bool, keep_draft: bool) -> (InputResult, bool) {
+        if !self.enabled {
+            return (InputResult::None, false);
+        }
+        let draft = self.text();
+        let literal_slash = self.literal_slash;
+        let unbalanced_fences = self.count_fences() % 2 == 1;
+        let mut text = normalize_submission(self.textarea.lines());
+        // Sent once the message has been delivered.
+        let mut notices = Vec::new();
+        if let Some(resolver) = &self.template_resolver {
+            let (expanded, unknown) = expand_templates(&text, resolver.as_ref());
+            if !unknown.is_empty() {
+                let names: Vec<String> = unknown.iter().map(|name| format!(":{name}:")).collect();
+                notices.push(format!("No value for {}; left as typed.", names.join(", ")));
+            }
+            text = expanded;
+        }
+        if let Some(filter) = self.submit_filter.as_ref().filter(|_| !text.is_empty()) {
+            text = match filter(text) {
+                Ok(text) => text,
+                Err(message) => {
+                    self.app_event_tx.send(AppEvent::Notice(message));
+                    return (InputResult::None, true);
+                }
+            };
+        }
+        // The whole message goes to history, even when it is split.
+        let mut full_text = text.clone();
+        let mut overflow = Vec::new();
+        if let Some(max) = self.config.max_chars {
+            let count = text.chars().count();
+            if count > max {
+                match self.config.over_limit {
+                    OverLimitBehavior::Block => {
+                        self.app_event_tx.send(AppEvent::Notice(format!(
+                            "Message is {count} characters long; the limit is {max}."
+                        )));
+                        return (InputResult::None, true);
+                    }
+                    OverLimitBehavior::Split => {
+                        overflow = split_message(&text, max);
+                        text = overflow.remove(0);
+                    }
+                }
+            }
+        }
+        if text.trim().is_empty() {
+            // Whitespace alone, typed or left by a template or the submit
+            // filter, is not a message; leave the text where it is.
+            text.clear();
+            full_text.clear();
+            if self.attachments.is_empty() {
+                return (InputResult::None, true);
+            }
+        }
+        // The attached pastes go with the message; clearing the text must
+        // not withdraw them.
+        let pasted_texts = std::mem::take(&mut self.pasted_texts);
+        self.set_text("");
+        let attachments = std::mem::take(&mut self.attachments);
+        let mut image_paths = Vec::new();
+        let mut file_paths = Vec::new();
+        for attachment in attachments.iter().cloned() {
+            match attachment {
+                Attachment::Image(path) => image_paths.push(path),
+                Attachment::File(path) => file_paths.push(path),
+            }
+        }
+
+        if unbalanced_fences {
+            // Often a sign of a truncated paste; still worth sending.
+            notices.push(
+                "The message has an unclosed ``` fence; part of it may be missing.".to_string(),
+            );
+        }
+        if keep_draft {
+            self.set_text(&draft);
+            if literal_slash {
+                self.literal_slash = true;
+                self.sync_popups();
+            }
+            self.textarea.move_cursor(CursorMove::Jump(0, 0));
+        }
+        let (sent_pastes, kept_pastes) = if keep_draft {
+            (Vec::new(), pasted_texts)
+        } else {
+            pasted_texts.into_iter().partition(|pasted| pasted.attached)
+        };
+        self.pasted_texts = kept_pastes;
+        self.update_border();
+        let (mode, prompt) = if literal_slash {
+            (PromptMode::Default, text.as_str())
+        } else {
+            parse_prompt_mode(&text)
+        };
+        let text = prompt.to_string();
+        // The rest of a split message follows once the first part is done,
+        // in the same mode; the attachments go with the first part only.
+        let queue_overflow = |tx: &AppEventSender| {
+            for part in overflow {
+                tx.send(AppEvent::QueueMessage {
+                    text: part,
+                    image_paths: Vec::new(),
+                    file_paths: Vec::new(),
+                    mode,
+                });
+            }
+        };
+        if !self.busy || send_now {
+            if self.busy {
+                self.app_event_tx.send(AppEvent::CodexOp(Op::Interrupt));
+            }
+            queue_overflow(&self.app_event_tx);
+            self.finish_submission(&full_text, notices);
+            let result = if self.config.parsed_submissions {
+                InputResult::SubmittedParsed(ParsedPrompt {
+                    segments: parse_segments(&text),
+                    text,
+                    mode,
+                    image_paths,
+                    file_paths,
+                    keep_draft,
+                })
+            } else {
+                InputResult::Submitted {
+                    text,
+                    image_paths,
+                    file_paths,
+                    mode,
+                    keep_draft,
+                }
+            };
+            (result, true)
+        } else {
+            let queued = self.app_event_tx.try_send(AppEvent::QueueMessage {
+                text: text.clone(),
+                image_paths,
+                file_paths,
+                mode,
+            });
+            if !queued {
+                self.pasted_texts.extend(sent_pastes);
+                self.set_text(&draft);
+                self.attachments = attachments;
+                self.update_border();
+                return (InputResult::Undelivered, true);
+            }
+            queue_overflow(&self.app_event_tx);
+            self.finish_submission(&full_text, notices);
+            (InputResult::Queued(text), true)
+        }
+    }
+
+    /// Record a delivered message in the history and send the notices
+    /// collected while submitting it.
+    fn finish_submission(&mut self, text: &str, notices: Vec<String>) {
+        self.history.record_local_submission(text);
+        if !text.is_empty() {
+            self.app_event_tx
+                .send(AppEvent::RecordHistory(text.to_string()));
+        }
+        for notice in notices {
+            self.app_event_tx.send(AppEvent::Notice(notice));
+        }
+    }
+
+    /// Ctrl+X Ctrl+E asks the app to open the text in the external editor.
+    /// Ctrl+X itself is left to the usual bindings (it cuts a selection).
+    fn handle_external_editor_keys(&mut self, input: Input) -> Option<(InputResult, bool)> {
+        let is_ctrl = |c: char| matches!(input, Input { key: Key::Char(k), ctrl: true, alt: false, .. } if k == c);
+        let after_ctrl_x = std::mem::replace(&mut self.ctrl_x_pending, is_ctrl('x'));
+        if !(after_ctrl_x && is_ctrl('e')) {
+            return None;
+        }
+        self.escape_armed = false;
+        self.app_event_tx
+            .send(AppEvent::OpenExternalEditor(self.text()));
+        Some((InputResult::None, false))
+    }
+
+    /// Handle Ctrl+Z (undo) and Ctrl+Y / Ctrl+Shift+Z (redo). Returns `None`
+    /// when `input` is not an undo/redo shortcut.
+    fn handle_undo_redo(&mut self, input: Input) -> Option<(InputResult, bool)> {
+        let changed = match input {
+            Input {
+                key: Key::Char('z'),
+                ctrl: true,
+                alt: false,
+                shift: false,
+            } => self.undo.undo(&mut self.textarea),
+            Input {
+                key: Key::Char('y'),
+                ctrl: true,
+                alt: false,
+                shift: false,
+            }
+            | Input {
+                key: Key::Char('z' | 'Z'),
+                ctrl: true,
+                alt: false,
+                shift: true,
+            } => self.undo.redo(&mut self.textarea),
+            _ => return None,
+        };
+        if changed {
+            // Every history recall pushed an undo entry, so undo can step
+            // back through them; the text it lands on is a fresh draft.
+            self.history.stop_browsing();
+            if self.column_edit.take().is_some() {
+                self.update_border();
+            }
+        }
+        self.escape_armed = false;
+        Some((InputResult::None, changed))
+    }
+
+    /// Ctrl+V starts a column edit. While one is active, typed characters
+    /// and Backspace apply to every line of the block, Up / Down resize it
+    /// and Esc or Ctrl+V end it. Any other key ends it and is then handled
+    /// as usual, so `None` is returned for it.
+    fn handle_column_edit(&mut self, input: Input) -> Option<(InputResult, bool)> {
+        let toggle = matches!(
+            input,
+            Input {
+                key: Key::Char('v'),
+                ctrl: true,
+                alt: false,
+                ..
+            }
+        );
+        let Some(column) = self.column_edit.as_mut() else {
+            if !toggle || self.is_popup_visible() {
+                return None;
+            }
+            self.column_edit = Some(ColumnEdit::start(&mut self.textarea));
+            self.update_border();
+            return Some((InputResult::None, true));
+        };
+        let before = ComposerSnapshot::capture(&self.textarea);
+        match input {
+            Input {
+                key: Key::Char(c),
+                ctrl: false,
+                alt: false,
+                ..
+            } => {
+                let first_edit = column.begin_edit();
+                column.insert(&mut self.textarea, c.encode_utf8(&mut [0; 4]));
+                if first_edit {
+                    self.undo.push_snapshot(before);
+                }
+            }
+            Input {
+                key: Key::Backspace,
+                ctrl: false,
+                alt: false,
+                ..
+            } => {
+                let first_edit = column.begin_edit();
+                column.backspace(&mut self.textarea);
+                if first_edit && self.textarea.lines() != before.lines() {
+                    self.undo.push_snapshot(before);
+                }
+            }
+            Input {
+                key: key @ (Key::Up | Key::Down),
+                ctrl: false,
+                alt: false,
+                shift: false,
+            } => column.move_row(&mut self.textarea, key == Key::Up),
+            Input { key: Key::Esc, .. } => {
+                self.column_edit = None;
+                self.update_border();
+            }
+            _ if toggle => {
+                self.column_edit = None;
+                self.update_border();
+            }
+            _ => {
+                self.column_edit = None;
+                self.update_border();
+                return None;
+            }
+        }
+        self.escape_armed = false;
+        Some((InputResult::None, true))
+    }
+
+    /// Offer `input` to the Vim state machine, if enabled. Returns `None` when
+    /// the input should be handled by the regular (Insert mode) bindings.
+    fn handle_vim_input(&mut self, input: Input) -> Option<(InputResult, bool)> {
+        let vim = self.vim.as_mut()?;
+        let before = ComposerSnapshot::capture(&self.textarea);
+        match vim.handle_input(&input, &mut self.textarea) {
+            VimOutcome::PassThrough => return None,
+            VimOutcome::Consumed => {
+                if self.textarea.lines() != before.lines() {
+                    self.undo.push_snapshot(before);
+                }
+            }
+            VimOutcome::ModeChanged => self.update_border(),
+        }
+        self.escape_armed = false;
+        Some((InputResult::None, true))
+    }
+
+    /// `gg` / `G` outside of Vim mode, when enabled, while no popup is open
+    /// and the text is too long to see at once. `can_start` is false right
+    /// after typing. Returns `None` when `input` should be handled as usual.
+    fn handle_jump_keys(&mut self, input: Input, can_start: bool) -> Option<(InputResult, bool)> {
+        if !self.config.jump_keys
+            || self.vim.is_some()
+            || self.is_popup_visible()
+            || !self.overflows()
+        {
+            self.jump_keys.reset();
+            return None;
+        }
+        let jump = self.jump_keys.on_input(&input, can_start, Instant::now())?;
+        match jump {
+            Jump::Top => {
+                // The first `g` was typed as text; take it out again.
+                self.textarea.delete_char();
+                self.textarea.move_cursor(CursorMove::Top);
+                self.textarea.move_cursor(CursorMove::Head);
+            }
+            Jump::Bottom => {
+                self.textarea.move_cursor(CursorMove::Bottom);
+                self.textarea.move_cursor(CursorMove::End);
+            }
+        }
+        // A jump is not typing, so another one may follow.
+        self.last_input_typed = false;
+        self.escape_armed = false;
+        Some((InputResult::None, true))
+    }
+
+    /// What the draft file holds: the main buffer, even while the scratch
+    /// buffer is shown in its place.
+    fn draft_state(&self) -> ComposerState {
+        match self.stash.as_ref().filter(|_| self.showing_scratch) {
+            Some(main) => ComposerState {
+                text: main.snapshot.lines().join("\n"),
+                cursor: main.snapshot.cursor(),
+                escape_armed: false,
+                popup_open: false,
+            },
+            // An armed Esc should not outlive a restart.
+            None => ComposerState {
+                escape_armed: false,
+                ..self.snapshot()
+            },
+        }
+    }
+
+    /// Show the stashed buffer and stash the current one, along with its
+    /// cursor, undo history and attachments.
+    fn swap_scratch(&mut self) {
+        let current = StashedBuffer {
+            snapshot: ComposerSnapshot::capture(&self.textarea),
+            undo: std::mem::replace(&mut self.undo, ChatComposerUndo::new()),
+            attachments: std::mem::take(&mut self.attachments),
+        };
+        match self.stash.replace(current) {
+            Some(stashed) => {
+                stashed.snapshot.apply(&mut self.textarea);
+                self.undo = stashed.undo;
+                self.attachments = stashed.attachments;
+            }
+            None => {
+                self.textarea.select_all();
+                self.textarea.cut();
+            }
+        }
+        self.history.stop_browsing();
+        self.showing_scratch = !self.showing_scratch;
+        self.update_border();
+    }
+
+    /// Whether `.` would repeat something instead of being typed: the
+    /// composer is empty and there is an action of the configured kind.
+    fn can_repeat(&self) -> bool {
+        if !(self.textarea.is_empty() && self.attachments.is_empty()) {
+            return false;
+        }
+        match self.config.dot_repeat {
+            DotRepeat::Disabled => false,
+            DotRepeat::Command => self.last_command.is_some(),
+            DotRepeat::Message => self.history.last_local_submission().is_some(),
+        }
+    }
+
+    /// Run the last command again, or submit the last message again,
+    /// according to `dot_repeat`.
+    fn repeat_last_action(&mut self) -> (InputResult, bool) {
+        match self.config.dot_repeat {
+            DotRepeat::Disabled => (InputResult::None, false),
+            DotRepeat::Command => {
+                let Some((command, args)) = self.last_command.clone() else {
+                    return (InputResult::None, false);
+                };
+                if !self
+                    .app_event_tx
+                    .try_send(AppEvent::DispatchCommand { command, args })
+                {
+                    return (InputResult::Undelivered, true);
+                }
+                (InputResult::None, true)
+            }
+            DotRepeat::Message => {
+                let Some(text) = self.history.last_local_submission().map(str::to_string) else {
+                    return (InputResult::None, false);
+                };
+                self.set_text(&text);
+                self.submit(false)
+            }
+        }
+    }
+
+    /// The text soft-wrapped to `width` columns.
+    fn wrapped_rows(&self, width: u16) -> Vec<WrappedRow> {
+        self.wrap_cache
+            .borrow_mut()
+            .rows(self.textarea.lines(), width)
+    }
+
+    /// Whether the text spans several lines and has more rows than fit in
+    /// the textarea as last rendered.
+    fn overflows(&self) -> bool {
+        self.textarea.lines().len() > 1
+            && self.wrapped_rows(self.wrap_width.get()).len()
+                > usize::from(self.viewport_rows.get())
+    }
+
+    /// Handle generic Input events that modify the textarea content.
+    fn handle_input_basic(&mut self, input: Input) -> (InputResult, bool) {
+        let escapes_slash = matches!(
+            input,
+            Input {
+                key: Key::Char('/'),
+                ctrl: false,
+                alt: false,
+                ..
+            }
+        ) && self.textarea.cursor() == (0, 1)
+            && self.textarea.lines()[0] == "/";
+        if escapes_slash {
+            // `//` types a literal slash, so a message can start with a path
+            // such as `/etc/hosts` without running a command.
+            self.literal_slash = true;
+            return (InputResult::None, true);
+        }
+        if !self.handle_word_motion(&input) && !self.handle_editing_shortcut(&input) {
+            self.textarea.input(input);
+        }
+        (InputResult::None, true)
+    }
+
+    /// Ctrl+Left / Ctrl+Right move to the start of the previous / end of the
+    /// next word, crossing onto the adjacent line at either end of a line.
+    /// Returns true when `input` was one of them.
+    fn handle_word_motion(&mut self, input: &Input) -> bool {
+        let (row, col) = self.textarea.cursor();
+        let lines = self.textarea.lines();
+        let target = match *input {
+            Input {
+                key: Key::Left,
+                ctrl: true,
+                alt: false,
+                ..
+            } => match prev_word_start(&lines[row], col) {
+                Some(start) => (row, start),
+                None if col > 0 => (row, 0),
+                None if row > 0 => (row - 1, lines[row - 1].chars().count()),
+                None => (row, col),
+            },
+            Input {
+                key: Key::Right,
+                ctrl: true,
+                alt: false,
+                ..
+            } => {
+                let len = lines[row].chars().count();
+                match next_word_end(&lines[row], col) {
+                    Some(end) => (row, end),
+                    None if col < len => (row, len),
+                    None if row + 1 < lines.len() => (row + 1, 0),
+                    None => (row, col),
+                }
+            }
+            _ => return false,
+        };
+        self.textarea
+            .move_cursor(CursorMove::Jump(target.0 as u16, target.1 as u16));
+        true
+    }
+
+    /// Readline-style line editing shortcuts. These are mapped explicitly
+    /// rather than relying on `tui_textarea`'s defaults (which bind Ctrl+U to
+    /// undo, for example). Returns true when `input` was one of them.
+    fn handle_editing_shortcut(&mut self, input: &Input) -> bool {
+        let c = match *input {
+            Input {
+                key: Key::Char(c),
+                ctrl: true,
+                alt: false,
+                ..
+            } => c,
+            // Ctrl+Backspace deletes the previous word like Ctrl+W, which is
+            // what terminals that cannot report it send instead (0x17).
+            // Ctrl+H is not mapped: some terminals send it for Backspace.
+            Input {
+                key: Key::Backspace,
+                ctrl: true,
+                alt: false,
+                ..
+            } => 'w',
+            _ => return false,
+        };
+
+        if input.shift && c.eq_ignore_ascii_case(&'k') {
+            self.clear_current_line();
+            return true;
+        }
+
+        // Ctrl+D deletes forward like readline, and quits once there is
+        // nothing left to delete.
+        if c == 'd' && self.textarea.is_empty() && self.attachments.is_empty() {
+            self.app_event_tx.send(AppEvent::ExitRequest);
+            return true;
+        }
+
+        let before = ComposerSnapshot::capture(&self.textarea);
+        let deleted = match c {
+            'w' => self.textarea.delete_word(),
+            'd' => self.textarea.delete_next_char(),
+            'u' => self.textarea.delete_line_by_head(),
+            'k' => self.textarea.delete_line_by_end(),
+            'a' => {
+                self.textarea.move_cursor(CursorMove::Head);
+                false
+            }
+            'e' => {
+                self.textarea.move_cursor(CursorMove::End);
+                false
+            }
+            _ => return false,
+        };
+        if deleted {
+            self.undo.push_snapshot(before);
+        }
+        true
+    }
+
+    /// Remove the cursor's line, line break included, leaving the rest of a
+    /// multi-line draft alone (Ctrl+Shift+K). Undoable like any other edit.
+    fn clear_current_line(&mut self) {
+        let (row, _) = self.textarea.cursor();
+        let lines = self.textarea.lines();
+        let len = |r: usize| lines[r].chars().count() as u16;
+        let (start, end) = if row + 1 < lines.len() {
+            ((row as u16, 0), (row as u16 + 1, 0))
+        } else if row > 0 {
+            ((row as u16 - 1, len(row - 1)), (row as u16, len(row)))
+        } else {
+            ((0, 0), (0, len(0)))
+        };
+        let next_row = if row + 1 < lines.len() {
+            row
+        } else {
+            row.saturating_sub(1)
+        };
+        if start == end {
+            return;
+        }
+
+        self.undo.push(&self.textarea);
+        self.textarea.cancel_selection();
+        self.textarea
+            .move_cursor(CursorMove::Jump(start.0, start.1));
+        self.textarea.start_selection();
+        self.textarea.move_cursor(CursorMove::Jump(end.0, end.1));
+        self.textarea.cut();
+        self.textarea
+            .move_cursor(CursorMove::Jump(next_row as u16, 0));
+        self.escape_armed = false;
+    }
+
+    /// Hand the current text to the draft store (if enabled) and report its
+    /// size to the app. Submitting empties the textarea, which removes the
+    /// draft file.
+    fn on_text_updated(&mut self) {
+        self.sync_pasted_texts();
+        if self.draft.is_some() {
+            let state = self.draft_state();
+            if let Some(draft) = self.draft.as_mut() {
+                draft.update(&state);
+            }
+        }
+        self.text_changed.on_text_change(self.textarea.lines());
+    }
+
+    /// Show, update, or hide every popup to reflect the current text.
+    fn sync_popups(&mut self) {
+        if let Some(dismissed) = &self.popups_dismissed_for {
+            if dismissed.as_slice() == self.textarea.lines() {
+                return;
+            }
+            self.popups_dismissed_for = None;
+        }
+        let was_visible = self.is_popup_visible();
+        self.sync_command_popup();
+        self.sync_file_popup();
+        if !was_visible && self.is_popup_visible() {
+            // An Esc pressed before the popup opened must not combine with
+            // one pressed to dismiss it.
+            self.escape_armed = false;
+        }
+    }
+
+    /// Hide every popup until the text changes (Esc while a popup is open).
+    fn dismiss_popups(&mut self) {
+        self.command_popup = None;
+        self.close_file_popup();
+        self.escape_armed = false;
+        self.popups_dismissed_for = Some(self.textarea.lines().to_vec());
+    }
+
+    /// Synchronize `self.file_popup` with the `@` token under the cursor. The
+    /// slash-command popup takes precedence when both would apply.
+    fn sync_file_popup(&mut self) {
+        if self
+            .file_watcher
+            .as_ref()
+            .is_some_and(FileWatcher::take_changed)
+        {
+            self.file_index = None;
+            if let Some(popup) = self.file_popup.as_mut() {
+                popup.invalidate();
+            }
+        }
+        let query = match &self.config.file_search_root {
+            Some(_) if self.command_popup.is_none() => self.mention_query(),
+            _ => None,
+        };
+        let (Some(query), Some(root)) = (query, &self.config.file_search_root) else {
+            self.close_file_popup();
+            return;
+        };
+        let theme = self.theme;
+        let index = &mut self.file_index;
+        self.file_popup
+            .get_or_insert_with(|| {
+                FileSearchPopup::new(root.clone())
+                    .with_theme(theme)
+                    .with_index(index.take())
+            })
+            .on_query_change(&query);
+    }
+
+    /// Hide the file popup, keeping its listing for the next one while the
+    /// root is watched for changes.
+    fn close_file_popup(&mut self) {
+        if let Some(mut popup) = self.file_popup.take() {
+            if self.file_watcher.is_some() {
+                self.file_index = popup.take_index();
+            }
+        }
+    }
+
+    /// Text between the `@` and the cursor when the cursor is inside an `@`
+    /// token.
+    fn mention_query(&self) -> Option<String> {
+        let (start, _) = self.mention_token_range()?;
+        let (row, col) = self.textarea.cursor();
+        let line = self.textarea.lines().get(row)?;
+        Some(line.chars().take(col).skip(start + 1).collect())
+    }
+
+    /// Synchronize `self.command_popup` with the current text in the
+    /// textarea. This must be called after every modification that can change
+    /// the text so the popup is shown/updated/hidden as appropriate.
+    fn sync_command_popup(&mut self) {
+        // Inspect only the first line to decide whether to show the popup. In
+        // the common case (no leading slash) we avoid copying the entire
+        // textarea contents.
+        let first_line = self
+            .textarea
+            .lines()
+            .first()
+            .map(|s| s.as_str())
+            .unwrap_or("");
+
+        if !first_line.starts_with('/') {
+            self.literal_slash = false;
+        }
+        if first_line.starts_with('/') && !self.literal_slash {
+            // Create popup lazily when the user starts a slash command.
+            let models = &self.config.models;
+            let theme = self.theme;
+            let commands = &self.commands;
+            let popup = self.command_popup.get_or_insert_with(|| {
+                let mut popup = CommandPopup::new(models.clone()).with_theme(theme);
+                if let Some(commands) = commands {
+                    popup.set_commands(commands);
+                }
+                popup
+            });
+
+            // Forward *only* the first line since `CommandPopup` only needs
+            // the command token.
+            popup.on_composer_text_change(first_line.to_string());
+        } else if self.command_popup.is_some() {
+            // Remove popup when '/' is no longer the first character, or is
+            // a literal one.
+            self.command_popup = None;
+        }
+    }
+
+    /// Height needed to show the soft-wrapped rows of the textarea (plus
+    /// border and popup) when rendered into `area`, kept within the limits
+    /// set by `set_row_limits`.
+    pub fn calculate_required_height(&self, area: &Rect) -> u16 {
+        let height = self.required_height(area);
+        self.measured_height.set(Some((*area, height)));
+        height
+    }
+
+    /// Whether the height last returned by `calculate_required_height` is
+    /// out of date, e.g. because lines were added or deleted since. Lets a
+    /// caller that caches the height know when to measure again.
+    pub(crate) fn height_changed(&self) -> bool {
+        match self.measured_height.get() {
+            Some((area, height)) => self.required_height(&area) != height,
+            None => true,
+        }
+    }
+
+    fn required_height(&self, area: &Rect) -> u16 {
+        let text_width = area.width.saturating_sub(BORDER_LINES);
+        let mut rows = self.wrapped_rows(text_width).len();
+        if self.empty_tips().is_some() {
+            // Placeholder plus the tips line.
+            rows = 2;
+        }
+        let rows = rows.clamp(self.min_rows, self.max_rows);
+        rows as u16 + BORDER_LINES + self.popup_height(area)
+    }
+
+    /// Height of whichever popup is visible, or 0.
+    fn popup_height(&self, area: &Rect) -> u16 {
+        if let Some(popup) = &self.command_popup {
+            popup.calculate_required_height(area)
+        } else if let Some(popup) = &self.file_popup {
+            popup.calculate_required_height(area)
+        } else {
+            0
+        }
+    }
+
+    fn update_border(&mut self) {
+        // The key hint in the bottom border depends on the width, so it is
+        // added in `render_textarea`; see `border_hint`.
+        let border_style = if self.has_input_focus && self.enabled {
+            Style::default()
+        } else {
+            Style::default().dim()
+        };
+
+        let mode_title = match self.vim.as_ref().map(VimState::mode) {
+            _ if self.column_edit.is_some() => Line::from(" COLUMN ").bold(),
+            Some(VimMode::Normal) => Line::from(" NORMAL ").bold(),
+            Some(VimMode::Insert) => Line::from(" INSERT "),
+            None => Line::default(),
+        };
+
+        let search_title = match self.history.search_prompt() {
+            Some(prompt) => Line::from(format!(" {prompt} ")).italic(),
+            None => Line::default(),
+        };
+
+        let scratch_title = if self.showing_scratch {
+            Line::from(" scratch · Ctrl+G for draft ").dim().italic()
+        } else {
+            Line::default()
+        };
+
+        let queued_title = match self.queued_messages {
+            0 => Line::default(),
+            n => Line::from(format!(" {n} queued ")).dim().italic(),
+        };
+
+        let attachments_title = Line::from(
+            self.attachments
+                .iter()
+                .map(|attachment| {
+                    let (kind, path) = match attachment {
+                        Attachment::Image(path) => ("image", path),
+                        Attachment::File(path) => ("file", path),
+                    };
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy())
+                        .unwrap_or_else(|| path.to_string_lossy());
+                    format!(" [{kind}: {name}] ")
+                })
+                .collect::<String>(),
+        )
+        .style(self.theme.accent);
+
+        self.textarea.set_block(
+            ratatui::widgets::Block::default()
+                .title(mode_title)
+                .title(search_title)
+                .title(attachments_title)
+                .title(queued_title)
+                .title(scratch_title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(border_style),
+        );
+    }
+
+    /// Key hint for the bottom border, shortened or dropped so that it fits
+    /// between the corners of a composer `width` columns wide.
+    fn border_hint(&self, width: u16) -> Option<String> {
+        if !(self.has_input_focus && self.enabled) {
+            return None;
+        }
+        let available = usize::from(width.saturating_sub(2));
+        [
+            submit_hint(self.config.submit_mode, self.config.esc_behavior),
+            short_submit_hint(self.config.submit_mode).to_string(),
+        ]
+        .into_iter()
+        .find(|hint| hint.width() <= available)
+    }
+
+    /// `" count/max "` once the draft, of `count` chars, is longer than
+    /// `max_chars`.
+    fn over_limit_counter(&self, count: usize) -> Option<String> {
+        let max = self.config.max_chars?;
+        (count > max).then(|| format!(" {count}/{max} "))
+    }
+
+    /// `" 12 words · ~18 tok "` for a draft of `chars` chars and `words`
+    /// words when `show_word_count` is set.
+    fn text_stats(&self, chars: usize, words: usize) -> Option<String> {
+        if !self.config.show_word_count || self.textarea.is_empty() {
+            return None;
+        }
+        let tokens = match &self.token_estimator {
+            Some(estimate) => estimate(&self.text()),
+            // About one token per four chars; close enough for an estimate.
+            None => chars.div_ceil(4),
+        };
+        let unit = if words == 1 { "word" } else { "words" };
+        Some(format!(" {words} {unit} · ~{tokens} tok "))
+    }
+
+    /// Bring the per-line render data up to date with the text, scanning
+    /// only the lines that changed since the last render.
+    fn update_line_cache(&self) -> Ref<'_, LineCache<FenceContext, LineRender>> {
+        let lines = self.textarea.lines();
+        let highlight = self.config.highlight_code_blocks && self.theme.syntax_highlighting;
+        let mentions = self.config.file_search_root.is_some();
+        self.line_cache
+            .borrow_mut()
+            .update(lines, &fence_contexts(lines), |line, context| LineRender {
+                chars: line.chars().count(),
+                words: line.split_whitespace().count(),
+                styles: if highlight {
+                    line_styles(line, context)
+                } else {
+                    Vec::new()
+                },
+                mentions: if mentions && context == FenceContext::Outside {
+                    line_mentions(line)
+                } else {
+                    Vec::new()
+                },
+            });
+        self.line_cache.borrow()
+    }
+
+    /// Tips shown on the line below the placeholder while the composer is
+    /// empty and focused.
+    fn empty_tips(&self) -> Option<String> {
+        if !(self.has_input_focus && self.textarea.is_empty()) {
+            return None;
+        }
+        let mut tips = vec!["/ for commands"];
+        if self.config.file_search_root.is_some() {
+            tips.push("@ to reference files");
+        }
+        Some(tips.join(" • "))
+    }
+
+    /// Draw the textarea block and its soft-wrapped contents. `tui-textarea`
+    /// only scrolls horizontally, so the text itself is laid out here.
+    fn render_textarea(&self, area: Rect, buf: &mut Buffer) {
+        let line_cache = self.update_line_cache();
+        let line_renders: Vec<&LineRender> = line_cache.values().collect();
+        // Char offset of the start of each line, counting line breaks, to
+        // find the chars beyond `max_chars`.
+        let line_offsets: Vec<usize> = line_renders
+            .iter()
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.chars + 1;
+                Some(start)
+            })
+            .collect();
+        let char_count = line_offsets
+            .last()
+            .zip(line_renders.last())
+            .map_or(0, |(start, line)| start + line.chars);
+        let inner = match self.textarea.block() {
+            // Too short for the border and a row of text: drop the border.
+            Some(_) if area.height <= BORDER_LINES => area,
+            Some(block) => {
+                let mut block = block.clone();
+                let words = line_renders.iter().map(|line| line.words).sum();
+                if let Some(stats) = self.text_stats(char_count, words) {
+                    block = block.title(Line::from(stats).dim().alignment(Alignment::Right));
+                }
+                if let Some(counter) = self.over_limit_counter(char_count) {
+                    block = block.title(
+                        Line::from(counter)
+                            .style(self.theme.warning)
+                            .alignment(Alignment::Right),
+                    );
+                }
+                if let Some(hint) = self.border_hint(area.width) {
+                    block = block.title_bottom(Line::from(hint).alignment(Alignment::Right));
+                }
+                block.render_ref(area, buf);
+                block.inner(area)
+            }
+            None => area,
+        };
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        let cursor_style = self.textarea.cursor_style();
+        if self.textarea.is_empty() {
+            buf[(inner.x, inner.y)].set_style(cursor_style);
+            if let Some(style) = self.textarea.placeholder_style() {
+                buf.set_stringn(
+                    inner.x + 1,
+                    inner.y,
+                    self.textarea.placeholder_text(),
+                    usize::from(inner.width - 1),
+                    style,
+                );
+            }
+            if let Some(tips) = self.empty_tips() {
+                if inner.height > 1 {
+                    buf.set_stringn(
+                        inner.x + 1,
+                        inner.y + 1,
+                        tips,
+                        usize::from(inner.width - 1),
+                        Style::default().dim(),
+                    );
+                }
+            }
+            return;
+        }
+
+        self.wrap_width.set(inner.width);
+        self.viewport_rows.set(inner.height);
+        let lines = self.textarea.lines();
+        let rows = self.wrapped_rows(inner.width);
+        let (cursor_row, cursor_x) = cursor_position(lines, &rows, self.textarea.cursor());
+        // Scroll just enough to keep the cursor row on screen, and never past
+        // the last row once the content shrinks.
+        let height = usize::from(inner.height);
+        let mut top = self.scroll_top.get();
+        if cursor_row < top {
+            top = cursor_row;
+        } else if cursor_row >= top + height {
+            top = cursor_row + 1 - height;
+        }
+        top = top.min(rows.len().saturating_sub(height));
+        self.scroll_top.set(top);
+        let selection = self.textarea.selection_range();
+        let selection_style = self.theme.selection;
+        let overflow_style = self.theme.warning.underlined();
+        let command = command_token(lines, |name| match &self.commands {
+            _ if self.literal_slash => false,
+            Some(commands) => commands.iter().any(|c| c.command() == name),
+            None => SlashCommand::lookup(name).is_some(),
+        });
+
+        for (y, row) in rows.iter().skip(top).take(height).enumerate() {
+            let y = inner.y + y as u16;
+            let mut x = inner.x;
+            let right = inner.x + inner.width;
+            for (col, g) in graphemes(&lines[row.line])
+                .into_iter()
+                .filter(|(col, _)| (row.start..row.end).contains(col))
+            {
+                if x >= right {
+                    break;
+                }
+                let selected = selection
+                    .is_some_and(|(start, end)| (row.line, col) >= start && (row.line, col) < end);
+                let over_limit = self
+                    .config
+                    .max_chars
+                    .is_some_and(|max| line_offsets[row.line] + col >= max);
+                let line = line_renders[row.line];
+                let in_token = command
+                    .filter(|_| row.line == 0)
+                    .into_iter()
+                    .chain(line.mentions.iter().copied())
+                    .any(|(start, end)| (start..end).contains(&col));
+                let style = if selected {
+                    selection_style
+                } else if over_limit {
+                    overflow_style
+                } else if in_token {
+                    self.theme.accent
+                } else {
+                    line.styles.get(col).copied().unwrap_or_default()
+                };
+                let (next_x, _) = buf.set_stringn(x, y, g, usize::from(right - x), style);
+                x = next_x;
+            }
+        }
+
+        if let Some(y) = cursor_row.checked_sub(top) {
+            let x = (cursor_x as u16).min(inner.width - 1);
+            buf[(inner.x + x, inner.y + y as u16)].set_style(cursor_style);
+        }
+
+        if rows.len() > height && inner != area {
+            render_scroll_indicator(area, inner, top, cursor_row, rows.len(), buf);
+        }
+    }
+
+    /// Whether a completion popup (slash commands or `@path` mentions) is
+    /// visible and therefore wants Tab/Up/Down.
+    pub(crate) fn is_popup_visible(&self) -> bool {
+        self.command_popup.is_some() || self.file_popup.is_some()
+    }
+}
+
+/// For content taller than the textarea, draw a `row/total` counter in the
+/// top border and a thumb on the right border marking the visible rows.
+fn render_scroll_indicator(
+    area: Rect,
+    inner: Rect,
+    top: usize,
+    cursor_row: usize,
+    total_rows: usize,
+    buf: &mut Buffer,
+) {
+    let counter = format!(" {}/{} ", cursor_row + 1, total_rows);
+    let counter_width = counter.len() as u16;
+    if counter_width + 2 <= area.width {
+        buf.set_string(
+            area.right() - 1 - counter_width,
+            area.y,
+            counter,
+            Style::default().dim(),
+        );
+    }
+
+    let height = usize::from(inner.height);
+    let thumb_len = (height * height / total_rows).max(1);
+    let thumb_start = (top * height / total_rows).min(height - thumb_len);
+    for y in thumb_start..thumb_start + thumb_len {
+        buf[(area.right() - 1, inner.y + y as u16)].set_symbol("┃");
+    }
+}
+
+impl WidgetRef for &ChatComposer<'_> {
+    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let popup_height = self.popup_height(&area);
+        if popup_height == 0 {
+            self.render_textarea(area, buf);
+            return;
+        }
+
+        // Split the provided rect so that the popup is rendered on the side
+        // given by `popup_placement` and the textarea occupies the remaining
+        // space. When the area is short, the popup shrinks (scrolling its
+        // entries) so the textarea keeps at least one usable row.
+        let textarea_min = (BORDER_LINES + 1).min(area.height);
+        let popup_rows = popup_height.min(area.height - textarea_min);
+        let textarea_rows = area.height - popup_rows;
+        let (popup_y, textarea_y) = match self.config.popup_placement {
+            PopupPlacement::Above => (area.y, area.y + popup_rows),
+            PopupPlacement::Below => (area.y + textarea_rows, area.y),
+        };
+        let popup_rect = Rect {
+            x: area.x,
+            y: popup_y,
+            width: area.width,
+            height: popup_rows,
+        };
+
+        let textarea_rect = Rect {
+            x: area.x,
+            y: textarea_y,
+            width: area.width,
+            height: textarea_rows,
+        };
+
+        if popup_rect.height > 0 {
+            if let Some(popup) = &self.command_popup {
+                popup.render(popup_rect, buf);
+            } else if let Some(popup) = &self.file_popup {
+                popup.render(popup_rect, buf);
+            }
+        }
+        self.render_textarea(textarea_rect, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::expect_used)]
+    use super::*;
+    use crossterm::event::KeyCode;
+    use crossterm::event::KeyModifiers;
+    use ratatui::style::Color;
+    use std::sync::mpsc::Receiver;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    fn make_composer() -> (ChatComposer<'static>, Receiver<AppEvent>) {
+        make_composer_with_config(ChatComposerConfig::default())
+    }
+
+    fn make_composer_with_config(
+        config: ChatComposerConfig,
+    ) -> (ChatComposer<'static>, Receiver<AppEvent>) {
+        make_composer_with_theme(config, ComposerTheme::default())
+    }
+
+    fn make_composer_with_theme(
+        config: ChatComposerConfig,
+        theme: ComposerTheme,
+    ) -> (ChatComposer<'static>, Receiver<AppEvent>) {
+        let (tx, rx) = channel::<AppEvent>();
+        (
+            ChatComposer::new(true, AppEventSender::new(tx), config, theme),
+            rx,
+        )
+    }
+
+    fn press(composer: &mut ChatComposer, code: KeyCode, modifiers: KeyModifiers) -> InputResult {
+        composer.handle_key_event(KeyEvent::new(code, modifiers)).0
+    }
+
+    fn type_str(composer: &mut ChatComposer, text: &str) {
+        for ch in text.chars() {
+            press(composer, KeyCode::Char(ch), KeyModifiers::NONE);
+        }
+    }
+
+    fn text_of(composer: &ChatComposer) -> String {
+        composer.text()
+    }
+
+    #[test]
+    fn esc_esc_clear_can_be_undone_and_redone() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "a long message");
+
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "a long message");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "");
+
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "a long message");
+
+        press(&mut composer, KeyCode::Char('y'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "");
+
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "a long message");
+        press(
+            &mut composer,
+            KeyCode::Char('Z'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn typing_between_escapes_disarms_clear() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "hi");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        type_str(&mut composer, "!");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "hi!");
+    }
+
+    #[test]
+    fn paste_inserts_newlines_without_submitting() {
+        let (mut composer, rx) = make_composer();
+        assert!(composer.handle_paste("line1\nline2\r\nline3".to_string()));
+
+        assert_eq!(composer.textarea.lines(), ["line1", "line2", "line3"]);
+        assert!(rx.try_recv().is_err());
+
+        // The pasted text is still pending; Enter submits all of it at once.
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(
+            matches!(result, InputResult::Submitted { text, .. } if text == "line1\nline2\nline3")
+        );
+    }
+
+    #[test]
+    fn history_recall_is_undoable() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "first");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "first"));
+
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "first");
+
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn enter_sends_mode_submits_on_enter() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "a");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::SHIFT);
+        type_str(&mut composer, "b");
+        assert_eq!(text_of(&composer), "a\nb");
+
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "a\nb"));
+    }
+
+    #[test]
+    fn enter_newline_mode_submits_on_ctrl_enter() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            submit_mode: SubmitMode::EnterNewlineCtrlEnterSends,
+            ..Default::default()
+        });
+        type_str(&mut composer, "a");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        type_str(&mut composer, "b");
+        assert_eq!(text_of(&composer), "a\nb");
+
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::CONTROL);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "a\nb"));
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn at_mention_completes_file_path_with_tab() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        std::fs::create_dir_all(dir.path().join("src/nested")).expect("mkdir");
+        std::fs::write(dir.path().join("src/nested/main.rs"), "").expect("write file");
+        std::fs::write(dir.path().join("README.md"), "").expect("write file");
+
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            file_search_root: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        });
+        type_str(&mut composer, "look at @nest/ma");
+        assert!(composer.is_popup_visible());
+
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "look at @src/nested/main.rs ");
+        assert!(!composer.is_popup_visible());
+    }
+
+    #[test]
+    fn draft_is_restored_after_restart_and_cleared_on_submit() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let config = ChatComposerConfig {
+            draft_path: Some(dir.path().join("draft")),
+            ..Default::default()
+        };
+
+        let (mut composer, _rx) = make_composer_with_config(config.clone());
+        type_str(&mut composer, "unsent work");
+        drop(composer);
+
+        let (mut composer, _rx) = make_composer_with_config(config.clone());
+        assert_eq!(text_of(&composer), "unsent work");
+        assert_eq!(composer.textarea.cursor(), (0, "unsent work".len()));
+
+        // The cursor is restored with the text.
+        press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Char('!'), KeyModifiers::NONE);
+        drop(composer);
+        let (mut composer, _rx) = make_composer_with_config(config.clone());
+        assert_eq!(text_of(&composer), "unsent wor!k");
+        assert_eq!(composer.textarea.cursor(), (0, "unsent wor!".len()));
+
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { .. }));
+        assert!(!dir.path().join("draft").exists());
+        drop(composer);
+
+        let (composer, _rx) = make_composer_with_config(config);
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[tokio::test]
+    async fn last_edits_reach_the_draft_file_without_a_drop() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("draft");
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            draft_path: Some(path.clone()),
+            ..Default::default()
+        });
+        // The first key is written at once; the rest land inside the
+        // debounce window.
+        type_str(&mut composer, "unsent work");
+        let restored = |path: &PathBuf| {
+            let contents = std::fs::read_to_string(path).expect("read draft");
+            serde_json::from_str::<ComposerState>(&contents)
+                .expect("parse draft")
+                .text
+        };
+        assert_eq!(restored(&path), "u");
+
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        assert_eq!(restored(&path), "unsent work");
+        drop(composer);
+    }
+
+    #[test]
+    fn vim_mode_motions_and_dd() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            vim_mode: true,
+            ..Default::default()
+        });
+        type_str(&mut composer, "one two");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::SHIFT);
+        type_str(&mut composer, "three");
+
+        // Esc enters Normal mode instead of arming the clear.
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "one two\nthree");
+        assert_eq!(composer.textarea.cursor(), (1, 4));
+
+        type_str(&mut composer, "0");
+        assert_eq!(composer.textarea.cursor(), (1, 0));
+        type_str(&mut composer, "k$");
+        assert_eq!(composer.textarea.cursor(), (0, 7));
+        type_str(&mut composer, "b");
+        assert_eq!(composer.textarea.cursor(), (0, 4));
+        type_str(&mut composer, "hl");
+        assert_eq!(composer.textarea.cursor(), (0, 4));
+
+        // Normal-mode keys never insert text.
+        type_str(&mut composer, "x:q");
+        assert_eq!(text_of(&composer), "one wo\nthree");
+
+        type_str(&mut composer, "dd");
+        assert_eq!(text_of(&composer), "three");
+
+        type_str(&mut composer, "A");
+        type_str(&mut composer, "i>");
+        assert_eq!(text_of(&composer), ">three");
+    }
+
+    #[test]
+    fn height_accounts_for_soft_wrapped_rows() {
+        let (mut composer, _rx) = make_composer();
+        let area = Rect::new(0, 0, 20, 10);
+        // Placeholder plus the tips line.
+        assert_eq!(composer.calculate_required_height(&area), 2 + BORDER_LINES);
+
+        // 40 columns of text in an 18-column textarea wraps onto 3 rows.
+        composer.handle_paste("a".repeat(40));
+        assert_eq!(composer.calculate_required_height(&area), 3 + BORDER_LINES);
+
+        // Wide characters take two columns each: 10 of them need 2 rows.
+        let (mut composer, _rx) = make_composer();
+        composer.handle_paste("日".repeat(10));
+        assert_eq!(composer.calculate_required_height(&area), 2 + BORDER_LINES);
+    }
+
+    #[test]
+    fn height_is_capped_and_page_keys_move_through_content() {
+        let (mut composer, _rx) = make_composer();
+        let text = (0..100)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        composer.handle_paste(text);
+
+        let area = Rect::new(0, 0, 40, 50);
+        let height = composer.calculate_required_height(&area);
+        assert_eq!(height, MAX_TEXTAREA_ROWS as u16 + BORDER_LINES);
+
+        // Rendering keeps the cursor (on the last line) in view.
+        let area = Rect::new(0, 0, 40, height);
+        let mut buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut buf);
+        assert_eq!(buf[(1, height - 2)].symbol(), "9");
+        assert_eq!(buf[(2, height - 2)].symbol(), "9");
+
+        press(&mut composer, KeyCode::PageUp, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor().0, 99 - MAX_TEXTAREA_ROWS);
+        let mut buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut buf);
+        assert_eq!(buf[(1, 1)].symbol(), "8");
+        assert_eq!(buf[(2, 1)].symbol(), "9");
+    }
+
+    #[tokio::test]
+    async fn text_changes_are_reported_once_per_burst() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "hello");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "yo");
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let events: Vec<(usize, usize)> = rx
+            .try_iter()
+            .filter_map(|event| match event {
+                AppEvent::ComposerTextChanged { chars, lines } => Some((chars, lines)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(events, vec![(8, 2)]);
+    }
+
+    #[test]
+    fn readline_shortcuts_edit_the_current_line() {
+        let ctrl = |composer: &mut ChatComposer, c: char| {
+            press(composer, KeyCode::Char(c), KeyModifiers::CONTROL);
+        };
+
+        // Ctrl+W deletes the word before the cursor.
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "hello brave world");
+        ctrl(&mut composer, 'w');
+        assert_eq!(text_of(&composer), "hello brave ");
+
+        // Ctrl+A / Ctrl+E move to the start / end of the line.
+        ctrl(&mut composer, 'a');
+        type_str(&mut composer, ">");
+        ctrl(&mut composer, 'e');
+        type_str(&mut composer, "<");
+        assert_eq!(text_of(&composer), ">hello brave <");
+
+        // Ctrl+U deletes from the cursor to the start of the line.
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "hello world");
+        for _ in 0.."world".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+        }
+        ctrl(&mut composer, 'u');
+        assert_eq!(text_of(&composer), "world");
+
+        // Ctrl+K deletes from the cursor to the end of the line, and the
+        // deletion is undoable.
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "hello world");
+        for _ in 0.." world".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+        }
+        ctrl(&mut composer, 'k');
+        assert_eq!(text_of(&composer), "hello");
+        ctrl(&mut composer, 'z');
+        assert_eq!(text_of(&composer), "hello world");
+    }
+
+    #[test]
+    fn ctrl_r_searches_persistent_history() {
+        let (mut composer, rx) = make_composer();
+        composer.set_history_metadata(1, 3);
+        type_str(&mut composer, "draft");
+
+        let requested = |rx: &Receiver<AppEvent>| -> Vec<usize> {
+            rx.try_iter()
+                .filter_map(|event| match event {
+                    AppEvent::CodexOp(Op::GetHistoryEntryRequest { offset, .. }) => Some(offset),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        press(&mut composer, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "fo");
+        assert_eq!(requested(&rx), vec![2]);
+
+        // The newest entry does not match, so the search moves on to the
+        // next older one and previews it once it arrives.
+        composer.on_history_entry_response(1, 2, Some("bar".into()));
+        assert_eq!(requested(&rx), vec![1]);
+        composer.on_history_entry_response(1, 1, Some("foo bar".into()));
+        assert_eq!(text_of(&composer), "foo bar");
+        assert_eq!(
+            composer.history.search_prompt().as_deref(),
+            Some("(reverse-i-search)'fo'")
+        );
+
+        // Esc cancels and restores the text typed before the search.
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "draft");
+        assert!(!composer.history.is_searching());
+
+        // Enter accepts the (now cached) match.
+        press(&mut composer, KeyCode::Char('r'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "foo");
+        assert_eq!(text_of(&composer), "foo bar");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "foo bar");
+        assert!(!composer.history.is_searching());
+    }
+
+    #[test]
+    fn esc_closes_popup_before_arming_clear() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "/co");
+        assert!(composer.is_popup_visible());
+
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(!composer.is_popup_visible());
+        assert_eq!(text_of(&composer), "/co");
+
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/co");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn set_text_round_trips_and_syncs_popup() {
+        let (mut composer, _rx) = make_composer();
+        composer.set_text("first line\nsecond");
+        assert_eq!(composer.text(), "first line\nsecond");
+        assert_eq!(composer.textarea.cursor(), (1, "second".len()));
+        assert!(!composer.is_popup_visible());
+
+        composer.set_text("/qu");
+        assert!(composer.is_popup_visible());
+        composer.set_text("");
+        assert!(!composer.is_popup_visible());
+    }
+
+    #[test]
+    fn placeholder_is_only_shown_while_empty() {
+        let render = |composer: &ChatComposer| -> String {
+            let area = Rect::new(0, 0, 40, 3);
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            (1..area.width - 1).map(|x| buf[(x, 1)].symbol()).collect()
+        };
+
+        let (mut composer, _rx) = make_composer();
+        assert!(render(&composer).contains("send a message"));
+
+        composer.set_placeholder("ask o3 anything");
+        assert!(render(&composer).contains("ask o3 anything"));
+
+        type_str(&mut composer, "hi");
+        composer.set_placeholder("something else");
+        assert_eq!(text_of(&composer), "hi");
+        assert_eq!(render(&composer).trim_end(), "hi");
+    }
+
+    #[test]
+    fn disabled_composer_ignores_submit() {
+        let (mut composer, rx) = make_composer();
+        composer.set_enabled(false);
+        type_str(&mut composer, "later");
+
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "later");
+        assert!(rx.try_recv().is_err());
+
+        composer.set_enabled(true);
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "later"));
+    }
+
+    #[test]
+    fn pasted_image_path_is_attached_not_inserted() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let image = dir.path().join("shot.png");
+        std::fs::write(&image, b"png").expect("write image");
+
+        let (mut composer, rx) = make_composer();
+        assert!(composer.handle_paste(format!("'{}'", image.display())));
+        assert_eq!(text_of(&composer), "");
+        assert!(matches!(rx.try_recv(), Ok(AppEvent::AttachImage(path)) if path == image));
+
+        type_str(&mut composer, "what is this?");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        let InputResult::Submitted {
+            text, image_paths, ..
+        } = result
+        else {
+            panic!("expected a submission");
+        };
+        assert_eq!(text, "what is this?");
+        assert_eq!(image_paths, vec![image]);
+    }
+
+    #[test]
+    fn tab_completes_model_argument() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            models: vec!["o3".to_string(), "o4-mini".to_string()],
+            ..Default::default()
+        });
+        type_str(&mut composer, "/model o4");
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/model o4-mini");
+    }
+
+    #[test]
+    fn height_uses_grapheme_widths() {
+        let area = Rect::new(0, 0, 20, 10);
+        // The textarea is 18 columns wide; each sample is 20 columns.
+        for sample in [
+            "你好".repeat(5),
+            "👩\u{200d}💻".repeat(10),
+            "e\u{301}".repeat(20),
+        ] {
+            let (mut composer, _rx) = make_composer();
+            composer.handle_paste(sample.clone());
+            assert_eq!(
+                composer.calculate_required_height(&area),
+                2 + BORDER_LINES,
+                "{sample}"
+            );
+
+            // Rendering (including the cursor) must not panic.
+            let render_area = Rect::new(0, 0, 20, 4);
+            let mut buf = Buffer::empty(render_area);
+            (&composer).render_ref(render_area, &mut buf);
+            press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+            (&composer).render_ref(render_area, &mut buf);
+        }
+    }
+
+    #[test]
+    fn busy_submit_queues_and_alt_submit_interrupts() {
+        let (mut composer, rx) = make_composer();
+
+        // Idle: both keys send without side events.
+        type_str(&mut composer, "one");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "one"));
+        type_str(&mut composer, "two");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::ALT);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "two"));
+        for _ in 0..2 {
+            assert!(matches!(rx.try_recv(), Ok(AppEvent::RecordHistory(_))));
+        }
+        assert!(rx.try_recv().is_err());
+
+        composer.set_busy(true);
+        type_str(&mut composer, "three");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Queued(text) if text == "three"));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(AppEvent::QueueMessage { text, .. }) if text == "three"
+        ));
+        assert!(matches!(rx.try_recv(), Ok(AppEvent::RecordHistory(_))));
+        assert_eq!(text_of(&composer), "");
+
+        type_str(&mut composer, "four");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::ALT);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "four"));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(AppEvent::CodexOp(Op::Interrupt))
+        ));
+        assert!(matches!(rx.try_recv(), Ok(AppEvent::RecordHistory(_))));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn queued_count_is_shown_in_border() {
+        let (mut composer, _rx) = make_composer();
+        let area = Rect::new(0, 0, 40, 3);
+        let top_row = |composer: &ChatComposer| {
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            (0..area.width)
+                .map(|x| buf[(x, 0)].symbol().to_string())
+                .collect::<String>()
+        };
+        assert!(!top_row(&composer).contains("queued"));
+        composer.set_queued_messages(2);
+        assert!(top_row(&composer).contains(" 2 queued "));
+        composer.set_queued_messages(1);
+        assert!(top_row(&composer).contains(" 1 queued "));
+        composer.set_queued_messages(0);
+        assert!(!top_row(&composer).contains("queued"));
+    }
+
+    #[test]
+    fn paste_strips_escape_sequences() {
+        let (mut composer, _rx) = make_composer();
+        assert!(composer.handle_paste("\u{1b}[32mgreen\u{1b}[0m\r\nnext".to_string()));
+        assert_eq!(text_of(&composer), "green\nnext");
+        assert!(!composer.handle_paste("\u{1b}[2J".to_string()));
+    }
+
+    #[test]
+    fn command_alias_expands_to_canonical_name() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "/q");
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/quit ");
+
+        composer.set_text("/exit");
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/quit ");
+    }
+
+    #[test]
+    fn history_navigation_only_at_the_edges_of_a_multiline_draft() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "sent");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+
+        composer.set_text("one\ntwo\nthree");
+        assert_eq!(composer.textarea.cursor(), (2, 5));
+
+        // Down on the last row with nothing newer to show leaves the draft.
+        press(&mut composer, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "one\ntwo\nthree");
+
+        // Up moves through the draft until the very start of the text.
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (1, 3));
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (0, 3));
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "one\ntwo\nthree");
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "sent");
+
+        // Down from the recalled entry's last row returns to the draft, and
+        // from the middle row it only moves the cursor.
+        press(&mut composer, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "one\ntwo\nthree");
+        assert_eq!(composer.textarea.cursor(), (0, 0));
+        press(&mut composer, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (1, 0));
+        press(&mut composer, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (2, 0));
+        assert_eq!(text_of(&composer), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn esc_behavior_controls_clearing() {
+        let with_esc = |esc_behavior| {
+            make_composer_with_config(ChatComposerConfig {
+                esc_behavior,
+                ..Default::default()
+            })
+        };
+
+        let (mut composer, _rx) = with_esc(EscBehavior::DoubleClear);
+        type_str(&mut composer, "text");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "text");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "");
+
+        let (mut composer, _rx) = with_esc(EscBehavior::SingleClear);
+        type_str(&mut composer, "text");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "");
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "text");
+
+        let (mut composer, _rx) = with_esc(EscBehavior::Disabled);
+        type_str(&mut composer, "text");
+        for _ in 0..3 {
+            press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        }
+        assert_eq!(text_of(&composer), "text");
+        assert!(!composer.escape_armed);
+    }
+
+    #[test]
+    fn border_hint_reflects_esc_behavior() {
+        assert_eq!(
+            submit_hint(SubmitMode::EnterSends, EscBehavior::DoubleClear),
+            "Enter to send | Esc Esc to clear | Ctrl+D to quit | Shift+Enter / Ctrl+J for newline"
+        );
+        assert_eq!(
+            submit_hint(SubmitMode::EnterSends, EscBehavior::SingleClear),
+            "Enter to send | Esc to clear | Ctrl+D to quit | Shift+Enter / Ctrl+J for newline"
+        );
+        assert_eq!(
+            submit_hint(
+                SubmitMode::EnterNewlineCtrlEnterSends,
+                EscBehavior::Disabled
+            ),
+            "Ctrl+Enter to send | Ctrl+D to quit | Enter for newline"
+        );
+    }
+
+    #[test]
+    fn fenced_rust_block_gets_keyword_styling() {
+        let render_fn_cell = |highlight_code_blocks| {
+            let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+                highlight_code_blocks,
+                ..Default::default()
+            });
+            composer.set_text("```rust\nfn main() {}\n```");
+            let area = Rect::new(0, 0, 30, 5);
+            let mut buf = Buffer::empty(area);
+            (&composer).render_ref(area, &mut buf);
+            // Second text row, inside the border.
+            assert_eq!(buf[(1, 2)].symbol(), "f");
+            buf[(1, 2)].fg
+        };
+        assert_eq!(render_fn_cell(true), Color::Magenta);
+        assert_eq!(render_fn_cell(false), Color::Reset);
+    }
+
+    #[test]
+    fn each_non_empty_submission_is_recorded_once() {
+        let (mut composer, rx) = make_composer();
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(rx.try_recv().is_err());
+
+        type_str(&mut composer, "hello");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(AppEvent::RecordHistory(text)) if text == "hello"
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn enter_submits_unknown_command_as_text() {
+        for submit_mode in [
+            SubmitMode::EnterSends,
+            SubmitMode::EnterNewlineCtrlEnterSends,
+        ] {
+            let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+                submit_mode,
+                ..Default::default()
+            });
+            type_str(&mut composer, "/notacommand");
+            assert!(composer.is_popup_visible());
+            let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+            assert!(
+                matches!(result, InputResult::Submitted { text, .. } if text == "/notacommand")
+            );
+        }
+    }
+
+    #[test]
+    fn large_paste_becomes_a_placeholder() {
+        let (mut composer, rx) = make_composer_with_config(ChatComposerConfig {
+            paste_attachment_lines: Some(3),
+            ..Default::default()
+        });
+
+        // At the threshold the paste is inlined.
+        composer.handle_paste("1\n2\n3".to_string());
+        assert_eq!(text_of(&composer), "1\n2\n3");
+        assert!(rx.try_recv().is_err());
+
+        composer.set_text("see ");
+        composer.handle_paste("1\n2\n3\n4".to_string());
+        assert_eq!(text_of(&composer), "see [pasted 4 lines]");
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(AppEvent::AttachText { content, suggested_name, placeholder })
+                if content == "1\n2\n3\n4"
+                    && suggested_name == "paste-1.txt"
+                    && placeholder == "[pasted 4 lines]"
+        ));
+
+        // Ctrl+Z inlines the paste instead.
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "see 1\n2\n3\n4");
+    }
+
+    #[test]
+    fn undoing_a_large_paste_withdraws_its_attachment() {
+        let (mut composer, rx) = make_composer_with_config(ChatComposerConfig {
+            paste_attachment_lines: Some(3),
+            ..Default::default()
+        });
+        let attached = |rx: &Receiver<AppEvent>| -> Vec<String> {
+            rx.try_iter()
+                .filter_map(|e| match e {
+                    AppEvent::AttachText { suggested_name, .. } => {
+                        Some(format!("+{suggested_name}"))
+                    }
+                    AppEvent::DetachText { suggested_name } => Some(format!("-{suggested_name}")),
+                    _ => None,
+                })
+                .collect()
+        };
+        type_str(&mut composer, "see ");
+        composer.handle_paste("1\n2\n3\n4".to_string());
+        assert_eq!(attached(&rx), vec!["+paste-1.txt"]);
+
+        // Inlining withdraws the attachment, and a second Ctrl+Z goes back
+        // to the text from before the paste.
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "see 1\n2\n3\n4");
+        assert_eq!(attached(&rx), vec!["-paste-1.txt"]);
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "see ");
+        assert!(attached(&rx).is_empty());
+
+        // Redoing back to the placeholder attaches the paste again.
+        press(&mut composer, KeyCode::Char('y'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('y'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "see [pasted 4 lines]");
+        assert_eq!(attached(&rx), vec!["+paste-1.txt"]);
+
+        // Sending the message does not withdraw what it carries.
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { .. }));
+        assert!(attached(&rx).is_empty());
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert!(attached(&rx).is_empty());
+    }
+
+    #[test]
+    fn scroll_indicator_only_shown_when_content_overflows() {
+        let area = Rect::new(0, 0, 20, MAX_TEXTAREA_ROWS as u16 + BORDER_LINES);
+        let render = |composer: &ChatComposer| {
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            let row = |y| {
+                (0..area.width)
+                    .map(|x| buf[(x, y)].symbol().to_string())
+                    .collect::<String>()
+            };
+            let right_edge = (1..area.height - 1)
+                .map(|y| buf[(area.width - 1, y)].symbol().to_string())
+                .collect::<String>();
+            (row(0), right_edge)
+        };
+
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "fits");
+        let (top, right_edge) = render(&composer);
+        assert!(!top.contains('/'));
+        assert_eq!(right_edge, "│".repeat(MAX_TEXTAREA_ROWS));
+
+        let text = (0..100)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        composer.set_text(&text);
+        let (top, right_edge) = render(&composer);
+        assert!(top.ends_with(" 100/100 ╮"), "{top}");
+        assert_eq!(
+            right_edge,
+            format!("{}┃", "│".repeat(MAX_TEXTAREA_ROWS - 1))
+        );
+
+        composer.textarea.move_cursor(CursorMove::Top);
+        let (top, right_edge) = render(&composer);
+        assert!(top.ends_with(" 1/100 ╮"), "{top}");
+        assert_eq!(
+            right_edge,
+            format!("┃{}", "│".repeat(MAX_TEXTAREA_ROWS - 1))
+        );
+    }
+
+    #[test]
+    fn pasted_file_path_is_attached_as_a_file() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, b"notes").expect("write file");
+
+        let (mut composer, rx) = make_composer();
+        assert!(composer.handle_paste(notes.display().to_string()));
+        assert_eq!(text_of(&composer), "");
+        assert!(matches!(rx.try_recv(), Ok(AppEvent::AttachFile(path)) if path == notes));
+
+        let area = Rect::new(0, 0, 40, 3);
+        let mut buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut buf);
+        let top: String = (0..area.width).map(|x| buf[(x, 0)].symbol()).collect();
+        assert!(top.contains("[file: notes.txt]"), "{top}");
+
+        // A path that does not exist is inserted literally.
+        let missing = dir.path().join("missing.txt").display().to_string();
+        composer.handle_paste(missing.clone());
+        assert_eq!(text_of(&composer), missing);
+
+        composer.set_text("see attached");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        let InputResult::Submitted {
+            text,
+            image_paths,
+            file_paths,
+            ..
+        } = result
+        else {
+            panic!("expected a submission");
+        };
+        assert_eq!(text, "see attached");
+        assert!(image_paths.is_empty());
+        assert_eq!(file_paths, vec![notes]);
+    }
+
+    #[test]
+    fn mode_prefix_is_stripped_on_submit() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "/ask how does X work");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, mode: PromptMode::Ask, .. } if text == "how does X work"
+        ));
+
+        // A bare prefix is completed rather than dispatched or submitted.
+        type_str(&mut composer, "/edit");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "/edit ");
+
+        composer.set_text("plain");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, mode: PromptMode::Default, .. } if text == "plain"
+        ));
+    }
+
+    #[test]
+    fn border_hint_shrinks_to_fit() {
+        let (composer, _rx) = make_composer();
+        let bottom_row = |width| {
+            let area = Rect::new(0, 0, width, 3);
+            let mut buf = Buffer::empty(area);
+            (&composer).render_ref(area, &mut buf);
+            (0..width)
+                .map(|x| buf[(x, 2)].symbol().to_string())
+                .collect::<String>()
+        };
+
+        let wide = bottom_row(120);
+        assert!(wide.ends_with(
+            "Enter to send | Esc Esc to clear | Ctrl+D to quit | Shift+Enter / Ctrl+J for newline╯"
+        ));
+        assert_eq!(
+            bottom_row(60),
+            format!("╰{}Enter send · Ctrl+D quit╯", "─".repeat(34))
+        );
+        assert_eq!(bottom_row(20), format!("╰{}╯", "─".repeat(18)));
+    }
+
+    #[test]
+    fn clearing_text_emits_one_notice() {
+        let (mut composer, rx) = make_composer();
+        press(&mut composer, KeyCode::Char(' '), KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(composer.textarea.is_empty());
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::Notice(_))));
+
+        type_str(&mut composer, "draft");
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(composer.textarea.is_empty());
+        let notices = rx
+            .try_iter()
+            .filter(|e| matches!(e, AppEvent::Notice(_)))
+            .count();
+        assert_eq!(notices, 1);
+    }
+
+    #[test]
+    fn tab_completes_a_unique_prefix_match_in_full() {
+        let (mut composer, _rx) = make_composer();
+        // `edit` and `quit` match fuzzily, only `toggle-mouse-mode` by prefix.
+        type_str(&mut composer, "/t");
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/toggle-mouse-mode ");
+    }
+
+    #[test]
+    fn tab_cycles_through_ambiguous_matches() {
+        let (mut composer, _rx) = make_composer();
+        // Matches `edit` and `quit`, neither by prefix.
+        type_str(&mut composer, "/it");
+        let selected = |c: &ChatComposer| {
+            c.command_popup
+                .as_ref()
+                .and_then(|p| p.selected_command().copied())
+        };
+        let first = selected(&composer);
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_ne!(selected(&composer), first);
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(selected(&composer), first);
+        assert_eq!(text_of(&composer), "/it");
+    }
+
+    #[test]
+    fn tab_on_a_bare_slash_completes_the_first_command() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "/");
+        assert!(composer.is_popup_visible());
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/ask ");
+    }
+
+    #[test]
+    fn cursor_is_only_drawn_while_focused() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "hi");
+        let cursor_cell = |composer: &ChatComposer| {
+            let area = Rect::new(0, 0, 20, 3);
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            buf[(3, 1)].modifier
+        };
+
+        assert!(composer.has_input_focus);
+        assert!(cursor_cell(&composer).contains(Modifier::REVERSED));
+
+        composer.set_input_focus(false);
+        assert!(!composer.has_input_focus);
+        assert!(!cursor_cell(&composer).contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn newline_keeps_indentation_inside_code_fences() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            auto_indent: true,
+            ..Default::default()
+        });
+        composer.set_text("```rust\nfn main() {\n    let x = 1;");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "x");
+        assert_eq!(
+            text_of(&composer),
+            "```rust\nfn main() {\n    let x = 1;\n    x"
+        );
+    }
+
+    #[test]
+    fn newline_indentation_is_scoped_and_optional() {
+        let auto = ChatComposerConfig {
+            auto_indent: true,
+            ..Default::default()
+        };
+        // Outside of a fence.
+        let (mut composer, _rx) = make_composer_with_config(auto.clone());
+        composer.set_text("  - item");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "  - item\n");
+
+        // After the closing fence.
+        composer.set_text("```\n  code\n```");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "```\n  code\n```\n");
+
+        // Disabled.
+        let (mut composer, _rx) = make_composer();
+        composer.set_text("```\n  code");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "```\n  code\n");
+    }
+
+    #[test]
+    fn ctrl_arrows_move_by_word() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "foo bar baz");
+        let mut jump = |code| {
+            press(&mut composer, code, KeyModifiers::CONTROL);
+            composer.textarea.cursor().1
+        };
+        assert_eq!(jump(KeyCode::Left), 8);
+        assert_eq!(jump(KeyCode::Left), 4);
+        assert_eq!(jump(KeyCode::Left), 0);
+        assert_eq!(jump(KeyCode::Left), 0);
+        assert_eq!(jump(KeyCode::Right), 3);
+        assert_eq!(jump(KeyCode::Right), 7);
+        assert_eq!(jump(KeyCode::Right), 11);
+        assert_eq!(jump(KeyCode::Right), 11);
+    }
+
+    #[test]
+    fn submit_trims_trailing_whitespace_and_blank_lines() {
+        let (mut composer, _rx) = make_composer();
+        composer.set_text("fix this  \nplease \n\n  \n");
+        let InputResult::Submitted { text, .. } =
+            press(&mut composer, KeyCode::Enter, KeyModifiers::NONE)
+        else {
+            panic!("expected a submission");
+        };
+        assert_eq!(text, "fix this\nplease");
+    }
+
+    #[test]
+    fn submit_keeps_whitespace_inside_code_fences() {
+        let (mut composer, _rx) = make_composer();
+        composer.set_text("look: \n```md \nline one  \n    \n```\n");
+        let InputResult::Submitted { text, .. } =
+            press(&mut composer, KeyCode::Enter, KeyModifiers::NONE)
+        else {
+            panic!("expected a submission");
+        };
+        assert_eq!(text, "look:\n```md\nline one  \n    \n```");
+    }
+
+    #[test]
+    fn registered_commands_replace_the_built_in_ones() {
+        let (mut composer, rx) = make_composer();
+        composer.set_commands(vec![SlashCommand::New]);
+        type_str(&mut composer, "/");
+        let popup = composer.command_popup.as_ref().expect("popup");
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::New));
+
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(rx.try_iter().any(|e| matches!(
+            e,
+            AppEvent::DispatchCommand {
+                command: SlashCommand::New,
+                ..
+            }
+        )));
+
+        // An unregistered command is submitted as text.
+        type_str(&mut composer, "/quit");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "/quit"));
+    }
+
+    #[test]
+    fn tips_are_shown_below_the_placeholder_while_empty_and_focused() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            file_search_root: Some(PathBuf::from(".")),
+            ..Default::default()
+        });
+        let render = |composer: &ChatComposer| -> Vec<String> {
+            let area = Rect::new(
+                0,
+                0,
+                60,
+                composer.calculate_required_height(&Rect::new(0, 0, 60, 20)),
+            );
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            (1..area.height - 1)
+                .map(|y| {
+                    (1..area.width - 1)
+                        .map(|x| buf[(x, y)].symbol())
+                        .collect::<String>()
+                        .trim_end()
+                        .to_string()
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            render(&composer),
+            vec![
+                " send a message".to_string(),
+                " / for commands • @ to reference files".to_string(),
+            ]
+        );
+
+        composer.set_input_focus(false);
+        assert_eq!(render(&composer), vec![" send a message".to_string()]);
+
+        composer.set_input_focus(true);
+        type_str(&mut composer, "h");
+        assert_eq!(render(&composer), vec!["h".to_string()]);
+    }
+
+    /// Records copied text instead of touching the real clipboard.
+    #[derive(Clone, Default)]
+    struct RecordingClipboard(std::rc::Rc<std::cell::RefCell<Vec<String>>>);
+
+    impl Clipboard for RecordingClipboard {
+        fn set_text(&mut self, text: &str) -> std::io::Result<()> {
+            self.0.borrow_mut().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shift_arrows_select_text() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "one\ntwo");
+        for _ in 0..2 {
+            press(&mut composer, KeyCode::Left, KeyModifiers::SHIFT);
+        }
+        assert_eq!(composer.textarea.selection_range(), Some(((1, 1), (1, 3))));
+
+        // Shift+Up extends the selection instead of recalling history.
+        press(&mut composer, KeyCode::Up, KeyModifiers::SHIFT);
+        assert_eq!(composer.textarea.selection_range(), Some(((0, 1), (1, 3))));
+    }
+
+    #[test]
+    fn copy_takes_the_selection_or_the_current_line() {
+        let (mut composer, _rx) = make_composer();
+        let clipboard = RecordingClipboard::default();
+        composer.clipboard = Box::new(clipboard.clone());
+
+        type_str(&mut composer, "first\nsecond line");
+        assert!(composer.copy_to_clipboard());
+        for _ in 0.."line".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::SHIFT);
+        }
+        assert!(composer.copy_to_clipboard());
+        press(&mut composer, KeyCode::Up, KeyModifiers::SHIFT);
+        assert!(composer.copy_to_clipboard());
+        assert_eq!(
+            *clipboard.0.borrow(),
+            vec![
+                "second line".to_string(),
+                "line".to_string(),
+                "\nsecond line".to_string(),
+            ]
+        );
+
+        // Without a selection, Ctrl+C interrupts a running task instead.
+        press(&mut composer, KeyCode::End, KeyModifiers::NONE);
+        composer.set_busy(true);
+        assert!(!composer.copy_to_clipboard());
+    }
+
+    #[test]
+    fn help_with_a_command_shows_help_instead_of_dispatching() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "/help model");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "");
+
+        let events: Vec<AppEvent> = rx.try_iter().collect();
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, AppEvent::ShowCommandHelp(SlashCommand::Model)))
+        );
+        assert!(
+            !events
+                .iter()
+                .any(|e| matches!(e, AppEvent::DispatchCommand { .. }))
+        );
+    }
+
+    #[test]
+    fn height_changed_until_measured_again() {
+        let (mut composer, _rx) = make_composer();
+        let area = Rect::new(0, 0, 40, 20);
+        assert!(composer.height_changed());
+        let empty = composer.calculate_required_height(&area);
+        assert!(!composer.height_changed());
+
+        type_str(&mut composer, "a");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "b");
+        press(&mut composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        assert!(composer.height_changed());
+        let tall = composer.calculate_required_height(&area);
+        assert!(tall > empty);
+        assert!(!composer.height_changed());
+
+        // Typing on the same line does not change the height.
+        type_str(&mut composer, "c");
+        assert!(!composer.height_changed());
+
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::NONE);
+        assert!(composer.height_changed());
+        assert!(composer.calculate_required_height(&area) < tall);
+        assert!(!composer.height_changed());
+    }
 
-            // Forward *only* the first line since `CommandPopup` only needs
-            // the command token.
-            popup.on_composer_text_change(first_line.to_string());
-        } else if self.command_popup.is_some() {
-            // Remove popup when '/' is no longer the first character.
-            self.command_popup = None;
+    #[test]
+    fn shift_enter_inserts_a_newline_in_either_submit_mode() {
+        for submit_mode in [
+            SubmitMode::EnterSends,
+            SubmitMode::EnterNewlineCtrlEnterSends,
+        ] {
+            let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+                submit_mode,
+                ..Default::default()
+            });
+            type_str(&mut composer, "a");
+            let result = press(&mut composer, KeyCode::Enter, KeyModifiers::SHIFT);
+            assert!(matches!(result, InputResult::None));
+            type_str(&mut composer, "b");
+            assert_eq!(text_of(&composer), "a\nb");
         }
     }
 
-    pub fn calculate_required_height(&self, area: &Rect) -> u16 {
-        let rows = self.textarea.lines().len().max(MIN_TEXTAREA_ROWS);
-        let num_popup_rows = if let Some(popup) = &self.command_popup {
-            popup.calculate_required_height(area)
-        } else {
-            0
+    #[test]
+    fn ctrl_p_and_ctrl_n_scroll_the_transcript() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "draft");
+        let _ = rx.try_iter().count();
+
+        press(&mut composer, KeyCode::Char('p'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('p'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('n'), KeyModifiers::CONTROL);
+        let directions: Vec<ScrollDirection> = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                AppEvent::ScrollTranscript(direction) => Some(direction),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            directions,
+            vec![
+                ScrollDirection::Up,
+                ScrollDirection::Up,
+                ScrollDirection::Down
+            ]
+        );
+        assert_eq!(text_of(&composer), "draft");
+        assert_eq!(composer.textarea.cursor(), (0, 5));
+    }
+
+    #[test]
+    fn undelivered_actions_leave_the_composer_untouched() {
+        let (mut composer, rx) = make_composer();
+        drop(rx);
+
+        composer.set_text("/new");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Undelivered));
+        assert_eq!(text_of(&composer), "/new");
+
+        composer.set_busy(true);
+        composer.set_text("later");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Undelivered));
+        assert_eq!(text_of(&composer), "later");
+
+        // The message that was not queued is not in the history either.
+        composer.set_text("");
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn text_beyond_max_chars_is_flagged() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            max_chars: Some(5),
+            ..Default::default()
+        });
+        let render = |composer: &ChatComposer| {
+            let area = Rect::new(0, 0, 30, 3);
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            buf
         };
+        let top_row = |buf: &Buffer| -> String { (0..30).map(|x| buf[(x, 0)].symbol()).collect() };
+        let red = |buf: &Buffer, x: u16| buf[(x, 1)].fg == Color::Red;
+
+        type_str(&mut composer, "hello");
+        let buf = render(&composer);
+        assert!(!(1..=5).any(|x| red(&buf, x)));
+        assert!(!top_row(&buf).contains("/5"));
+
+        type_str(&mut composer, "!");
+        let buf = render(&composer);
+        assert!(!red(&buf, 5));
+        assert!(red(&buf, 6));
+        assert!(top_row(&buf).contains(" 6/5 "));
+    }
+
+    #[test]
+    fn over_limit_submission_is_blocked_or_split() {
+        let (mut composer, rx) = make_composer_with_config(ChatComposerConfig {
+            max_chars: Some(10),
+            ..Default::default()
+        });
+        type_str(&mut composer, "aaaa bbbb cccc");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "aaaa bbbb cccc");
+        assert!(rx.try_iter().any(|e| matches!(e, AppEvent::Notice(_))));
+
+        let (mut composer, rx) = make_composer_with_config(ChatComposerConfig {
+            max_chars: Some(10),
+            over_limit: OverLimitBehavior::Split,
+            ..Default::default()
+        });
+        type_str(&mut composer, "aaaa bbbb cccc");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "aaaa bbbb"));
+        let queued: Vec<String> = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                AppEvent::QueueMessage { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(queued, vec!["cccc".to_string()]);
+    }
+
+    #[test]
+    fn split_parts_keep_the_mode_and_attachments_stay_on_the_first() {
+        let (mut composer, rx) = make_composer_with_config(ChatComposerConfig {
+            max_chars: Some(15),
+            over_limit: OverLimitBehavior::Split,
+            ..Default::default()
+        });
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, b"notes").expect("write file");
+        composer.handle_paste(notes.display().to_string());
+        type_str(&mut composer, "/ask aaaa bbbb cccc");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, mode: PromptMode::Ask, file_paths, .. }
+                if text == "aaaa bbbb" && file_paths == vec![notes.clone()]
+        ));
+        let events: Vec<AppEvent> = rx.try_iter().collect();
+        let queued: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                AppEvent::QueueMessage {
+                    text,
+                    image_paths,
+                    file_paths,
+                    mode,
+                } => Some((text.as_str(), image_paths.len() + file_paths.len(), *mode)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(queued, vec![("cccc", 0, PromptMode::Ask)]);
+        let recorded: Vec<&str> = events
+            .iter()
+            .filter_map(|e| match e {
+                AppEvent::RecordHistory(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(recorded, vec!["/ask aaaa bbbb cccc"]);
+    }
+
+    #[test]
+    fn split_message_breaks_at_whitespace_when_possible() {
+        assert_eq!(
+            split_message("aaaa bbbb cccc", 10),
+            vec!["aaaa bbbb", "cccc"]
+        );
+        assert_eq!(split_message("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(split_message("ab\ncd ef", 5), vec!["ab\ncd", "ef"]);
+    }
+
+    /// A composer holding lines "0" to "99", rendered with a 5-row textarea,
+    /// and with a submitted message in history.
+    fn tall_composer() -> ChatComposer<'static> {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "sent");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        let text = (0..100)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        composer.handle_paste(text);
+        let area = Rect::new(0, 0, 20, 5 + BORDER_LINES);
+        let mut buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut buf);
+        composer
+    }
 
-        rows as u16 + BORDER_LINES + num_popup_rows
+    #[test]
+    fn home_jumps_to_the_start_of_the_text() {
+        let mut composer = tall_composer();
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (0, 0));
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.lines().len(), 100);
     }
 
-    fn update_border(&mut self, has_focus: bool) {
-        struct BlockState {
-            right_title: Line<'static>,
-            border_style: Style,
+    #[test]
+    fn end_jumps_to_the_end_of_the_text() {
+        let mut composer = tall_composer();
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::End, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (99, 2));
+        press(&mut composer, KeyCode::End, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.lines().len(), 100);
+    }
+
+    #[test]
+    fn page_up_moves_by_the_viewport_height() {
+        let mut composer = tall_composer();
+        press(&mut composer, KeyCode::PageUp, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (94, 2));
+        for _ in 0..30 {
+            press(&mut composer, KeyCode::PageUp, KeyModifiers::NONE);
+        }
+        // Paging stops at the first line instead of recalling history.
+        assert_eq!(composer.textarea.cursor(), (0, 1));
+        assert_eq!(composer.textarea.lines().len(), 100);
+    }
+
+    #[test]
+    fn page_down_moves_by_the_viewport_height() {
+        let mut composer = tall_composer();
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::PageDown, KeyModifiers::NONE);
+        assert_eq!(composer.textarea.cursor(), (5, 0));
+        for _ in 0..30 {
+            press(&mut composer, KeyCode::PageDown, KeyModifiers::NONE);
         }
+        assert_eq!(composer.textarea.cursor(), (99, 0));
+        assert_eq!(composer.textarea.lines().len(), 100);
+    }
 
-        let bs = if has_focus {
-            BlockState {
-                right_title: Line::from("Enter to send | Ctrl+D to quit | Ctrl+J for newline")
-                    .alignment(Alignment::Right),
-                border_style: Style::default(),
+    #[test]
+    fn snapshot_round_trips_through_serde() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "first\nsecond");
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        let state = composer.snapshot();
+        assert_eq!(
+            state,
+            ComposerState {
+                text: "first\nsecond".to_string(),
+                cursor: (0, 5),
+                escape_armed: true,
+                popup_open: false,
             }
-        } else {
-            BlockState {
-                right_title: Line::from(""),
-                border_style: Style::default().dim(),
+        );
+
+        let json = serde_json::to_string(&state).expect("serialize");
+        let (mut restored, _rx) = make_composer();
+        restored.restore(serde_json::from_str(&json).expect("deserialize"));
+        assert_eq!(restored.snapshot(), state);
+
+        // A dismissed popup stays closed, an open one comes back.
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "/mo");
+        assert!(composer.snapshot().popup_open);
+        let (mut restored, _rx) = make_composer();
+        restored.restore(composer.snapshot());
+        assert!(restored.is_popup_visible());
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        restored.restore(composer.snapshot());
+        assert!(!restored.is_popup_visible());
+        assert_eq!(text_of(&restored), "/mo");
+    }
+
+    #[test]
+    fn code_command_inserts_a_fenced_block() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "/code rust");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "```rust\n\n```");
+        assert_eq!(composer.textarea.cursor(), (1, 0));
+        assert!(!composer.is_popup_visible());
+        assert!(
+            rx.try_iter()
+                .all(|e| !matches!(e, AppEvent::DispatchCommand { .. }))
+        );
+
+        type_str(&mut composer, "fn main() {}");
+        assert_eq!(text_of(&composer), "```rust\nfn main() {}\n```");
+    }
+
+    #[test]
+    fn no_color_renders_without_colors() {
+        let theme = ComposerTheme::for_no_color(Some(std::ffi::OsStr::new("1")));
+        assert_eq!(theme, ComposerTheme::plain());
+        assert_eq!(
+            ComposerTheme::for_no_color(Some(std::ffi::OsStr::new(""))),
+            ComposerTheme::default()
+        );
+
+        let (mut composer, _rx) = make_composer_with_theme(
+            ChatComposerConfig {
+                highlight_code_blocks: true,
+                max_chars: Some(8),
+                ..Default::default()
+            },
+            theme,
+        );
+        composer.handle_paste("```rust\nfn main() {}\n```".to_string());
+        press(&mut composer, KeyCode::Left, KeyModifiers::SHIFT);
+        let area = Rect::new(0, 0, 40, 8);
+        let mut buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut buf);
+        composer.set_text("/");
+        assert!(composer.is_popup_visible());
+        let mut popup_buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut popup_buf);
+
+        for buf in [&buf, &popup_buf] {
+            for cell in buf.content() {
+                assert_eq!((cell.fg, cell.bg), (Color::Reset, Color::Reset));
+            }
+        }
+    }
+
+    #[test]
+    fn dispatched_commands_carry_their_arguments() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "/model gpt-4o");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "");
+        let dispatched: Vec<(SlashCommand, String)> = rx
+            .try_iter()
+            .filter_map(|e| match e {
+                AppEvent::DispatchCommand { command, args } => Some((command, args)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            dispatched,
+            vec![(SlashCommand::Model, "gpt-4o".to_string())]
+        );
+
+        type_str(&mut composer, "/new");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(rx.try_iter().any(|e| matches!(
+            e,
+            AppEvent::DispatchCommand { command: SlashCommand::New, args } if args.is_empty()
+        )));
+    }
+
+    #[test]
+    fn command_args_are_the_text_after_the_command() {
+        assert_eq!(command_args("/model gpt-4o"), "gpt-4o");
+        assert_eq!(command_args("/model   gpt-4o \n"), "gpt-4o");
+        assert_eq!(command_args("/new"), "");
+    }
+
+    #[test]
+    fn multibyte_arguments_are_dispatched_intact() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "/model ñandú-模型 ✓");
+        let popup = composer.command_popup.as_ref().expect("popup");
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Model));
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/model ñandú-模型 ✓");
+
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(rx.try_iter().any(|e| matches!(
+            e,
+            AppEvent::DispatchCommand { command: SlashCommand::Model, args } if args == "ñandú-模型 ✓"
+        )));
+    }
+
+    #[test]
+    fn alternate_history_keys_leave_arrows_to_the_cursor() {
+        for (keys, older, newer) in [
+            (
+                HistoryNavKeys::CtrlArrows,
+                (KeyCode::Up, KeyModifiers::CONTROL),
+                (KeyCode::Down, KeyModifiers::CONTROL),
+            ),
+            (
+                HistoryNavKeys::AltPN,
+                (KeyCode::Char('p'), KeyModifiers::ALT),
+                (KeyCode::Char('n'), KeyModifiers::ALT),
+            ),
+        ] {
+            let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+                history_nav_keys: keys,
+                ..Default::default()
+            });
+            type_str(&mut composer, "sent");
+            press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+
+            // Bare Up only moves the cursor, even at the start of the text.
+            type_str(&mut composer, "one\ntwo");
+            press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+            assert_eq!(composer.textarea.cursor(), (0, 3));
+            press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+            press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+            assert_eq!(text_of(&composer), "one\ntwo");
+
+            // The dedicated keys recall history from anywhere in the text.
+            press(&mut composer, KeyCode::End, KeyModifiers::NONE);
+            press(&mut composer, older.0, older.1);
+            assert_eq!(text_of(&composer), "sent");
+            press(&mut composer, newer.0, newer.1);
+            assert_eq!(text_of(&composer), "one\ntwo");
+        }
+    }
+
+    #[test]
+    fn commands_and_mentions_are_rendered_as_live_tokens() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            file_search_root: Some(PathBuf::from("/nonexistent")),
+            ..Default::default()
+        });
+        composer.set_text("/model o3 @a.rs x@y\n@ @b\n```\n@c\n```");
+        let area = Rect::new(0, 0, 30, 7);
+        let mut buf = Buffer::empty(area);
+        composer.render_textarea(area, &mut buf);
+
+        let cyan = |x: u16, y: u16| buf[(x + 1, y + 1)].fg == Color::Cyan;
+        let styled_cols = |y: u16| -> Vec<u16> { (0..28).filter(|&x| cyan(x, y)).collect() };
+        // `/model` and `@a.rs`, but not the `@` inside `x@y`.
+        assert_eq!(styled_cols(0), vec![0, 1, 2, 3, 4, 5, 10, 11, 12, 13, 14]);
+        // A bare `@` is not a mention; `@b` is.
+        assert_eq!(styled_cols(1), vec![2, 3]);
+        // Nothing inside a fence.
+        assert!(styled_cols(3).is_empty());
+
+        composer.set_text("/nope @a");
+        let mut buf = Buffer::empty(area);
+        composer.render_textarea(area, &mut buf);
+        let styled: Vec<u16> = (0..28)
+            .filter(|&x| buf[(x + 1, 1)].fg == Color::Cyan)
+            .collect();
+        assert_eq!(styled, vec![6, 7]);
+    }
+
+    #[test]
+    fn ctrl_enter_sends_and_keeps_the_draft() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "Review this:\nfoo");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::CONTROL);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, keep_draft: true, .. } if text == "Review this:\nfoo"
+        ));
+        assert_eq!(text_of(&composer), "Review this:\nfoo");
+        assert_eq!(composer.textarea.cursor(), (0, 0));
+
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted {
+                keep_draft: false,
+                ..
+            }
+        ));
+        assert_eq!(text_of(&composer), "");
+
+        // With Ctrl+Enter sending, Ctrl+Shift+Enter keeps the draft.
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            submit_mode: SubmitMode::EnterNewlineCtrlEnterSends,
+            ..Default::default()
+        });
+        type_str(&mut composer, "again");
+        let result = press(
+            &mut composer,
+            KeyCode::Enter,
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert!(matches!(
+            result,
+            InputResult::Submitted {
+                keep_draft: true,
+                ..
             }
+        ));
+        assert_eq!(text_of(&composer), "again");
+    }
+
+    #[test]
+    fn unbalanced_fences_warn_without_blocking() {
+        let notices = |text: &str| {
+            let (mut composer, rx) = make_composer();
+            composer.handle_paste(text.to_string());
+            let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+            assert!(matches!(result, InputResult::Submitted { .. }));
+            rx.try_iter()
+                .filter(|e| matches!(e, AppEvent::Notice(_)))
+                .count()
         };
+        let (mut composer, _rx) = make_composer();
+        composer.handle_paste("see\n```rust\nfn main() {".to_string());
+        assert_eq!(composer.count_fences(), 1);
 
-        self.textarea.set_block(
-            ratatui::widgets::Block::default()
-                .title_bottom(bs.right_title)
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .border_style(bs.border_style),
+        assert_eq!(notices("see\n```rust\nfn main() {"), 1);
+        assert_eq!(notices("see\n```rust\nfn main() {}\n```"), 0);
+        assert_eq!(notices("no code"), 0);
+    }
+
+    #[test]
+    fn ctrl_shift_k_clears_only_the_current_line() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "one\ntwo\nthree");
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(composer.escape_armed);
+
+        press(
+            &mut composer,
+            KeyCode::Char('K'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert_eq!(text_of(&composer), "one\nthree");
+        assert_eq!(composer.textarea.cursor(), (1, 0));
+        assert!(!composer.escape_armed);
+
+        // A second Esc starts over instead of clearing everything.
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "one\nthree");
+
+        // The last line takes the preceding line break with it.
+        press(
+            &mut composer,
+            KeyCode::Char('k'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
         );
+        assert_eq!(text_of(&composer), "one");
+
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "one\nthree");
     }
 
-    pub(crate) fn is_command_popup_visible(&self) -> bool {
-        self.command_popup.is_some()
+    #[test]
+    fn esc_interrupts_a_busy_agent_instead_of_arming_the_clear() {
+        let interrupts = |rx: &Receiver<AppEvent>| {
+            rx.try_iter()
+                .filter(|e| matches!(e, AppEvent::InterruptAgent))
+                .count()
+        };
+
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "draft");
+        composer.set_busy(true);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(interrupts(&rx), 1);
+        assert!(!composer.escape_armed);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(interrupts(&rx), 1);
+        assert_eq!(text_of(&composer), "draft");
+
+        // Idle again: Esc arms the clear and a second one clears.
+        composer.set_busy(false);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(composer.escape_armed);
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "");
+        assert_eq!(interrupts(&rx), 0);
     }
-}
 
-impl WidgetRef for &ChatComposer<'_> {
-    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
-        if let Some(popup) = &self.command_popup {
-            let popup_height = popup.calculate_required_height(&area);
-
-            // Split the provided rect so that the popup is rendered at the
-            // *top* and the textarea occupies the remaining space below.
-            let popup_rect = Rect {
-                x: area.x,
-                y: area.y,
-                width: area.width,
-                height: popup_height.min(area.height),
-            };
+    #[test]
+    fn selection_response_is_inserted_at_the_cursor() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "Explain  please");
+        for _ in 0.." please".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+        }
+        assert!(composer.on_selection_response(Some("fn main() {}".to_string())));
+        assert_eq!(text_of(&composer), "Explain fn main() {} please");
 
-            let textarea_rect = Rect {
-                x: area.x,
-                y: area.y + popup_rect.height,
-                width: area.width,
-                height: area.height.saturating_sub(popup_rect.height),
-            };
+        assert!(!composer.on_selection_response(None));
+        assert_eq!(text_of(&composer), "Explain fn main() {} please");
+        assert!(rx.try_iter().any(|e| matches!(e, AppEvent::Notice(_))));
 
-            popup.render(popup_rect, buf);
-            self.textarea.render(textarea_rect, buf);
-        } else {
-            self.textarea.render(area, buf);
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "Explain  please");
+    }
+
+    #[test]
+    fn row_limits_bound_the_required_height() {
+        let (mut composer, _rx) = make_composer();
+        composer.set_row_limits(3, 4);
+        let area = Rect::new(0, 0, 40, 30);
+        assert_eq!(composer.calculate_required_height(&area), 3 + BORDER_LINES);
+
+        type_str(&mut composer, "hi");
+        assert_eq!(composer.calculate_required_height(&area), 3 + BORDER_LINES);
+
+        for _ in 0..10 {
+            press(&mut composer, KeyCode::Enter, KeyModifiers::SHIFT);
+        }
+        assert_eq!(composer.calculate_required_height(&area), 4 + BORDER_LINES);
+    }
+
+    #[test]
+    fn gg_and_shift_g_jump_through_long_text() {
+        let mut composer = tall_composer();
+        assert_eq!(composer.textarea.cursor(), (99, 2));
+
+        // Unless enabled, both keys are always text.
+        type_str(&mut composer, "G");
+        assert_eq!(composer.textarea.lines()[99], "99G");
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::NONE);
+
+        composer.config.jump_keys = true;
+        type_str(&mut composer, "gg");
+        assert_eq!(composer.textarea.cursor(), (0, 0));
+        assert_eq!(composer.textarea.lines()[99], "99");
+
+        type_str(&mut composer, "G");
+        assert_eq!(composer.textarea.cursor(), (99, 2));
+        assert_eq!(composer.textarea.lines().len(), 100);
+
+        // Right after typing, both keys are text.
+        type_str(&mut composer, "xgG");
+        assert_eq!(composer.textarea.lines()[99], "99xgG");
+    }
+
+    #[test]
+    fn parsed_submissions_split_prose_mentions_and_fences() {
+        use crate::bottom_pane::parsed_prompt::PromptSegment;
+
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            parsed_submissions: true,
+            ..Default::default()
+        });
+        composer.set_text("/ask why does @src/lib.rs fail?\n```text\npanicked at 3:9\n```");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        let InputResult::SubmittedParsed(parsed) = result else {
+            panic!("expected a parsed submission");
+        };
+        assert_eq!(parsed.mode, PromptMode::Ask);
+        assert_eq!(
+            parsed.text,
+            "why does @src/lib.rs fail?\n```text\npanicked at 3:9\n```"
+        );
+        assert_eq!(
+            parsed.segments,
+            vec![
+                PromptSegment::Text("why does ".to_string()),
+                PromptSegment::FileMention("src/lib.rs".to_string()),
+                PromptSegment::Text(" fail?".to_string()),
+                PromptSegment::CodeBlock {
+                    language: Some("text".to_string()),
+                    code: "panicked at 3:9".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn undoing_history_recall_restores_the_draft_and_keeps_browsing_usable() {
+        let (mut composer, _rx) = make_composer();
+        for message in ["first", "second"] {
+            type_str(&mut composer, message);
+            press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        }
+        type_str(&mut composer, "draft");
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "first");
+
+        // One undo per replacement.
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "second");
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "draft");
+
+        // Browsing starts over from the newest entry and returns to the draft.
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "second");
+        press(&mut composer, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "draft");
+
+        // Returning to the draft is itself undoable.
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "second");
+    }
+
+    #[test]
+    fn unsent_content_ignores_whitespace() {
+        let (mut composer, _rx) = make_composer();
+        assert!(!composer.has_unsent_content());
+
+        composer.set_text("  \n\t\n");
+        assert!(!composer.has_unsent_content());
+
+        composer.set_text(" \n draft");
+        assert!(composer.has_unsent_content());
+
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(!composer.has_unsent_content());
+    }
+
+    #[test]
+    fn column_edit_prefixes_every_selected_line() {
+        let (mut composer, _rx) = make_composer();
+        composer.set_text("milk\neggs\nbread\nsigned, me");
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Down, KeyModifiers::SHIFT);
+        press(&mut composer, KeyCode::Down, KeyModifiers::SHIFT);
+
+        press(&mut composer, KeyCode::Char('v'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "- ");
+        assert_eq!(text_of(&composer), "- milk\n- eggs\n- bread\nsigned, me");
+        assert_eq!(composer.textarea.cursor(), (2, 2));
+
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "-milk\n-eggs\n-bread\nsigned, me");
+
+        // Esc ends the column edit without clearing; typing is normal again.
+        press(&mut composer, KeyCode::Esc, KeyModifiers::NONE);
+        type_str(&mut composer, " ");
+        assert_eq!(text_of(&composer), "-milk\n-eggs\n- bread\nsigned, me");
+
+        // The whole column edit is one undo step.
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "milk\neggs\nbread\nsigned, me");
+    }
+
+    #[test]
+    fn tab_indents_without_a_popup_and_completes_with_one() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "fn main() {");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::SHIFT);
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        type_str(&mut composer, "run();");
+        assert_eq!(text_of(&composer), "fn main() {\n    run();");
+
+        composer.set_text("");
+        type_str(&mut composer, "/he");
+        assert!(composer.is_popup_visible());
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "/help ");
+
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            tab_width: Some(2),
+            ..Default::default()
+        });
+        press(&mut composer, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "  ");
+    }
+
+    #[test]
+    fn submit_filter_can_accept_rewrite_or_reject() {
+        let (mut composer, rx) = make_composer();
+        composer.set_submit_filter(Box::new(|text| match text.as_str() {
+            "!greet" => Ok("Hello there!".to_string()),
+            t if t.contains("TODO") => Err("Fill in the template first.".to_string()),
+            _ => Ok(text),
+        }));
+
+        type_str(&mut composer, "as typed");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "as typed"));
+
+        type_str(&mut composer, "!greet");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "Hello there!"));
+
+        type_str(&mut composer, "Fix TODO");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "Fix TODO");
+        assert!(rx.try_iter().any(
+            |e| matches!(e, AppEvent::Notice(message) if message == "Fill in the template first.")
+        ));
+    }
+
+    #[test]
+    fn paste_punctuation_is_only_normalized_when_enabled() {
+        let pasted = "say \u{201c}curly\u{201d} \u{2014} then stop";
+
+        let (mut composer, _rx) = make_composer();
+        composer.handle_paste(pasted.to_string());
+        assert_eq!(text_of(&composer), pasted);
+
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            ascii_paste_punctuation: true,
+            ..Default::default()
+        });
+        composer.handle_paste(pasted.to_string());
+        assert_eq!(text_of(&composer), "say \"curly\" -- then stop");
+    }
+
+    #[test]
+    fn ctrl_x_ctrl_e_round_trips_through_the_external_editor() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "short draft");
+        press(&mut composer, KeyCode::Char('x'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        assert!(
+            rx.try_iter()
+                .any(|e| matches!(e, AppEvent::OpenExternalEditor(text) if text == "short draft"))
+        );
+        assert_eq!(composer.textarea.cursor(), (0, "short draft".len()));
+
+        assert!(composer.on_external_editor_response("a much\nlonger prompt".to_string()));
+        assert_eq!(text_of(&composer), "a much\nlonger prompt");
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "short draft");
+
+        // Ctrl+E on its own still moves to the end of the line.
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        assert!(
+            !rx.try_iter()
+                .any(|e| matches!(e, AppEvent::OpenExternalEditor(_)))
+        );
+        assert_eq!(composer.textarea.cursor(), (0, "short draft".len()));
+    }
+
+    #[test]
+    fn textarea_keeps_a_row_when_the_area_is_very_short() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "/he");
+        assert!(composer.is_popup_visible());
+        let rows = |composer: &ChatComposer, height: u16| {
+            let area = Rect::new(0, 0, 30, height);
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            (0..height)
+                .map(|y| (0..30).map(|x| buf[(x, y)].symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+        };
+
+        // No room for the popup or the border: the text gets both rows.
+        let short = rows(&composer, 2);
+        assert!(short[0].starts_with("/he"), "{short:?}");
+        assert!(!short.concat().contains("help"));
+
+        // One row to spare goes to the popup; the bordered textarea keeps 3.
+        let taller = rows(&composer, 4);
+        assert!(taller[2].contains("/he"), "{taller:?}");
+    }
+
+    #[test]
+    fn ctrl_backspace_deletes_the_previous_word() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "git commit --amend");
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "git commit --");
+
+        // Terminals without a distinct Ctrl+Backspace send 0x17 (Ctrl+W).
+        press(&mut composer, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "git ");
+
+        // A plain Backspace still deletes one character, and Ctrl+Z brings
+        // the words back one deletion at a time.
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "git");
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "git commit --");
+    }
+
+    #[test]
+    fn paste_replaces_the_selection() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "fix the bug now");
+        for _ in 0.." now".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+        }
+        for _ in 0.."bug".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::SHIFT);
+        }
+        composer.handle_paste("X".to_string());
+        assert_eq!(text_of(&composer), "fix the X now");
+
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "fix the bug now");
+
+        // Pastes that become an attachment replace it as well.
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            paste_attachment_lines: Some(1),
+            ..Default::default()
+        });
+        type_str(&mut composer, "see OLD");
+        for _ in 0.."OLD".len() {
+            press(&mut composer, KeyCode::Left, KeyModifiers::SHIFT);
+        }
+        composer.handle_paste("a\nb".to_string());
+        assert_eq!(text_of(&composer), "see [pasted 2 lines]");
+    }
+
+    #[test]
+    fn templates_are_expanded_on_submit() {
+        let (mut composer, rx) = make_composer();
+        composer.set_template_resolver(Box::new(|name: &str| {
+            (name == "ticket").then(|| "CX-42: crash on resize".to_string())
+        }));
+
+        type_str(&mut composer, "Fix :ticket:");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, .. } if text == "Fix CX-42: crash on resize"
+        ));
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::Notice(_))));
+
+        type_str(&mut composer, ":ticket: see :diff:");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, .. } if text == "CX-42: crash on resize see :diff:"
+        ));
+        assert!(rx.try_iter().any(
+            |e| matches!(e, AppEvent::Notice(message) if message == "No value for :diff:; left as typed.")
+        ));
+    }
+
+    #[test]
+    fn alt_up_recalls_the_last_submission() {
+        let (mut composer, _rx) = make_composer();
+        press(&mut composer, KeyCode::Up, KeyModifiers::ALT);
+        assert_eq!(text_of(&composer), "");
+
+        for message in ["older", "latest"] {
+            type_str(&mut composer, message);
+            press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
         }
+        type_str(&mut composer, "draft");
+        press(&mut composer, KeyCode::Up, KeyModifiers::ALT);
+        assert_eq!(text_of(&composer), "latest");
+        assert_eq!(composer.textarea.cursor(), (0, "latest".len()));
+
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "draft");
+    }
+
+    #[test]
+    fn typing_while_unfocused_requests_focus_and_keeps_the_key() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "a");
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::RequestFocus)));
+
+        composer.set_input_focus(false);
+        press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::RequestFocus)));
+        type_str(&mut composer, "b");
+        assert!(rx.try_iter().any(|e| matches!(e, AppEvent::RequestFocus)));
+        assert_eq!(text_of(&composer), "ba");
+    }
+
+    #[test]
+    fn blank_line_mode_sends_on_enter_at_an_empty_last_line() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            submit_mode: SubmitMode::BlankLineSends,
+            ..Default::default()
+        });
+        type_str(&mut composer, "first");
+        assert!(matches!(
+            press(&mut composer, KeyCode::Enter, KeyModifiers::NONE),
+            InputResult::None
+        ));
+        type_str(&mut composer, "second");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "first\nsecond\n");
+
+        // A blank line that is not the last one only takes a newline.
+        composer.set_text("first\n\nsecond\n");
+        composer.textarea.move_cursor(CursorMove::Jump(1, 0));
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(text_of(&composer), "first\n\n\nsecond\n");
+
+        composer.textarea.move_cursor(CursorMove::Bottom);
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, .. } if text == "first\n\n\nsecond"
+        ));
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn ctrl_d_deletes_forward_and_quits_only_when_empty() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "ab");
+        press(&mut composer, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "b");
+        // At the end of the text there is nothing to delete, but the draft
+        // still keeps Ctrl+D from quitting.
+        press(&mut composer, KeyCode::End, KeyModifiers::NONE);
+        press(&mut composer, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "b");
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::ExitRequest)));
+
+        press(&mut composer, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        press(&mut composer, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "");
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::ExitRequest)));
+        press(&mut composer, KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert!(rx.try_iter().any(|e| matches!(e, AppEvent::ExitRequest)));
+    }
+
+    #[test]
+    fn border_shows_word_and_token_counts() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            show_word_count: true,
+            ..Default::default()
+        });
+        let top_border = |composer: &ChatComposer| -> String {
+            let area = Rect::new(0, 0, 50, 4);
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            (0..area.width).map(|x| buf[(x, 0)].symbol()).collect()
+        };
+        assert!(!top_border(&composer).contains("tok"));
+
+        composer.set_text("The quick  brown\nfox jumps.");
+        assert!(
+            top_border(&composer).contains(" 5 words · ~7 tok "),
+            "{}",
+            top_border(&composer)
+        );
+
+        composer.set_token_estimator(Box::new(|text| text.split_whitespace().count() * 2));
+        assert!(top_border(&composer).contains(" 5 words · ~10 tok "));
+    }
+
+    #[test]
+    fn double_slash_types_a_literal_slash() {
+        let (mut composer, _rx) = make_composer();
+        type_str(&mut composer, "//etc/hosts");
+        assert_eq!(text_of(&composer), "/etc/hosts");
+        assert!(!composer.is_popup_visible());
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, .. } if text == "/etc/hosts"
+        ));
+
+        // Not even a mode prefix is recognised after the escape.
+        type_str(&mut composer, "//ask why");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, mode: PromptMode::Default, .. } if text == "/ask why"
+        ));
+
+        // Deleting the slash ends the escape; a new one starts a command.
+        type_str(&mut composer, "//");
+        press(&mut composer, KeyCode::Backspace, KeyModifiers::NONE);
+        type_str(&mut composer, "/");
+        assert!(composer.is_popup_visible());
+        // A slash typed later in a command is just text.
+        type_str(&mut composer, "a/");
+        assert_eq!(text_of(&composer), "/a/");
+    }
+
+    #[test]
+    fn dot_repeats_the_last_command_when_empty() {
+        let (mut composer, rx) = make_composer_with_config(ChatComposerConfig {
+            dot_repeat: DotRepeat::Command,
+            ..Default::default()
+        });
+        // Nothing to repeat yet: the dot is typed.
+        type_str(&mut composer, ".");
+        assert_eq!(text_of(&composer), ".");
+        composer.set_text("");
+
+        type_str(&mut composer, "/model o3");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        let dispatched = |rx: &Receiver<AppEvent>| -> Vec<(SlashCommand, String)> {
+            rx.try_iter()
+                .filter_map(|e| match e {
+                    AppEvent::DispatchCommand { command, args } => Some((command, args)),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            dispatched(&rx),
+            vec![(SlashCommand::Model, "o3".to_string())]
+        );
+
+        type_str(&mut composer, ".");
+        assert_eq!(text_of(&composer), "");
+        assert_eq!(
+            dispatched(&rx),
+            vec![(SlashCommand::Model, "o3".to_string())]
+        );
+
+        // With text in the composer the dot is just typed.
+        type_str(&mut composer, "a.");
+        assert_eq!(text_of(&composer), "a.");
+        assert!(dispatched(&rx).is_empty());
+    }
+
+    #[test]
+    fn dot_resubmits_the_last_message_when_configured() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            dot_repeat: DotRepeat::Message,
+            ..Default::default()
+        });
+        type_str(&mut composer, "run the tests");
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        let result = press(&mut composer, KeyCode::Char('.'), KeyModifiers::NONE);
+        assert!(matches!(
+            result,
+            InputResult::Submitted { text, .. } if text == "run the tests"
+        ));
+        assert_eq!(text_of(&composer), "");
+    }
+
+    #[test]
+    fn recalled_history_entries_keep_every_line() {
+        let (mut composer, rx) = make_composer();
+        composer.set_history_metadata(1, 1);
+        composer.handle_paste("local one\nlocal two\nlocal three".to_string());
+        press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(
+            composer.textarea.lines(),
+            ["local one", "local two", "local three"]
+        );
+
+        // An entry fetched from the persistent history arrives as one string.
+        press(&mut composer, KeyCode::Up, KeyModifiers::NONE);
+        assert!(rx.try_iter().any(|e| matches!(
+            e,
+            AppEvent::CodexOp(Op::GetHistoryEntryRequest { offset: 0, .. })
+        )));
+        composer.on_history_entry_response(1, 0, Some("first\nsecond\nthird".into()));
+        assert_eq!(composer.textarea.lines(), ["first", "second", "third"]);
+
+        let area = Rect::new(0, 0, 30, 6);
+        let mut buf = Buffer::empty(area);
+        (&composer).render_ref(area, &mut buf);
+        let rows: Vec<String> = (1..4)
+            .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect();
+        assert!(rows[0].contains("first"), "{rows:?}");
+        assert!(rows[1].contains("second"), "{rows:?}");
+        assert!(rows[2].contains("third"), "{rows:?}");
+    }
+
+    #[test]
+    fn typing_in_a_long_draft_redoes_only_the_edited_line() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            file_search_root: Some(PathBuf::from("/nonexistent")),
+            show_word_count: true,
+            max_chars: Some(100_000),
+            ..Default::default()
+        });
+        let text: Vec<String> = (0..300)
+            .map(|i| format!("line {i} of a long draft, long enough to wrap"))
+            .collect();
+        composer.set_text(&text.join("\n"));
+        composer.textarea.move_cursor(CursorMove::Jump(150, 4));
+        let area = Rect::new(0, 0, 40, 12);
+        let render = |composer: &ChatComposer| {
+            let mut buf = Buffer::empty(area);
+            composer.calculate_required_height(&area);
+            composer.render_ref(area, &mut buf);
+        };
+        // Lines wrapped and lines scanned for counts, mentions and syntax
+        // highlighting so far.
+        let work = |composer: &ChatComposer| {
+            (
+                composer.wrap_cache.borrow().wrapped(),
+                composer.line_cache.borrow().computed(),
+            )
+        };
+        render(&composer);
+        let (wrapped, scanned) = work(&composer);
+        assert!(wrapped >= 300);
+        assert_eq!(scanned, 300);
+
+        // A frame without an edit redoes nothing.
+        render(&composer);
+        assert_eq!(work(&composer), (wrapped, scanned));
+
+        let start = std::time::Instant::now();
+        type_str(&mut composer, "x");
+        render(&composer);
+        assert_eq!(work(&composer), (wrapped + 1, scanned + 1));
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert_eq!(
+            composer.textarea.lines()[150],
+            "linex 150 of a long draft, long enough to wrap"
+        );
+    }
+
+    #[test]
+    fn whitespace_only_text_is_not_submitted() {
+        let (mut composer, rx) = make_composer();
+        type_str(&mut composer, "   ");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert!(
+            !rx.try_iter()
+                .any(|e| matches!(e, AppEvent::RecordHistory(_)))
+        );
+        assert_eq!(text_of(&composer), "   ");
+
+        // Leading whitespace of a real message is kept.
+        composer.set_text("");
+        type_str(&mut composer, "  x");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "  x"));
+
+        // Nor is whitespace left over by the submit filter.
+        composer.set_submit_filter(Box::new(|_| Ok(" \n ".to_string())));
+        type_str(&mut composer, "y");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::None));
+        assert_eq!(text_of(&composer), "y");
+    }
+
+    #[test]
+    fn scratch_buffer_is_not_saved_as_the_draft() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let config = ChatComposerConfig {
+            draft_path: Some(dir.path().join("draft")),
+            ..Default::default()
+        };
+
+        let (mut composer, _rx) = make_composer_with_config(config.clone());
+        type_str(&mut composer, "long draft");
+        press(&mut composer, KeyCode::Char('g'), KeyModifiers::CONTROL);
+        type_str(&mut composer, "quick note");
+        drop(composer);
+
+        let (composer, _rx) = make_composer_with_config(config);
+        assert_eq!(text_of(&composer), "long draft");
+    }
+
+    #[test]
+    fn ctrl_g_swaps_the_draft_with_a_scratch_buffer() {
+        let (mut composer, _rx) = make_composer();
+        let top_border = |composer: &ChatComposer| -> String {
+            let area = Rect::new(0, 0, 50, 3);
+            let mut buf = Buffer::empty(area);
+            composer.render_ref(area, &mut buf);
+            (0..area.width).map(|x| buf[(x, 0)].symbol()).collect()
+        };
+        type_str(&mut composer, "a long draft");
+        press(&mut composer, KeyCode::Left, KeyModifiers::NONE);
+
+        press(&mut composer, KeyCode::Char('g'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "");
+        assert!(top_border(&composer).contains("scratch"));
+        // The draft's edits cannot be undone into the scratch buffer.
+        press(&mut composer, KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "");
+
+        type_str(&mut composer, "quick");
+        let result = press(&mut composer, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "quick"));
+
+        press(&mut composer, KeyCode::Char('g'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "a long draft");
+        assert_eq!(composer.textarea.cursor(), (0, 11));
+        assert!(!top_border(&composer).contains("scratch"));
+
+        // The scratch buffer was emptied by sending it.
+        type_str(&mut composer, "!");
+        press(&mut composer, KeyCode::Char('g'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "");
+        press(&mut composer, KeyCode::Char('g'), KeyModifiers::CONTROL);
+        assert_eq!(text_of(&composer), "a long draf!t");
+    }
+
+    #[test]
+    fn list_markers_continue_on_newline() {
+        let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+            continue_lists: true,
+            ..Default::default()
+        });
+        let newline = |composer: &mut ChatComposer| {
+            press(composer, KeyCode::Char('j'), KeyModifiers::CONTROL);
+        };
+        type_str(&mut composer, "Steps:");
+        newline(&mut composer);
+        type_str(&mut composer, "  - one");
+        newline(&mut composer);
+        type_str(&mut composer, "two");
+        assert_eq!(text_of(&composer), "Steps:\n  - one\n  - two");
+
+        composer.set_text("* a");
+        newline(&mut composer);
+        assert_eq!(text_of(&composer), "* a\n* ");
+
+        composer.set_text("9. nine");
+        newline(&mut composer);
+        type_str(&mut composer, "ten");
+        newline(&mut composer);
+        assert_eq!(text_of(&composer), "9. nine\n10. ten\n11. ");
+
+        // A newline on the empty item removes its marker instead.
+        newline(&mut composer);
+        assert_eq!(text_of(&composer), "9. nine\n10. ten\n");
+        newline(&mut composer);
+        assert_eq!(text_of(&composer), "9. nine\n10. ten\n\n");
+
+        // Not a list item, or inside a fence: a plain newline.
+        composer.set_text("-not a list");
+        newline(&mut composer);
+        assert_eq!(text_of(&composer), "-not a list\n");
+        composer.set_text("```\n- code");
+        newline(&mut composer);
+        assert_eq!(text_of(&composer), "```\n- code\n");
+    }
+
+    #[test]
+    fn list_marker_detection() {
+        assert_eq!(list_marker("- a"), Some((2, "- ".to_string())));
+        assert_eq!(list_marker("\t* a"), Some((3, "\t* ".to_string())));
+        assert_eq!(list_marker("1. "), Some((3, "2. ".to_string())));
+        assert_eq!(list_marker("12. x"), Some((4, "13. ".to_string())));
+        assert_eq!(list_marker("1.5 x"), None);
+        assert_eq!(list_marker("-x"), None);
+        assert_eq!(list_marker("x - y"), None);
+    }
+
+    #[test]
+    fn popup_opens_on_the_configured_side() {
+        let rendered = |placement: PopupPlacement| -> Vec<String> {
+            let (mut composer, _rx) = make_composer_with_config(ChatComposerConfig {
+                popup_placement: placement,
+                ..Default::default()
+            });
+            type_str(&mut composer, "/mo");
+            let area = Rect::new(0, 0, 40, 12);
+            let height = composer.calculate_required_height(&area);
+            let area = Rect::new(0, 0, 40, height);
+            let mut buf = Buffer::empty(area);
+            (&composer).render_ref(area, &mut buf);
+            (0..area.height)
+                .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+                .collect()
+        };
+        let row_of = |lines: &[String], prefix: &str| {
+            lines
+                .iter()
+                .position(|line| line.starts_with(prefix))
+                .expect("row")
+        };
+
+        let above = rendered(PopupPlacement::Above);
+        let below = rendered(PopupPlacement::Below);
+        assert_eq!(above.len(), below.len());
+        assert!(
+            row_of(&above, "│/model") < row_of(&above, "│/mo "),
+            "{above:?}"
+        );
+        assert!(
+            row_of(&below, "│/model") > row_of(&below, "│/mo "),
+            "{below:?}"
+        );
+        // Each layout is the other one with the two parts swapped.
+        assert_eq!(row_of(&below, "│/mo "), 1);
+        assert_eq!(above.last(), below.get(2));
     }
 }
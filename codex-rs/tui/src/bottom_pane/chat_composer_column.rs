@@ -0,0 +1,118 @@
+//! Column (block) editing, entered with Ctrl+V: text typed while it is
+//! active goes in at the same column on every line of a range, e.g. to put
+//! `- ` in front of each line of a pasted list.
+//!
+//! tui_textarea has a single cursor, so each edit is applied line by line by
+//! moving the cursor to the column on every row in turn.
+
+use std::ops::RangeInclusive;
+
+use tui_textarea::CursorMove;
+use tui_textarea::TextArea;
+
+/// An active column edit. The block spans from `anchor_row` to the row of
+/// the cursor.
+pub(crate) struct ColumnEdit {
+    anchor_row: usize,
+    /// Column, in chars, at which text is inserted.
+    col: usize,
+    /// Whether the block has been edited yet, so the whole edit can be
+    /// undone in one step.
+    edited: bool,
+}
+
+impl ColumnEdit {
+    /// Start at the cursor. With a selection, the block instead spans the
+    /// selected lines, which are deselected.
+    pub fn start(textarea: &mut TextArea) -> Self {
+        let (row, col) = textarea.cursor();
+        // The cursor is at one end of the selection; anchor at the other.
+        let anchor_row = match textarea.selection_range() {
+            Some((start, end)) if end == (row, col) => start.0,
+            Some((_, end)) => end.0,
+            None => row,
+        };
+        textarea.cancel_selection();
+        Self {
+            anchor_row,
+            col,
+            edited: false,
+        }
+    }
+
+    /// Lines covered by the block.
+    pub fn rows(&self, textarea: &TextArea) -> RangeInclusive<usize> {
+        let row = textarea.cursor().0;
+        self.anchor_row.min(row)..=self.anchor_row.max(row)
+    }
+
+    /// Returns true the first time it is called, when the caller should
+    /// record an undo snapshot.
+    pub fn begin_edit(&mut self) -> bool {
+        !std::mem::replace(&mut self.edited, true)
+    }
+
+    /// Insert `text` (a single line) at the block's column on every line of
+    /// the block. Lines too short to reach the column are left alone.
+    pub fn insert(&mut self, textarea: &mut TextArea, text: &str) {
+        let new_col = self.col + text.chars().count();
+        self.for_each_row(textarea, new_col, |textarea| {
+            textarea.insert_str(text);
+        });
+    }
+
+    /// Delete the character before the block's column on every line of the
+    /// block.
+    pub fn backspace(&mut self, textarea: &mut TextArea) {
+        if self.col == 0 {
+            return;
+        }
+        let new_col = self.col - 1;
+        self.for_each_row(textarea, new_col, |textarea| {
+            textarea.delete_char();
+        });
+    }
+
+    /// Move the cursor to the previous (`up`) or next line, growing or
+    /// shrinking the block.
+    pub fn move_row(&self, textarea: &mut TextArea, up: bool) {
+        let row = textarea.cursor().0;
+        let last = textarea.lines().len().saturating_sub(1);
+        let row = if up {
+            row.saturating_sub(1)
+        } else {
+            (row + 1).min(last)
+        };
+        jump(textarea, row, self.col);
+    }
+
+    /// Apply `edit` with the cursor at the block's column on each line that
+    /// reaches it, then move the block to `new_col`.
+    fn for_each_row(
+        &mut self,
+        textarea: &mut TextArea,
+        new_col: usize,
+        mut edit: impl FnMut(&mut TextArea),
+    ) {
+        let cursor_row = textarea.cursor().0;
+        for row in self.rows(textarea) {
+            let reaches = textarea
+                .lines()
+                .get(row)
+                .is_some_and(|line| line.chars().count() >= self.col);
+            if reaches {
+                jump(textarea, row, self.col);
+                edit(textarea);
+            }
+        }
+        self.col = new_col;
+        jump(textarea, cursor_row, new_col);
+    }
+}
+
+fn jump(textarea: &mut TextArea, row: usize, col: usize) {
+    textarea.move_cursor(CursorMove::Jump(
+        u16::try_from(row).unwrap_or(u16::MAX),
+        u16::try_from(col).unwrap_or(u16::MAX),
+    ));
+}
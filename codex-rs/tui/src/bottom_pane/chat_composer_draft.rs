@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use tokio::runtime::Handle;
+use tokio::time::sleep_until;
+
+use super::chat_composer::ComposerState;
+
+/// Minimum interval between two writes of the draft file while typing.
+const DRAFT_WRITE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Persists the unsent composer text, as a JSON [`ComposerState`], so it
+/// survives crashes and restarts. Writes are debounced while the user is
+/// typing: an edit that lands inside the window is written by a timer once
+/// the window ends, and any pending state is flushed when the draft is
+/// dropped.
+pub(crate) struct ChatComposerDraft {
+    file: Arc<Mutex<DraftFile>>,
+    timer_scheduled: Arc<AtomicBool>,
+    /// The trailing write runs on Tokio. Without a runtime (e.g. in unit
+    /// tests) a debounced edit waits for the next update or the drop.
+    runtime: Option<Handle>,
+}
+
+struct DraftFile {
+    path: PathBuf,
+    /// Contents most recently written to `path` (empty when no file exists).
+    saved: String,
+    /// Contents that have changed since the last write, if any.
+    pending: Option<String>,
+    last_write: Option<Instant>,
+}
+
+impl ChatComposerDraft {
+    pub fn new(path: PathBuf) -> Self {
+        let saved = std::fs::read_to_string(&path).unwrap_or_default();
+        Self {
+            file: Arc::new(Mutex::new(DraftFile {
+                path,
+                saved,
+                pending: None,
+                last_write: None,
+            })),
+            timer_scheduled: Arc::new(AtomicBool::new(false)),
+            runtime: Handle::try_current().ok(),
+        }
+    }
+
+    /// Draft restored from disk, if a non-empty one exists.
+    pub fn restored(&self) -> Option<ComposerState> {
+        let file = self.file.lock().ok()?;
+        if file.saved.is_empty() {
+            return None;
+        }
+        match serde_json::from_str::<ComposerState>(&file.saved) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                tracing::warn!("ignoring unreadable composer draft {:?}: {e}", file.path);
+                None
+            }
+        }
+    }
+
+    /// Record the current composer state. An empty text removes the draft
+    /// immediately; otherwise the write is debounced.
+    pub fn update(&mut self, state: &ComposerState) {
+        let contents = if state.text.is_empty() {
+            String::new()
+        } else {
+            match serde_json::to_string(state) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    tracing::warn!("failed to serialize composer draft: {e}");
+                    return;
+                }
+            }
+        };
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        if contents == file.saved {
+            file.pending = None;
+            return;
+        }
+
+        let debounced = file
+            .last_write
+            .is_some_and(|t| t.elapsed() < DRAFT_WRITE_DEBOUNCE);
+        let remove = contents.is_empty();
+        file.pending = Some(contents);
+        if remove || !debounced {
+            file.flush();
+        } else {
+            drop(file);
+            self.schedule_flush();
+        }
+    }
+
+    /// Write any pending contents to disk.
+    pub fn flush(&mut self) {
+        if let Ok(mut file) = self.file.lock() {
+            file.flush();
+        }
+    }
+
+    /// Starts a one-shot timer, **only once** per burst of edits, that
+    /// writes the pending contents when the debounce window ends.
+    fn schedule_flush(&self) {
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        if self
+            .timer_scheduled
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return;
+        }
+
+        let file = Arc::clone(&self.file);
+        let timer_flag = Arc::clone(&self.timer_scheduled);
+        runtime.spawn(async move {
+            let due = file
+                .lock()
+                .ok()
+                .and_then(|file| file.last_write)
+                .map(|t| t + DRAFT_WRITE_DEBOUNCE);
+            if let Some(due) = due {
+                sleep_until(due.into()).await;
+            }
+
+            timer_flag.store(false, Ordering::SeqCst);
+            if let Ok(mut file) = file.lock() {
+                file.flush();
+            }
+        });
+    }
+}
+
+impl DraftFile {
+    fn flush(&mut self) {
+        let Some(text) = self.pending.take() else {
+            return;
+        };
+        let result = if text.is_empty() {
+            match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            }
+        } else {
+            std::fs::write(&self.path, &text)
+        };
+        if let Err(e) = result {
+            tracing::warn!("failed to persist composer draft to {:?}: {e}", self.path);
+        }
+        self.saved = text;
+        self.last_write = Some(Instant::now());
+    }
+}
+
+impl Drop for ChatComposerDraft {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
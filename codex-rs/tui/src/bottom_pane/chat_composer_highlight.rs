@@ -0,0 +1,299 @@
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::style::Stylize;
+
+/// Keyword-level highlighting for one language of fenced code.
+pub(crate) struct Language {
+    keywords: &'static [&'static str],
+    line_comment: &'static str,
+    /// Quote characters that delimit string literals.
+    quotes: &'static [char],
+}
+
+const RUST: Language = Language {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: "//",
+    quotes: &['"'],
+};
+
+const PYTHON: Language = Language {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+        "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+        "try", "while", "with", "yield",
+    ],
+    line_comment: "#",
+    quotes: &['"', '\''],
+};
+
+const JAVASCRIPT: Language = Language {
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "return",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "undefined",
+        "var",
+        "while",
+        "yield",
+    ],
+    line_comment: "//",
+    quotes: &['"', '\'', '`'],
+};
+
+const SHELL: Language = Language {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "until", "while",
+    ],
+    line_comment: "#",
+    quotes: &['"', '\''],
+};
+
+fn language(tag: &str) -> Option<&'static Language> {
+    match tag.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => Some(&RUST),
+        "python" | "py" => Some(&PYTHON),
+        "javascript" | "js" | "typescript" | "ts" | "jsx" | "tsx" => Some(&JAVASCRIPT),
+        "sh" | "bash" | "shell" | "zsh" => Some(&SHELL),
+        _ => None,
+    }
+}
+
+/// Where a line sits relative to ``` fences.
+#[derive(Clone, Copy)]
+pub(crate) enum FenceContext {
+    Outside,
+    /// A line that opens or closes a fence.
+    Fence,
+    /// Code in a fence, in the language named when it was opened if that
+    /// language is recognised.
+    Inside(Option<&'static Language>),
+}
+
+impl PartialEq for FenceContext {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Outside, Self::Outside) | (Self::Fence, Self::Fence) => true,
+            (Self::Inside(a), Self::Inside(b)) => match (a, b) {
+                (Some(a), Some(b)) => std::ptr::eq(*a, *b),
+                (None, None) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// The [`FenceContext`] of each of `lines`.
+pub(crate) fn fence_contexts(lines: &[String]) -> Vec<FenceContext> {
+    let mut fence: Option<Option<&'static Language>> = None;
+    lines
+        .iter()
+        .map(|line| {
+            if let Some(tag) = line.trim_start().strip_prefix("```") {
+                fence = match fence {
+                    Some(_) => None,
+                    None => Some(language(tag.trim())),
+                };
+                return FenceContext::Fence;
+            }
+            match fence {
+                Some(lang) => FenceContext::Inside(lang),
+                None => FenceContext::Outside,
+            }
+        })
+        .collect()
+}
+
+/// Per-char styles for `line`, highlighting it as code when `context` is
+/// inside a fence. Lines outside a fence (and code in languages that are
+/// not recognised) get an empty vector, meaning "no styling".
+///
+/// Highlighting is line-local: strings and comments never continue onto the
+/// next line, so a line's styles only depend on it and its context.
+pub(crate) fn line_styles(line: &str, context: FenceContext) -> Vec<Style> {
+    match context {
+        FenceContext::Fence => vec![Style::default().dim(); line.chars().count()],
+        FenceContext::Inside(Some(lang)) => highlight_line(lang, line),
+        FenceContext::Inside(None) | FenceContext::Outside => Vec::new(),
+    }
+}
+
+/// [`line_styles`] for every line of `lines`.
+#[cfg(test)]
+pub(crate) fn highlight_code_blocks(lines: &[String]) -> Vec<Vec<Style>> {
+    lines
+        .iter()
+        .zip(fence_contexts(lines))
+        .map(|(line, context)| line_styles(line, context))
+        .collect()
+}
+
+/// Whether the text following line `line` is inside a ``` fence, i.e. an
+/// odd number of fence lines occur up to and including it.
+pub(crate) fn is_inside_code_block(lines: &[String], line: usize) -> bool {
+    lines
+        .iter()
+        .take(line + 1)
+        .filter(|l| l.trim_start().starts_with("```"))
+        .count()
+        % 2
+        == 1
+}
+
+/// Char range `(start, end)` of a leading `/command` on the first of
+/// `lines`, when `is_command` accepts its name.
+pub(crate) fn command_token(
+    lines: &[String],
+    is_command: impl Fn(&str) -> bool,
+) -> Option<(usize, usize)> {
+    let name = lines
+        .first()?
+        .strip_prefix('/')?
+        .split(char::is_whitespace)
+        .next()?;
+    (!name.is_empty() && is_command(name)).then(|| (0, 1 + name.chars().count()))
+}
+
+/// Char ranges `(start, end)` of the `@path` mentions in `line`, a line
+/// outside ``` fences. Never touches the file system.
+pub(crate) fn line_mentions(line: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut mentions = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let at_word_start = i == 0 || chars[i - 1].is_whitespace();
+        if chars[i] == '@' && at_word_start {
+            let end = run_end(&chars, i, |c| !c.is_whitespace());
+            if end > i + 1 {
+                mentions.push((i, end));
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    mentions
+}
+
+fn highlight_line(lang: &Language, line: &str) -> Vec<Style> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut styles = vec![Style::default(); chars.len()];
+    let comment: Vec<char> = lang.line_comment.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if chars[i..].starts_with(&comment) {
+            styles[i..].fill(Style::default().fg(Color::DarkGray));
+            break;
+        } else if lang.quotes.contains(&ch) {
+            let mut end = i + 1;
+            while end < chars.len() && chars[end] != ch {
+                end += if chars[end] == '\\' { 2 } else { 1 };
+            }
+            let end = (end + 1).min(chars.len());
+            styles[i..end].fill(Style::default().fg(Color::Green));
+            i = end;
+        } else if ch.is_alphabetic() || ch == '_' {
+            let end = run_end(&chars, i, |c| c.is_alphanumeric() || c == '_');
+            let word: String = chars[i..end].iter().collect();
+            if lang.keywords.contains(&word.as_str()) {
+                styles[i..end].fill(Style::default().fg(Color::Magenta).bold());
+            }
+            i = end;
+        } else if ch.is_ascii_digit() {
+            let end = run_end(&chars, i, |c| {
+                c.is_ascii_alphanumeric() || c == '_' || c == '.'
+            });
+            styles[i..end].fill(Style::default().fg(Color::Cyan));
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    styles
+}
+
+/// Index just past the run of chars starting at `start` that satisfy `f`.
+fn run_end(chars: &[char], start: usize, f: impl Fn(char) -> bool) -> usize {
+    chars[start..]
+        .iter()
+        .position(|c| !f(*c))
+        .map_or(chars.len(), |n| start + n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn only_fenced_lines_are_highlighted() {
+        let lines: Vec<String> = ["let x = 1;", "```rust", "let x = 1;", "```", "let x = 1;"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let styles = highlight_code_blocks(&lines);
+        assert!(styles[0].is_empty());
+        assert!(styles[4].is_empty());
+        assert_eq!(styles[2][0], Style::default().fg(Color::Magenta).bold());
+        assert_eq!(styles[2][4], Style::default());
+        assert_eq!(styles[2][8], Style::default().fg(Color::Cyan));
+    }
+
+    #[test]
+    fn strings_and_comments() {
+        let styles = highlight_line(&PYTHON, r#"x = "a\"b" # if"#);
+        let green = Style::default().fg(Color::Green);
+        let gray = Style::default().fg(Color::DarkGray);
+        assert_eq!(&styles[4..10], &[green; 6]);
+        assert_eq!(styles[10], Style::default());
+        // Keywords inside comments are not highlighted separately.
+        assert_eq!(&styles[11..], &[gray; 4]);
+    }
+
+    #[test]
+    fn unknown_languages_are_left_plain() {
+        let lines: Vec<String> = ["```cobol", "if let", "```"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert!(highlight_code_blocks(&lines)[1].is_empty());
+    }
+}
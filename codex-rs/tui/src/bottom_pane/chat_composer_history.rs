@@ -31,6 +31,26 @@ pub(crate) struct ChatComposerHistory {
     /// history navigation. Used to decide if further Up/Down presses should be
     /// treated as navigation versus normal cursor movement.
     last_history_text: Option<String>,
+
+    /// Text and `(row, col)` cursor of the in-progress message from before
+    /// navigation began, restored when navigating back past the newest entry.
+    draft: Option<(String, (usize, usize))>,
+
+    /// Active reverse incremental (Ctrl+R) search, if any.
+    search: Option<HistorySearch>,
+}
+
+/// State of a reverse incremental search through the combined history.
+struct HistorySearch {
+    query: String,
+    /// Next offset to examine; the search proceeds towards older entries.
+    next: Option<usize>,
+    /// Offset of the entry currently previewed in the textarea.
+    matched: Option<usize>,
+    /// Offset requested from the persistent history and not yet received.
+    pending: Option<usize>,
+    /// Whether the whole history was searched without finding `query`.
+    failed: bool,
 }
 
 impl ChatComposerHistory {
@@ -42,6 +62,8 @@ impl ChatComposerHistory {
             fetched_history: HashMap::new(),
             history_cursor: None,
             last_history_text: None,
+            draft: None,
+            search: None,
         }
     }
 
@@ -53,6 +75,8 @@ impl ChatComposerHistory {
         self.local_history.clear();
         self.history_cursor = None;
         self.last_history_text = None;
+        self.draft = None;
+        self.search = None;
     }
 
     /// Record a message submitted by the user in the current session so it can
@@ -62,29 +86,39 @@ impl ChatComposerHistory {
             self.local_history.push(text.to_string());
             self.history_cursor = None;
             self.last_history_text = None;
+            self.draft = None;
         }
     }
 
+    /// The most recent message submitted in this session, if any.
+    pub fn last_local_submission(&self) -> Option<&str> {
+        self.local_history.last().map(String::as_str)
+    }
+
+    /// Forget the browsing position and the saved draft, e.g. because undo
+    /// replaced the recalled entry. The next Up starts again from the newest
+    /// entry and saves the text it replaces as the draft.
+    pub fn stop_browsing(&mut self) {
+        self.history_cursor = None;
+        self.last_history_text = None;
+        self.draft = None;
+    }
+
     /// Should Up/Down key presses be interpreted as history navigation given
-    /// the current content and cursor position of `textarea`?
+    /// the current content of `textarea`? The caller checks that the cursor
+    /// is on the first (Up) or last (Down) row.
     pub fn should_handle_navigation(&self, textarea: &TextArea) -> bool {
         if self.history_entry_count == 0 && self.local_history.is_empty() {
             return false;
         }
 
         let lines = textarea.lines();
-        if lines.len() == 1 && lines[0].is_empty() {
+        if self.history_cursor.is_none() || (lines.len() == 1 && lines[0].is_empty()) {
             return true;
         }
 
-        // Textarea is not empty – only navigate when cursor is at start and
-        // text matches last recalled history entry so regular editing is not
-        // hijacked.
-        let (row, col) = textarea.cursor();
-        if row != 0 || col != 0 {
-            return false;
-        }
-
+        // While browsing, only keep navigating if the recalled entry was not
+        // edited so regular editing is not hijacked.
         matches!(&self.last_history_text, Some(prev) if prev == &lines.join("\n"))
     }
 
@@ -97,7 +131,10 @@ impl ChatComposerHistory {
         }
 
         let next_idx = match self.history_cursor {
-            None => (total_entries as isize) - 1,
+            None => {
+                self.draft = Some((textarea.lines().join("\n"), textarea.cursor()));
+                (total_entries as isize) - 1
+            }
             Some(0) => return true, // already at oldest
             Some(idx) => idx - 1,
         };
@@ -130,10 +167,15 @@ impl ChatComposerHistory {
                 self.populate_history_at_index(idx as usize, textarea, app_event_tx);
             }
             None => {
-                // Past newest – clear and exit browsing mode.
+                // Past newest – restore the draft and exit browsing mode.
+                let (text, (row, col)) = self.draft.take().unwrap_or_default();
+                self.replace_textarea_content(textarea, &text);
+                textarea.move_cursor(CursorMove::Jump(
+                    u16::try_from(row).unwrap_or(u16::MAX),
+                    u16::try_from(col).unwrap_or(u16::MAX),
+                ));
                 self.history_cursor = None;
                 self.last_history_text = None;
-                self.replace_textarea_content(textarea, "");
             }
         }
         true
@@ -160,6 +202,151 @@ impl ChatComposerHistory {
         false
     }
 
+    // ---------------------------------------------------------------------
+    // Reverse incremental search (Ctrl+R)
+    // ---------------------------------------------------------------------
+
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_ref().map(|search| search.query.as_str())
+    }
+
+    /// Prompt describing the active search, e.g. `(reverse-i-search)'foo'`.
+    pub fn search_prompt(&self) -> Option<String> {
+        self.search.as_ref().map(|search| {
+            let prefix = if search.failed { "failed " } else { "" };
+            format!("({prefix}reverse-i-search)'{}'", search.query)
+        })
+    }
+
+    /// Begin a new search with an empty query.
+    pub fn start_search(&mut self) {
+        self.history_cursor = None;
+        self.search = Some(HistorySearch {
+            query: String::new(),
+            next: None,
+            matched: None,
+            pending: None,
+            failed: false,
+        });
+    }
+
+    /// Replace the query and search again from the newest entry. Returns true
+    /// when the previewed entry changed.
+    pub fn set_search_query(
+        &mut self,
+        query: String,
+        textarea: &mut TextArea,
+        app_event_tx: &AppEventSender,
+    ) -> bool {
+        let total_entries = self.history_entry_count + self.local_history.len();
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        search.next = if query.is_empty() {
+            None
+        } else {
+            total_entries.checked_sub(1)
+        };
+        search.query = query;
+        search.matched = None;
+        // A still outstanding request is kept so it is not sent twice.
+        search.failed = false;
+        self.continue_search(textarea, app_event_tx)
+    }
+
+    /// Look for the next older entry matching the current query (Ctrl+R while
+    /// searching). Returns true when the previewed entry changed.
+    pub fn search_older(&mut self, textarea: &mut TextArea, app_event_tx: &AppEventSender) -> bool {
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        if let Some(matched) = search.matched {
+            search.next = matched.checked_sub(1);
+        }
+        self.continue_search(textarea, app_event_tx)
+    }
+
+    /// Resume a search that was waiting for the persistent entry at `offset`.
+    /// Must be called after [`Self::on_entry_response`] has cached it.
+    pub fn on_search_entry_response(
+        &mut self,
+        offset: usize,
+        textarea: &mut TextArea,
+        app_event_tx: &AppEventSender,
+    ) -> bool {
+        let missing = !self.fetched_history.contains_key(&offset);
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        if search.pending != Some(offset) {
+            return false;
+        }
+        search.pending = None;
+        if missing {
+            // The log has no entry at this offset; skip past it.
+            search.next = offset.checked_sub(1);
+        }
+        self.continue_search(textarea, app_event_tx)
+    }
+
+    /// End the search, keeping the previewed entry. Up/Down continue from
+    /// there.
+    pub fn accept_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.history_cursor = search.matched.map(|idx| idx as isize);
+        }
+    }
+
+    /// End the search; the caller restores the text from before it began.
+    pub fn cancel_search(&mut self) {
+        self.search = None;
+        self.last_history_text = None;
+    }
+
+    /// Walk towards older entries until one contains the query, an entry has
+    /// to be fetched first, or the history is exhausted.
+    fn continue_search(&mut self, textarea: &mut TextArea, app_event_tx: &AppEventSender) -> bool {
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        while let Some(idx) = search.next {
+            let text = if idx >= self.history_entry_count {
+                self.local_history.get(idx - self.history_entry_count)
+            } else {
+                self.fetched_history.get(&idx)
+            };
+            match text {
+                Some(text) if text.contains(&search.query) => {
+                    let text = text.clone();
+                    search.matched = Some(idx);
+                    search.next = Some(idx);
+                    self.replace_textarea_content(textarea, &text);
+                    return true;
+                }
+                Some(_) => search.next = idx.checked_sub(1),
+                None => {
+                    if let Some(log_id) = self.history_log_id {
+                        if search.pending != Some(idx) {
+                            search.pending = Some(idx);
+                            app_event_tx.send(AppEvent::CodexOp(Op::GetHistoryEntryRequest {
+                                offset: idx,
+                                log_id,
+                            }));
+                        }
+                        return false;
+                    }
+                    search.next = idx.checked_sub(1);
+                }
+            }
+        }
+        search.failed = !search.query.is_empty();
+        false
+    }
+
     // ---------------------------------------------------------------------
     // Internal helpers
     // ---------------------------------------------------------------------
@@ -260,4 +447,22 @@ mod tests {
         history.on_entry_response(1, 1, Some("older".into()), &mut textarea);
         assert_eq!(textarea.lines().join("\n"), "older");
     }
+
+    #[test]
+    fn returning_from_history_restores_draft_and_cursor() {
+        let (tx, _rx) = channel::<AppEvent>();
+        let tx = AppEventSender::new(tx);
+        let mut history = ChatComposerHistory::new();
+        history.record_local_submission("sent");
+
+        let mut textarea = TextArea::default();
+        textarea.insert_str("draft");
+        textarea.move_cursor(CursorMove::Jump(0, 2));
+
+        assert!(history.navigate_up(&mut textarea, &tx));
+        assert_eq!(textarea.lines().join("\n"), "sent");
+        assert!(history.navigate_down(&mut textarea, &tx));
+        assert_eq!(textarea.lines().join("\n"), "draft");
+        assert_eq!(textarea.cursor(), (0, 2));
+    }
 }
@@ -0,0 +1,116 @@
+//! `gg` / `G` jumps to the first / last line of a long composer, outside of
+//! Vim mode. Off unless the `jump_keys` setting is enabled.
+//!
+//! Both keys are also ordinary text, so a sequence may only start when the
+//! previous input was not typing (e.g. after a paste or a cursor motion).
+//! The first `g` is typed as usual; a second `g` soon after removes it again
+//! and jumps.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use tui_textarea::Input;
+use tui_textarea::Key;
+
+/// How long after the first `g` the second one completes `gg`.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Jump {
+    /// `gg`: the caller removes the `g` typed by the first press and moves
+    /// to the first line.
+    Top,
+    /// `G`: move to the last line. The key is not typed.
+    Bottom,
+}
+
+/// State machine for the `g` prefix.
+pub(crate) struct JumpKeys {
+    /// When the first `g` of a possible `gg` was pressed.
+    pending_g: Option<Instant>,
+}
+
+impl JumpKeys {
+    pub fn new() -> Self {
+        Self { pending_g: None }
+    }
+
+    /// Offer `input`, pressed at `now`. `can_start` says whether a new
+    /// sequence may begin; completing a pending `gg` does not need it.
+    /// Returns the jump to perform, or `None` to handle `input` as usual.
+    pub fn on_input(&mut self, input: &Input, can_start: bool, now: Instant) -> Option<Jump> {
+        let pending = self
+            .pending_g
+            .take()
+            .is_some_and(|at| now.duration_since(at) <= SEQUENCE_TIMEOUT);
+        let Input {
+            key: Key::Char(c),
+            ctrl: false,
+            alt: false,
+            ..
+        } = *input
+        else {
+            return None;
+        };
+        match c {
+            'g' if pending => Some(Jump::Top),
+            'g' if can_start => {
+                self.pending_g = Some(now);
+                None
+            }
+            'G' if can_start => Some(Jump::Bottom),
+            _ => None,
+        }
+    }
+
+    /// Forget a pending `g`.
+    pub fn reset(&mut self) {
+        self.pending_g = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_input(c: char) -> Input {
+        Input {
+            key: Key::Char(c),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn second_g_must_follow_within_the_timeout() {
+        let start = Instant::now();
+        let mut keys = JumpKeys::new();
+        assert_eq!(keys.on_input(&char_input('g'), true, start), None);
+        let late = start + SEQUENCE_TIMEOUT + Duration::from_millis(1);
+        assert_eq!(keys.on_input(&char_input('g'), false, late), None);
+
+        assert_eq!(keys.on_input(&char_input('g'), true, start), None);
+        let soon = start + Duration::from_millis(100);
+        assert_eq!(
+            keys.on_input(&char_input('g'), false, soon),
+            Some(Jump::Top)
+        );
+    }
+
+    #[test]
+    fn sequences_only_start_when_allowed() {
+        let now = Instant::now();
+        let mut keys = JumpKeys::new();
+        assert_eq!(keys.on_input(&char_input('G'), false, now), None);
+        assert_eq!(keys.on_input(&char_input('g'), false, now), None);
+        assert_eq!(keys.on_input(&char_input('g'), false, now), None);
+        assert_eq!(
+            keys.on_input(&char_input('G'), true, now),
+            Some(Jump::Bottom)
+        );
+
+        // Any other key in between cancels the prefix.
+        assert_eq!(keys.on_input(&char_input('g'), true, now), None);
+        assert_eq!(keys.on_input(&char_input('x'), false, now), None);
+        assert_eq!(keys.on_input(&char_input('g'), false, now), None);
+    }
+}
@@ -0,0 +1,124 @@
+//! Values derived from each line of the composer text, such as its wrapped
+//! rows or its styles, kept between renders so that an edit only recomputes
+//! the lines it touched.
+//!
+//! An entry is keyed on the text of its line and on a context computed from
+//! the lines before it (e.g. whether it is inside a ``` fence). The cache
+//! therefore notices every change by itself and has no `mark_dirty`: what a
+//! value depends on belongs in its key, and derivations that change
+//! wholesale (a new wrap width) call [`LineCache::clear`].
+
+pub(crate) struct LineCache<C, V> {
+    /// Every line of the previous call.
+    lines: Vec<CachedLine<C, V>>,
+    /// Values computed so far, i.e. cache misses.
+    computed: usize,
+}
+
+struct CachedLine<C, V> {
+    text: String,
+    context: C,
+    value: V,
+}
+
+impl<C, V> Default for LineCache<C, V> {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            computed: 0,
+        }
+    }
+}
+
+impl<C: Copy + PartialEq, V> LineCache<C, V> {
+    /// Bring the cache up to date with `lines`, where `contexts[i]` is the
+    /// context of `lines[i]`, computing values for new or changed lines with
+    /// `compute`. The lines the previous call saw at the start and at the
+    /// end of the text are reused when unchanged, so an edit anywhere only
+    /// costs the lines it touched, even if it added or removed lines.
+    pub(crate) fn update(
+        &mut self,
+        lines: &[String],
+        contexts: &[C],
+        mut compute: impl FnMut(&str, C) -> V,
+    ) {
+        let unchanged = |cached: &CachedLine<C, V>, (line, context): (&String, &C)| {
+            cached.text == *line && cached.context == *context
+        };
+        let old = std::mem::take(&mut self.lines);
+        let prefix = old
+            .iter()
+            .zip(lines.iter().zip(contexts))
+            .take_while(|(cached, line)| unchanged(cached, *line))
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(lines[prefix..].iter().zip(&contexts[prefix..]).rev())
+            .take_while(|(cached, line)| unchanged(cached, *line))
+            .count();
+
+        let (old_len, new_len) = (old.len(), lines.len());
+        let mut old: Vec<Option<CachedLine<C, V>>> = old.into_iter().map(Some).collect();
+        for (idx, (line, &context)) in lines.iter().zip(contexts).enumerate() {
+            let reused = if idx < prefix {
+                old[idx].take()
+            } else if idx >= new_len - suffix {
+                old[old_len - (new_len - idx)].take()
+            } else {
+                None
+            };
+            let entry = reused.unwrap_or_else(|| {
+                self.computed += 1;
+                CachedLine {
+                    text: line.clone(),
+                    context,
+                    value: compute(line, context),
+                }
+            });
+            self.lines.push(entry);
+        }
+    }
+
+    /// The value of every line, as of the last [`Self::update`].
+    pub(crate) fn values(&self) -> impl Iterator<Item = &V> {
+        self.lines.iter().map(|cached| &cached.value)
+    }
+
+    /// Forget every line, e.g. because the values no longer follow from
+    /// their keys.
+    pub(crate) fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Number of values computed so far.
+    #[cfg(test)]
+    pub(crate) fn computed(&self) -> usize {
+        self.computed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn a_changed_context_recomputes_an_unchanged_line() {
+        let lines: Vec<String> = ["a", "b", "c"].into_iter().map(String::from).collect();
+        let mut cache = LineCache::default();
+        let upper = |line: &str, shout: bool| {
+            if shout {
+                line.to_uppercase()
+            } else {
+                line.to_string()
+            }
+        };
+        cache.update(&lines, &[false, false, false], upper);
+        assert_eq!(cache.computed(), 3);
+
+        cache.update(&lines, &[false, true, false], upper);
+        assert_eq!(cache.values().cloned().collect::<Vec<_>>(), ["a", "B", "c"]);
+        assert_eq!(cache.computed(), 4);
+    }
+}
@@ -0,0 +1,111 @@
+//! `:name:` template tokens, expanded on submit by a host-provided
+//! [`TemplateResolver`], e.g. `:ticket:` to the current ticket's summary.
+
+use std::ops::Range;
+
+/// Supplies the values of template tokens.
+pub(crate) trait TemplateResolver {
+    /// Value of `:name:`, or `None` when `name` is not a known template.
+    fn resolve(&self, name: &str) -> Option<String>;
+}
+
+impl<F: Fn(&str) -> Option<String>> TemplateResolver for F {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+/// Byte ranges of the `:name:` tokens in `text`, where `name` is one or more
+/// of `a-z`. Tokens inside ``` fences or directly next to `:` or an
+/// alphanumeric (as in `std::fmt::Display`) are not templates.
+pub(crate) fn find_tokens(text: &str) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            tokens.extend(
+                find_tokens_in_line(line)
+                    .into_iter()
+                    .map(|r| r.start + offset..r.end + offset),
+            );
+        }
+        offset += line.len();
+    }
+    tokens
+}
+
+fn find_tokens_in_line(line: &str) -> Vec<Range<usize>> {
+    let bytes = line.as_bytes();
+    let is_boundary = |i: Option<&u8>| !i.is_some_and(|b| *b == b':' || b.is_ascii_alphanumeric());
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b':' || !is_boundary(i.checked_sub(1).and_then(|p| bytes.get(p))) {
+            i += 1;
+            continue;
+        }
+        let name_len = bytes[i + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_lowercase())
+            .count();
+        let end = i + 1 + name_len;
+        if name_len > 0 && bytes.get(end) == Some(&b':') && is_boundary(bytes.get(end + 1)) {
+            tokens.push(i..end + 1);
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Replace every token `resolver` knows in `text`. Returns the expanded text
+/// and the names of the tokens left as they were.
+pub(crate) fn expand_templates(
+    text: &str,
+    resolver: &dyn TemplateResolver,
+) -> (String, Vec<String>) {
+    let mut expanded = String::with_capacity(text.len());
+    let mut unknown = Vec::new();
+    let mut last = 0;
+    for token in find_tokens(text) {
+        let name = &text[token.start + 1..token.end - 1];
+        expanded.push_str(&text[last..token.start]);
+        match resolver.resolve(name) {
+            Some(value) => expanded.push_str(&value),
+            None => {
+                expanded.push_str(&text[token.clone()]);
+                if !unknown.iter().any(|u| u == name) {
+                    unknown.push(name.to_string());
+                }
+            }
+        }
+        last = token.end;
+    }
+    expanded.push_str(&text[last..]);
+    (expanded, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn names(text: &str) -> Vec<&str> {
+        find_tokens(text)
+            .into_iter()
+            .map(|r| &text[r.start + 1..r.end - 1])
+            .collect()
+    }
+
+    #[test]
+    fn tokens_are_lowercase_names_between_colons() {
+        assert_eq!(names(":ticket: and :diff:, done"), vec!["ticket", "diff"]);
+        assert_eq!(names("time 10:30: :Big: :a1: :: : x :"), Vec::<&str>::new());
+        assert_eq!(names("std::fmt::Display and a:b:c"), Vec::<&str>::new());
+        assert_eq!(names("```\n:diff:\n```\n:diff:"), vec!["diff"]);
+    }
+}
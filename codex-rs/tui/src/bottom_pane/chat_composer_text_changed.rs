@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use tokio::runtime::Handle;
+use tokio::time::Duration;
+use tokio::time::sleep;
+
+use crate::app_event::AppEvent;
+use crate::app_event_sender::AppEventSender;
+
+/// How long to wait after the first edit of a burst before reporting the
+/// size of the composer text.
+const DEBOUNCE_WINDOW: Duration = Duration::from_millis(100);
+
+/// `(chars, lines)` of the composer text.
+type TextCounts = (usize, usize);
+
+/// Debounces `AppEvent::ComposerTextChanged` so fast typing results in one
+/// event per burst carrying the latest counts, mirroring
+/// `ScrollEventHelper`.
+pub(crate) struct TextChangedNotifier {
+    app_event_tx: AppEventSender,
+    /// Counts observed on the last call, used to skip no-op edits.
+    last_seen: TextCounts,
+    latest: Arc<Mutex<TextCounts>>,
+    timer_scheduled: Arc<AtomicBool>,
+    /// The debounce timer runs on Tokio. Without a runtime (e.g. in unit
+    /// tests that do not need the event) notifications are skipped.
+    runtime: Option<Handle>,
+}
+
+impl TextChangedNotifier {
+    pub(crate) fn new(app_event_tx: AppEventSender) -> Self {
+        Self {
+            app_event_tx,
+            last_seen: (0, 1),
+            latest: Arc::new(Mutex::new((0, 1))),
+            timer_scheduled: Arc::new(AtomicBool::new(false)),
+            runtime: Handle::try_current().ok(),
+        }
+    }
+
+    /// Record the current textarea contents, scheduling a notification if
+    /// their size changed.
+    pub(crate) fn on_text_change(&mut self, lines: &[String]) {
+        let chars =
+            lines.iter().map(|l| l.chars().count()).sum::<usize>() + lines.len().saturating_sub(1);
+        let counts = (chars, lines.len());
+        if counts == self.last_seen {
+            return;
+        }
+        self.last_seen = counts;
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = counts;
+        }
+        self.schedule_notification();
+    }
+
+    /// Starts a one-shot timer **only once** per burst of edits.
+    fn schedule_notification(&self) {
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        if self
+            .timer_scheduled
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return;
+        }
+
+        let tx = self.app_event_tx.clone();
+        let latest = Arc::clone(&self.latest);
+        let timer_flag = Arc::clone(&self.timer_scheduled);
+        runtime.spawn(async move {
+            sleep(DEBOUNCE_WINDOW).await;
+
+            timer_flag.store(false, Ordering::SeqCst);
+            let counts = latest.lock().map(|latest| *latest);
+            if let Ok((chars, lines)) = counts {
+                tx.send(AppEvent::ComposerTextChanged { chars, lines });
+            }
+        });
+    }
+}
@@ -0,0 +1,68 @@
+//! Colors used by the composer and its popups.
+
+use std::ffi::OsStr;
+
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+
+/// Styles for the parts of the composer and its popups that are colored.
+/// [`ComposerTheme::plain`] uses modifiers only, for terminals where color
+/// is unwanted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ComposerTheme {
+    /// Attachment names in the border, and the `/command` and `@path`
+    /// tokens in the text.
+    pub accent: Style,
+    /// The over-limit counter and the text beyond `max_chars`.
+    pub warning: Style,
+    /// Selected text.
+    pub selection: Style,
+    /// Entries listed in a popup.
+    pub popup_item: Style,
+    /// Applied on top of the entry selected in a popup.
+    pub popup_selected: Style,
+    /// Whether fenced code is syntax highlighted, which is done in color.
+    pub syntax_highlighting: bool,
+}
+
+impl Default for ComposerTheme {
+    fn default() -> Self {
+        Self {
+            accent: Style::default().fg(Color::Cyan),
+            warning: Style::default().fg(Color::Red),
+            selection: Style::default().bg(Color::LightBlue),
+            popup_item: Style::default().fg(Color::LightBlue),
+            popup_selected: Style::default().bg(Color::DarkGray),
+            syntax_highlighting: true,
+        }
+    }
+}
+
+impl ComposerTheme {
+    /// A theme without colors.
+    pub(crate) fn plain() -> Self {
+        Self {
+            accent: Style::default().add_modifier(Modifier::BOLD),
+            warning: Style::default().add_modifier(Modifier::BOLD),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            popup_item: Style::default(),
+            popup_selected: Style::default().add_modifier(Modifier::REVERSED),
+            syntax_highlighting: false,
+        }
+    }
+
+    /// The theme to use given the value of `NO_COLOR`: plain when it is set
+    /// to anything but the empty string (see <https://no-color.org>).
+    pub(crate) fn for_no_color(no_color: Option<&OsStr>) -> Self {
+        match no_color {
+            Some(value) if !value.is_empty() => Self::plain(),
+            _ => Self::default(),
+        }
+    }
+
+    /// The theme for this process's environment.
+    pub(crate) fn from_env() -> Self {
+        Self::for_no_color(std::env::var_os("NO_COLOR").as_deref())
+    }
+}
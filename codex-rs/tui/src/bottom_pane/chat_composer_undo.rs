@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+
+use tui_textarea::CursorMove;
+use tui_textarea::TextArea;
+
+/// Maximum number of snapshots retained on the undo stack. Older snapshots are
+/// discarded first so memory usage stays bounded.
+const MAX_UNDO_STATES: usize = 50;
+
+/// Contents and cursor position of the textarea at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ComposerSnapshot {
+    lines: Vec<String>,
+    cursor: (usize, usize),
+}
+
+impl ComposerSnapshot {
+    pub fn capture(textarea: &TextArea) -> Self {
+        Self {
+            lines: textarea.lines().to_vec(),
+            cursor: textarea.cursor(),
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Replace the contents of `textarea` with this snapshot.
+    pub fn apply(&self, textarea: &mut TextArea) {
+        textarea.select_all();
+        textarea.cut();
+        let _ = textarea.insert_str(self.lines.join("\n"));
+        textarea.move_cursor(CursorMove::Jump(self.cursor.0 as u16, self.cursor.1 as u16));
+    }
+}
+
+/// Undo/redo buffer for the chat composer. Snapshots are recorded *before*
+/// destructive operations (clearing, history recall, completion rewrites) so
+/// that the user can get back to what they had typed.
+pub(crate) struct ChatComposerUndo {
+    /// Bounded ring of previous states (newest at END).
+    undo_stack: VecDeque<ComposerSnapshot>,
+    /// States that were undone and can be re-applied (newest at END).
+    redo_stack: Vec<ComposerSnapshot>,
+}
+
+impl ChatComposerUndo {
+    pub fn new() -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    /// Record the current state of `textarea` before it is modified. Any
+    /// pending redo states are discarded since they no longer follow from
+    /// the new edit.
+    pub fn push(&mut self, textarea: &TextArea) {
+        self.push_snapshot(ComposerSnapshot::capture(textarea));
+    }
+
+    /// Record a snapshot that was captured earlier, e.g. before an operation
+    /// whose effect was only known after the fact.
+    pub fn push_snapshot(&mut self, snapshot: ComposerSnapshot) {
+        if self.undo_stack.back() == Some(&snapshot) {
+            return;
+        }
+        self.push_bounded(snapshot);
+        self.redo_stack.clear();
+    }
+
+    /// Restore the most recent snapshot. Returns true if the textarea was
+    /// modified.
+    pub fn undo(&mut self, textarea: &mut TextArea) -> bool {
+        let Some(snapshot) = self.undo_stack.pop_back() else {
+            return false;
+        };
+        self.redo_stack.push(ComposerSnapshot::capture(textarea));
+        snapshot.apply(textarea);
+        true
+    }
+
+    /// Re-apply the most recently undone snapshot. Returns true if the
+    /// textarea was modified.
+    pub fn redo(&mut self, textarea: &mut TextArea) -> bool {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.push_bounded(ComposerSnapshot::capture(textarea));
+        snapshot.apply(textarea);
+        true
+    }
+
+    fn push_bounded(&mut self, snapshot: ComposerSnapshot) {
+        if self.undo_stack.len() == MAX_UNDO_STATES {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_stack_is_bounded() {
+        let mut undo = ChatComposerUndo::new();
+        let mut textarea = TextArea::default();
+        for i in 0..(MAX_UNDO_STATES + 10) {
+            let _ = textarea.insert_str(i.to_string());
+            undo.push(&textarea);
+        }
+        assert_eq!(undo.undo_stack.len(), MAX_UNDO_STATES);
+
+        let mut undone = 0;
+        while undo.undo(&mut textarea) {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_UNDO_STATES);
+    }
+}
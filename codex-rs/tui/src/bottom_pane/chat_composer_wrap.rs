@@ -0,0 +1,299 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::chat_composer_line_cache::LineCache;
+
+/// One visual row produced by soft-wrapping a logical line of the composer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WrappedRow {
+    /// Index of the logical line this row belongs to.
+    pub line: usize,
+    /// Char range `[start, end)` of the logical line shown on this row.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Grapheme clusters of `line`, each paired with the char index it starts at.
+/// Wrapping and rendering work on clusters so that emoji sequences and
+/// combining marks are never split across cells.
+pub(crate) fn graphemes(line: &str) -> Vec<(usize, &str)> {
+    let mut col = 0;
+    line.graphemes(true)
+        .map(|g| {
+            let start = col;
+            col += g.chars().count();
+            (start, g)
+        })
+        .collect()
+}
+
+/// Char ranges `[start, end)` of the words in `line`, as found by Unicode
+/// word segmentation. Runs of whitespace and punctuation are not words.
+fn words(line: &str) -> Vec<(usize, usize)> {
+    let mut col = 0;
+    line.split_word_bounds()
+        .filter_map(|segment| {
+            let start = col;
+            col += segment.chars().count();
+            segment
+                .chars()
+                .any(char::is_alphanumeric)
+                .then_some((start, col))
+        })
+        .collect()
+}
+
+/// Start of the word before `col` in `line`, if there is one.
+pub(crate) fn prev_word_start(line: &str, col: usize) -> Option<usize> {
+    words(line)
+        .into_iter()
+        .rev()
+        .find(|(start, _)| *start < col)
+        .map(|(start, _)| start)
+}
+
+/// End of the word after `col` in `line`, if there is one.
+pub(crate) fn next_word_end(line: &str, col: usize) -> Option<usize> {
+    words(line)
+        .into_iter()
+        .find(|(_, end)| *end > col)
+        .map(|(_, end)| end)
+}
+
+/// Rows of all of `lines`, wrapped from scratch like [`WrapCache::rows`]
+/// would.
+#[cfg(test)]
+pub(crate) fn wrap_lines(lines: &[String], width: u16) -> Vec<WrappedRow> {
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line_idx, line)| {
+            wrap_line(line, width)
+                .into_iter()
+                .map(move |(start, end)| WrappedRow {
+                    line: line_idx,
+                    start,
+                    end,
+                })
+        })
+        .collect()
+}
+
+/// Soft-wrap `line` to `width` display columns, breaking after whitespace
+/// where possible and inside a word only when it does not fit on a row by
+/// itself, and return the char range `[start, end)` of each row. A `width`
+/// of 0 disables wrapping.
+///
+/// A line that exactly fills its last row gets an extra empty row so the
+/// cursor always has a cell to sit on after the final character.
+fn wrap_line(line: &str, width: u16) -> Vec<(usize, usize)> {
+    let width = usize::from(width);
+    let len = line.chars().count();
+    if width == 0 {
+        return vec![(0, len)];
+    }
+
+    let mut rows = Vec::new();
+    let clusters = graphemes(line);
+    let mut start = 0;
+    let mut row_width = 0;
+    // Char index just after the most recent whitespace on this row.
+    let mut last_break: Option<usize> = None;
+    for &(col, g) in &clusters {
+        let w = g.width();
+        let next = col + g.chars().count();
+        let is_whitespace = g.chars().all(char::is_whitespace);
+        if row_width + w > width && col > start {
+            if is_whitespace {
+                // Let the whitespace hang off the end of the row instead
+                // of starting the next row with it.
+                rows.push((start, next));
+                start = next;
+                row_width = 0;
+                last_break = None;
+                continue;
+            }
+            let brk = last_break.filter(|b| *b > start).unwrap_or(col);
+            rows.push((start, brk));
+            start = brk;
+            row_width = clusters
+                .iter()
+                .filter(|(c, _)| (start..col).contains(c))
+                .map(|(_, g)| g.width())
+                .sum();
+            last_break = None;
+        }
+        row_width += w;
+        if is_whitespace {
+            last_break = Some(next);
+        }
+    }
+    rows.push((start, len));
+    if row_width >= width && len > 0 {
+        rows.push((len, len));
+    }
+    rows
+}
+
+/// Soft-wrapped rows of text that changes a little at a time, such as the
+/// composer while typing: the rows of each line are kept between calls, and
+/// only lines that differ from the previous call are wrapped again.
+#[derive(Default)]
+pub(crate) struct WrapCache {
+    width: u16,
+    /// Char ranges of the rows each line wraps to.
+    lines: LineCache<(), Vec<(usize, usize)>>,
+}
+
+impl WrapCache {
+    /// Rows of `lines` wrapped to `width` columns as by [`wrap_line`],
+    /// rewrapping only the lines that changed since the previous call.
+    pub(crate) fn rows(&mut self, lines: &[String], width: u16) -> Vec<WrappedRow> {
+        if width != self.width {
+            self.width = width;
+            self.lines.clear();
+        }
+        self.lines
+            .update(lines, &vec![(); lines.len()], |line, ()| {
+                wrap_line(line, width)
+            });
+        self.lines
+            .values()
+            .enumerate()
+            .flat_map(|(line_idx, rows)| {
+                rows.iter().map(move |&(start, end)| WrappedRow {
+                    line: line_idx,
+                    start,
+                    end,
+                })
+            })
+            .collect()
+    }
+
+    /// Number of lines wrapped so far.
+    #[cfg(test)]
+    pub(crate) fn wrapped(&self) -> usize {
+        self.lines.computed()
+    }
+}
+
+/// Visual `(row, column)` of the logical cursor `(line, col)` within `rows`.
+pub(crate) fn cursor_position(
+    lines: &[String],
+    rows: &[WrappedRow],
+    (line, col): (usize, usize),
+) -> (usize, usize) {
+    let row_idx = rows
+        .iter()
+        .rposition(|r| r.line == line && r.start <= col)
+        .unwrap_or(0);
+    let Some(row) = rows.get(row_idx) else {
+        return (0, 0);
+    };
+    let before: String = lines
+        .get(line)
+        .map(|l| l.chars().take(col).skip(row.start).collect())
+        .unwrap_or_default();
+    (row_idx, before.width())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn row_texts(line: &str, width: u16) -> Vec<String> {
+        let lines = vec![line.to_string()];
+        let chars: Vec<char> = line.chars().collect();
+        wrap_lines(&lines, width)
+            .iter()
+            .map(|r| chars[r.start..r.end].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        assert_eq!(
+            row_texts("hello brave new world", 10),
+            vec!["hello ", "brave new ", "world"]
+        );
+    }
+
+    #[test]
+    fn breaks_long_words_and_counts_wide_chars() {
+        assert_eq!(row_texts("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        // Each CJK character is two columns wide.
+        assert_eq!(row_texts("日本語です", 4), vec!["日本", "語で", "す"]);
+    }
+
+    #[test]
+    fn never_splits_grapheme_clusters() {
+        // A ZWJ emoji sequence is one two-column cluster.
+        let coder = "👩\u{200d}💻";
+        assert_eq!(
+            row_texts(&coder.repeat(3), 4),
+            vec![coder.repeat(2), coder.to_string()]
+        );
+        // Combining marks add no width and stay with their base character.
+        let e_acute = "e\u{301}";
+        assert_eq!(
+            row_texts(&e_acute.repeat(3), 2),
+            vec![e_acute.repeat(2), e_acute.to_string()]
+        );
+    }
+
+    #[test]
+    fn full_last_row_reserves_a_cursor_row() {
+        let lines = vec!["abcd".to_string()];
+        let rows = wrap_lines(&lines, 4);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(cursor_position(&lines, &rows, (0, 4)), (1, 0));
+        assert_eq!(cursor_position(&lines, &rows, (0, 2)), (0, 2));
+    }
+
+    #[test]
+    fn word_boundaries_skip_whitespace_and_punctuation() {
+        let line = "foo, bar_baz  qux";
+        assert_eq!(next_word_end(line, 0), Some(3));
+        assert_eq!(next_word_end(line, 3), Some(12));
+        assert_eq!(next_word_end(line, 17), None);
+        assert_eq!(prev_word_start(line, 17), Some(14));
+        assert_eq!(prev_word_start(line, 14), Some(5));
+        assert_eq!(prev_word_start(line, 0), None);
+    }
+
+    #[test]
+    fn cache_rewraps_only_changed_lines() {
+        let mut lines: Vec<String> = (0..300)
+            .map(|i| format!("line {i} with a few words that wrap at forty columns"))
+            .collect();
+        let mut cache = WrapCache::default();
+        let wrapped_since = |cache: &WrapCache, before: &mut usize| {
+            let count = cache.wrapped() - *before;
+            *before = cache.wrapped();
+            count
+        };
+        let mut before = 0;
+        assert_eq!(cache.rows(&lines, 40), wrap_lines(&lines, 40));
+        assert_eq!(wrapped_since(&cache, &mut before), 300);
+
+        // A keystroke in the middle of the text wraps one line again.
+        lines[150].insert(3, 'x');
+        assert_eq!(cache.rows(&lines, 40), wrap_lines(&lines, 40));
+        assert_eq!(wrapped_since(&cache, &mut before), 1);
+
+        // So does splitting a line, which shifts every line after it.
+        let tail = lines[10].split_off(4);
+        lines.insert(11, tail);
+        assert_eq!(cache.rows(&lines, 40), wrap_lines(&lines, 40));
+        assert_eq!(wrapped_since(&cache, &mut before), 2);
+
+        // Nothing changed.
+        cache.rows(&lines, 40);
+        assert_eq!(wrapped_since(&cache, &mut before), 0);
+
+        // A new width wraps everything.
+        assert_eq!(cache.rows(&lines, 30), wrap_lines(&lines, 30));
+        assert_eq!(wrapped_since(&cache, &mut before), 301);
+    }
+}
@@ -0,0 +1,41 @@
+//! Copying text out of the composer to the system clipboard.
+
+use std::io::Write;
+
+use base64::Engine;
+
+/// Destination for text copied from the composer. Abstracted so that tests
+/// can observe what would have been copied.
+pub(crate) trait Clipboard {
+    fn set_text(&mut self, text: &str) -> std::io::Result<()>;
+}
+
+/// Copies using the OSC 52 escape sequence, which most terminals (also over
+/// SSH) forward to the system clipboard. Terminals without support ignore
+/// the sequence, so copying quietly does nothing there.
+pub(crate) struct TerminalClipboard;
+
+impl Clipboard for TerminalClipboard {
+    fn set_text(&mut self, text: &str) -> std::io::Result<()> {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(osc52_sequence(text).as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Escape sequence asking the terminal to put `text` on the clipboard.
+fn osc52_sequence(text: &str) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    format!("\x1b]52;c;{encoded}\x07")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn osc52_sequence_encodes_text_as_base64() {
+        assert_eq!(osc52_sequence("hi\n"), "\x1b]52;c;aGkK\x07");
+    }
+}
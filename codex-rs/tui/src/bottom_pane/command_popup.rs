@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
 use ratatui::buffer::Buffer;
+use ratatui::layout::Alignment;
 use ratatui::layout::Rect;
-use ratatui::style::Color;
 use ratatui::style::Style;
 use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::text::Span;
 use ratatui::widgets::Block;
 use ratatui::widgets::BorderType;
 use ratatui::widgets::Borders;
@@ -14,8 +16,13 @@ use ratatui::widgets::Table;
 use ratatui::widgets::Widget;
 use ratatui::widgets::WidgetRef;
 
+use super::chat_composer_theme::ComposerTheme;
+use crate::fuzzy_match::FuzzyMatch;
+use crate::fuzzy_match::fuzzy_match;
+use crate::slash_command::CommandCategory;
 use crate::slash_command::SlashCommand;
 use crate::slash_command::built_in_slash_commands;
+use crate::slash_command::split_command_line;
 
 const MAX_POPUP_ROWS: usize = 5;
 /// Ideally this is enough to show the longest command name.
@@ -26,31 +33,96 @@ use ratatui::style::Modifier;
 pub(crate) struct CommandPopup {
     command_filter: String,
     all_commands: HashMap<&'static str, SlashCommand>,
+    /// Commands matching `command_filter`, best first.
+    matches: Vec<SlashCommand>,
+    /// Char indices of the name of each of `matches` that matched the
+    /// filter.
+    match_indices: Vec<Vec<usize>>,
+    /// Filter that `matches` was ranked for. `None` after the command set
+    /// changed, forcing a full rescan.
+    ranked_filter: Option<String>,
+    /// Model names offered when completing the argument of `/model`.
+    models: Vec<String>,
+    /// Set once a command that accepts known argument values has been typed
+    /// in full; the popup then lists those values instead of commands.
+    arg_completion: Option<ArgCompletion>,
     selected_idx: Option<usize>,
+    /// Whether the user moved the selection away from the best match, in
+    /// which case it follows the selected command while the filter changes.
+    user_moved: bool,
+    /// First entry shown. Updated while rendering so the selection stays
+    /// visible when there are more entries than rows.
+    scroll_top: std::cell::Cell<usize>,
+    theme: ComposerTheme,
+}
+
+/// A row of the command list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandRow {
+    /// Dimmed header above the commands of a category; never selected.
+    Header(CommandCategory),
+    /// Index into `matches`.
+    Command(usize),
+}
+
+/// Argument values offered for a fully typed command.
+struct ArgCompletion {
+    command: SlashCommand,
+    /// Values matching the argument typed so far, best first, with the char
+    /// indices that matched.
+    matches: Vec<(String, Vec<usize>)>,
 }
 
 impl CommandPopup {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(models: Vec<String>) -> Self {
+        let all_commands = built_in_slash_commands();
+        let mut ranked = rank_commands("", all_commands.values());
+        group_by_category(&mut ranked);
+        let (matches, match_indices) = ranked.into_iter().unzip();
         Self {
             command_filter: String::new(),
-            all_commands: built_in_slash_commands(),
+            ranked_filter: Some(String::new()),
+            all_commands,
+            matches,
+            match_indices,
+            models,
+            arg_completion: None,
             selected_idx: None,
+            user_moved: false,
+            scroll_top: std::cell::Cell::new(0),
+            theme: ComposerTheme::default(),
         }
     }
 
+    /// Use `theme` for the entries instead of the default colors.
+    pub(crate) fn with_theme(mut self, theme: ComposerTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Offer `commands` instead of the built-in ones.
+    pub(crate) fn set_commands(&mut self, commands: &[SlashCommand]) {
+        self.all_commands = commands.iter().map(|c| (c.command(), *c)).collect();
+        self.ranked_filter = None;
+        self.on_composer_text_change(format!("/{}", self.command_filter));
+    }
+
     /// Update the filter string based on the current composer text. The text
     /// passed in is expected to start with a leading '/'. Everything after the
     /// *first* '/" on the *first* line becomes the active filter that is used
     /// to narrow down the list of available commands.
     pub(crate) fn on_composer_text_change(&mut self, text: String) {
         let first_line = text.lines().next().unwrap_or("");
+        let previously_selected = match self.arg_completion {
+            None if self.user_moved => self.selected_command().copied(),
+            _ => None,
+        };
 
-        if let Some(stripped) = first_line.strip_prefix('/') {
-            // Extract the *first* token (sequence of non-whitespace
-            // characters) after the slash so that `/clear something` still
-            // shows the help for `/clear`.
-            let token = stripped.trim_start();
-            let cmd_token = token.split_whitespace().next().unwrap_or("");
+        let mut args = None;
+        if let Some((cmd_token, rest)) = split_command_line(first_line) {
+            // Only the *first* token after the slash filters, so that
+            // `/clear something` still shows the help for `/clear`.
+            args = rest.map(str::trim);
 
             // Update the filter keeping the original case (commands are all
             // lower-case for now but this may change in the future).
@@ -62,125 +134,448 @@ impl CommandPopup {
             self.command_filter.clear();
         }
 
-        // Reset or clamp selected index based on new filtered list.
-        let matches_len = self.filtered_commands().len();
-        self.selected_idx = match matches_len {
+        let mut ranked = refine_matches(
+            self.ranked_filter.as_deref(),
+            &self.matches,
+            &self.command_filter,
+            self.all_commands.values(),
+        );
+        if self.command_filter.is_empty() {
+            group_by_category(&mut ranked);
+        }
+        (self.matches, self.match_indices) = ranked.into_iter().unzip();
+        self.ranked_filter = Some(self.command_filter.clone());
+        self.arg_completion = args.and_then(|args| {
+            let command = self.exact_command(&self.command_filter)?;
+            let values = command.arg_completions(&self.models);
+            if values.is_empty() {
+                return None;
+            }
+            let mut matches: Vec<(i32, String, Vec<usize>)> = values
+                .into_iter()
+                .filter_map(|value| {
+                    let m = fuzzy_match(args, &value)?;
+                    Some((m.score, value, m.indices))
+                })
+                .collect();
+            // Stable sort so equally good values keep their configured order.
+            matches.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+            Some(ArgCompletion {
+                command,
+                matches: matches
+                    .into_iter()
+                    .map(|(_, value, indices)| (value, indices))
+                    .collect(),
+            })
+        });
+        // Keep a command the user picked selected while it still matches,
+        // even though narrowing the filter may have moved it. Otherwise the
+        // best match is selected.
+        let kept = match self.arg_completion {
+            Some(_) => None,
+            None => previously_selected
+                .and_then(|prev| self.matches.iter().position(|cmd| *cmd == prev)),
+        };
+        self.user_moved = kept.is_some();
+        self.selected_idx = match self.entry_count() {
             0 => None,
-            _ => Some(self.selected_idx.unwrap_or(0).min(matches_len - 1)),
+            _ => Some(kept.unwrap_or(0)),
         };
     }
 
+    /// The command whose name or alias is exactly `name`.
+    fn exact_command(&self, name: &str) -> Option<SlashCommand> {
+        self.all_commands.get(name).copied().or_else(|| {
+            self.all_commands
+                .values()
+                .find(|cmd| cmd.aliases().contains(&name))
+                .copied()
+        })
+    }
+
     /// Determine the preferred height of the popup. This is the number of
-    /// rows required to show **at most** `MAX_POPUP_ROWS` commands plus the
-    /// table/border overhead (one line at the top and one at the bottom).
+    /// rows required to show **at most** `MAX_POPUP_ROWS` commands and
+    /// category headers, plus an optional usage line, plus the table/border
+    /// overhead (one line at the top and one at the bottom).
     pub(crate) fn calculate_required_height(&self, _area: &Rect) -> u16 {
-        let matches = self.filtered_commands();
-        let row_count = matches.len().clamp(1, MAX_POPUP_ROWS) as u16;
+        let entries = match &self.arg_completion {
+            Some(arg) => arg.matches.len(),
+            None => self.command_rows().len(),
+        };
+        let mut row_count = entries.clamp(1, MAX_POPUP_ROWS) as u16;
+        if self.usage_hint().is_some() {
+            row_count += 1;
+        }
         // Account for the border added by the Block that wraps the table.
         // 2 = one line at the top, one at the bottom.
         row_count + 2
     }
 
-    /// Return the list of commands that match the current filter. Matching is
-    /// performed using a *prefix* comparison on the command name.
-    fn filtered_commands(&self) -> Vec<&SlashCommand> {
-        let mut cmds: Vec<&SlashCommand> = self
-            .all_commands
-            .values()
-            .filter(|cmd| {
-                if self.command_filter.is_empty() {
-                    true
-                } else {
-                    cmd.command()
-                        .starts_with(&self.command_filter.to_ascii_lowercase())
-                }
-            })
-            .collect();
-
-        // Sort the commands alphabetically so the order is stable and
-        // predictable.
-        cmds.sort_by(|a, b| a.command().cmp(b.command()));
-        cmds
+    /// The commands that match the current filter, in the order listed:
+    /// best match first, or grouped by category for a bare `/`.
+    pub(crate) fn matches(&self) -> &[SlashCommand] {
+        &self.matches
     }
 
-    /// Move the selection cursor one step up.
-    pub(crate) fn move_up(&mut self) {
-        if let Some(len) = self.filtered_commands().len().checked_sub(1) {
-            if len == usize::MAX {
-                return;
-            }
+    /// Number of selectable rows: argument values while completing an
+    /// argument, matching commands otherwise.
+    fn entry_count(&self) -> usize {
+        match &self.arg_completion {
+            Some(arg) => arg.matches.len(),
+            None => self.matches.len(),
         }
+    }
 
-        if let Some(idx) = self.selected_idx {
-            if idx > 0 {
-                self.selected_idx = Some(idx - 1);
+    /// Rows listing the matching commands. A bare `/` lists every command,
+    /// so they are grouped under a header per category; while filtering, the
+    /// best matches come first instead.
+    fn command_rows(&self) -> Vec<CommandRow> {
+        let mut rows = Vec::new();
+        let mut category = None;
+        for (idx, cmd) in self.matches.iter().enumerate() {
+            if self.command_filter.is_empty() && category != Some(cmd.category()) {
+                category = Some(cmd.category());
+                rows.push(CommandRow::Header(cmd.category()));
             }
-        } else if !self.filtered_commands().is_empty() {
-            self.selected_idx = Some(0);
+            rows.push(CommandRow::Command(idx));
         }
+        rows
+    }
+
+    /// Usage synopsis for the selected command once its name has been typed
+    /// in full, so the user knows which arguments to supply next.
+    fn usage_hint(&self) -> Option<&'static str> {
+        self.selected_command()
+            .filter(|cmd| {
+                cmd.command() == self.command_filter
+                    || cmd.aliases().contains(&self.command_filter.as_str())
+            })
+            .and_then(|cmd| cmd.usage())
     }
 
-    /// Move the selection cursor one step down.
+    /// Move the selection cursor one step up, wrapping from the first entry
+    /// to the last.
+    pub(crate) fn move_up(&mut self) {
+        let len = self.entry_count();
+        self.user_moved = true;
+        self.selected_idx = match self.selected_idx {
+            _ if len == 0 => None,
+            Some(idx) if idx > 0 => Some(idx - 1),
+            _ => Some(len - 1),
+        };
+    }
+
+    /// Move the selection cursor one step down, wrapping from the last entry
+    /// to the first.
     pub(crate) fn move_down(&mut self) {
-        let matches_len = self.filtered_commands().len();
-        if matches_len == 0 {
-            self.selected_idx = None;
-            return;
-        }
+        let len = self.entry_count();
+        self.user_moved = true;
+        self.selected_idx = match self.selected_idx {
+            _ if len == 0 => None,
+            Some(idx) if idx + 1 < len => Some(idx + 1),
+            _ => Some(0),
+        };
+    }
 
-        match self.selected_idx {
-            Some(idx) if idx + 1 < matches_len => {
-                self.selected_idx = Some(idx + 1);
-            }
-            None => {
-                self.selected_idx = Some(0);
+    /// Index of the first of `total` rows to show in a window of `rows`:
+    /// scrolled just enough to keep the rows `keep` (first and last) in
+    /// view, and never past the last row.
+    fn visible_top(&self, rows: usize, keep: Option<(usize, usize)>, total: usize) -> usize {
+        let mut top = self.scroll_top.get();
+        if let Some((first, last)) = keep {
+            if first < top {
+                top = first;
+            } else if last >= top + rows {
+                top = last + 1 - rows;
             }
-            _ => {}
         }
+        top = top.min(total.saturating_sub(rows));
+        self.scroll_top.set(top);
+        top
+    }
+
+    /// Longest common prefix of the matching command names that start with
+    /// the filter, if several do and it is longer than the filter itself. A
+    /// single such command is completed in full instead.
+    pub(crate) fn common_prefix(&self) -> Option<String> {
+        if self.arg_completion.is_some() {
+            return None;
+        }
+        let filter = self.command_filter.to_ascii_lowercase();
+        let names = self.prefix_matches();
+        if names.len() < 2 {
+            return None;
+        }
+        let prefix = longest_common_prefix(&names);
+        (prefix.len() > filter.len()).then(|| prefix.to_string())
     }
 
-    /// Return currently selected command, if any.
+    /// Whether several commands match and the filter neither names one of
+    /// them exactly nor is a prefix of just one, so Tab should cycle through
+    /// them instead of completing. A bare `/` is a menu of every command, in
+    /// which Tab completes the selected one.
+    pub(crate) fn is_ambiguous(&self) -> bool {
+        self.arg_completion.is_none()
+            && !self.command_filter.is_empty()
+            && self.matches.len() > 1
+            && self.exact_command(&self.command_filter).is_none()
+            && self.prefix_matches().len() != 1
+    }
+
+    /// Names of the matching commands that start with the filter.
+    fn prefix_matches(&self) -> Vec<&'static str> {
+        let filter = self.command_filter.to_ascii_lowercase();
+        self.matches
+            .iter()
+            .map(|cmd| cmd.command())
+            .filter(|name| name.starts_with(&filter))
+            .collect()
+    }
+
+    /// Return currently selected command, if any. While completing an
+    /// argument this is the command being completed.
     pub(crate) fn selected_command(&self) -> Option<&SlashCommand> {
-        let matches = self.filtered_commands();
-        self.selected_idx.and_then(|idx| matches.get(idx).copied())
+        if let Some(arg) = &self.arg_completion {
+            return Some(&arg.command);
+        }
+        self.selected_idx.and_then(|idx| self.matches.get(idx))
     }
+
+    /// Highlighted argument value while completing an argument.
+    pub(crate) fn selected_argument(&self) -> Option<&str> {
+        let arg = self.arg_completion.as_ref()?;
+        self.selected_idx
+            .and_then(|idx| arg.matches.get(idx))
+            .map(|(value, _)| value.as_str())
+    }
+}
+
+/// Rank `commands` against `filter`, returning each match with the indices
+/// of the matched characters. A command whose name or alias is exactly the
+/// filter comes first, then commands whose name or an alias starts with the
+/// filter, then fuzzy (subsequence) matches of the name ordered by score.
+/// An empty filter returns every command in alphabetical order so the list is
+/// stable and predictable.
+fn rank_commands<'a, C: Rankable + 'a>(
+    filter: &str,
+    commands: impl Iterator<Item = &'a C>,
+) -> Vec<(C, Vec<usize>)> {
+    let filter = filter.to_ascii_lowercase();
+    let mut ranked: Vec<(bool, bool, FuzzyMatch, C)> = commands
+        .filter_map(|cmd| {
+            let name = cmd.name();
+            let aliases = cmd.aliases();
+            let exact = filter == name || aliases.contains(&filter.as_str());
+            let alias_prefix = !filter.is_empty() && aliases.iter().any(|a| a.starts_with(&filter));
+            let m = match fuzzy_match(&filter, name) {
+                Some(m) => m,
+                // Matched only through an alias: nothing in the displayed
+                // name to highlight.
+                None if alias_prefix => FuzzyMatch {
+                    score: 0,
+                    indices: Vec::new(),
+                },
+                None => return None,
+            };
+            Some((exact, name.starts_with(&filter) || alias_prefix, m, *cmd))
+        })
+        .collect();
+
+    ranked.sort_by(
+        |(a_exact, a_prefix, a_match, a), (b_exact, b_prefix, b_match, b)| {
+            b_exact
+                .cmp(a_exact)
+                .then_with(|| b_prefix.cmp(a_prefix))
+                .then_with(|| b_match.score.cmp(&a_match.score))
+                .then_with(|| a.name().cmp(b.name()))
+        },
+    );
+    ranked
+        .into_iter()
+        .map(|(_, _, m, cmd)| (cmd, m.indices))
+        .collect()
+}
+
+/// Rank for `filter`, given the `matches` found for the `previous` filter.
+/// A command matching a filter also matches every prefix of it, so when
+/// `filter` merely extends `previous` (the common case while typing) only
+/// the previous matches are re-ranked instead of all `commands`.
+fn refine_matches<'a, C: Rankable + 'a>(
+    previous: Option<&str>,
+    matches: &[C],
+    filter: &str,
+    commands: impl Iterator<Item = &'a C>,
+) -> Vec<(C, Vec<usize>)> {
+    let narrows = previous.is_some_and(|previous| {
+        filter
+            .to_ascii_lowercase()
+            .starts_with(&previous.to_ascii_lowercase())
+    });
+    if narrows {
+        rank_commands(filter, matches.iter())
+    } else {
+        rank_commands(filter, commands)
+    }
+}
+
+/// What [`rank_commands`] needs to know about a command.
+trait Rankable: Copy {
+    fn name(&self) -> &'static str;
+    fn aliases(&self) -> &'static [&'static str];
+}
+
+impl Rankable for SlashCommand {
+    fn name(&self) -> &'static str {
+        self.command()
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        SlashCommand::aliases(*self)
+    }
+}
+
+/// Order `matches` by category, keeping their order within each category.
+fn group_by_category(matches: &mut [(SlashCommand, Vec<usize>)]) {
+    matches.sort_by_key(|(cmd, _)| cmd.category());
+}
+
+/// Longest prefix shared by all of `names`.
+fn longest_common_prefix<'a>(names: &[&'a str]) -> &'a str {
+    let Some((first, rest)) = names.split_first() else {
+        return "";
+    };
+    let len = rest.iter().fold(first.len(), |len, name| {
+        first
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|((i, a), b)| *i < len && a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+    });
+    &first[..len]
+}
+
+/// `prefix` followed by `name`, with the characters at `indices` (into
+/// `name`) in bold.
+fn highlighted_name(prefix: &'static str, name: &str, indices: &[usize]) -> Line<'static> {
+    let mut spans = vec![Span::raw(prefix)];
+    spans.extend(name.chars().enumerate().map(|(i, c)| {
+        if indices.contains(&i) {
+            Span::raw(c.to_string()).bold()
+        } else {
+            Span::raw(c.to_string())
+        }
+    }));
+    Line::from(spans)
 }
 
 impl WidgetRef for CommandPopup {
     fn render_ref(&self, area: Rect, buf: &mut Buffer) {
-        let matches = self.filtered_commands();
-
         let mut rows: Vec<Row> = Vec::new();
-        let visible_matches: Vec<&SlashCommand> =
-            matches.into_iter().take(MAX_POPUP_ROWS).collect();
+        // Entries that fit between the borders (and the usage line), at most
+        // `MAX_POPUP_ROWS`.
+        let usage_rows = u16::from(self.usage_hint().is_some());
+        let window =
+            usize::from(area.height.saturating_sub(2 + usage_rows)).clamp(1, MAX_POPUP_ROWS);
 
-        if visible_matches.is_empty() {
+        if let Some(arg) = &self.arg_completion {
+            let top = self.visible_top(
+                window,
+                self.selected_idx.map(|idx| (idx, idx)),
+                arg.matches.len(),
+            );
+            if arg.matches.is_empty() {
+                rows.push(Row::new(vec![
+                    Cell::from(""),
+                    Cell::from("No suggestions").add_modifier(Modifier::ITALIC | Modifier::DIM),
+                ]));
+            }
+            let value_style = self.theme.popup_item;
+            for (idx, (value, indices)) in arg.matches.iter().enumerate().skip(top).take(window) {
+                let style = if Some(idx) == self.selected_idx {
+                    value_style.patch(self.theme.popup_selected)
+                } else {
+                    value_style
+                };
+                rows.push(Row::new(vec![
+                    Cell::from(highlighted_name(" ", value, indices)).style(style),
+                    Cell::from(""),
+                ]));
+            }
+        } else if self.matches.is_empty() {
             rows.push(Row::new(vec![
                 Cell::from(""),
-                Cell::from("No matching commands").add_modifier(Modifier::ITALIC),
+                Cell::from("No matching commands").add_modifier(Modifier::ITALIC | Modifier::DIM),
             ]));
         } else {
-            let default_style = Style::default();
-            let command_style = Style::default().fg(Color::LightBlue);
-            for (idx, cmd) in visible_matches.iter().enumerate() {
+            let command_rows = self.command_rows();
+            // Keep the header of the selected command's group in view along
+            // with it when scrolling up.
+            let keep = self.selected_idx.and_then(|selected| {
+                let pos = command_rows
+                    .iter()
+                    .position(|row| *row == CommandRow::Command(selected))?;
+                let header_above =
+                    pos > 0 && matches!(command_rows[pos - 1], CommandRow::Header(_)) && window > 1;
+                Some((if header_above { pos - 1 } else { pos }, pos))
+            });
+            let top = self.visible_top(window, keep, command_rows.len());
+
+            let default_style = Style::default().add_modifier(Modifier::DIM);
+            let command_style = self.theme.popup_item;
+            for row in command_rows.into_iter().skip(top).take(window) {
+                let idx = match row {
+                    CommandRow::Header(category) => {
+                        rows.push(Row::new(vec![
+                            Cell::from(category.label()).style(default_style),
+                            Cell::from(""),
+                        ]));
+                        continue;
+                    }
+                    CommandRow::Command(idx) => idx,
+                };
+                let (cmd, indices) = (&self.matches[idx], &self.match_indices[idx]);
                 let (cmd_style, desc_style) = if Some(idx) == self.selected_idx {
                     (
-                        command_style.bg(Color::DarkGray),
-                        default_style.bg(Color::DarkGray),
+                        command_style.patch(self.theme.popup_selected),
+                        default_style.patch(self.theme.popup_selected),
                     )
                 } else {
                     (command_style, default_style)
                 };
 
                 rows.push(Row::new(vec![
-                    Cell::from(format!("/{}", cmd.command())).style(cmd_style),
+                    Cell::from(highlighted_name("/", cmd.command(), indices)).style(cmd_style),
                     Cell::from(cmd.description().to_string()).style(desc_style),
                 ]));
             }
         }
 
+        if let Some(usage) = self.usage_hint() {
+            rows.push(Row::new(vec![
+                Cell::from(""),
+                Cell::from(format!("Usage: {usage}")).add_modifier(Modifier::ITALIC),
+            ]));
+        }
+
         use ratatui::layout::Constraint;
 
+        // Point at `/help` while browsing commands, but not once the user
+        // is already asking for help.
+        let help_affordance = match self.selected_command() {
+            Some(SlashCommand::Help) | None => Line::from(""),
+            Some(_) => Line::from(" ? /help <command> ").dim(),
+        };
+
+        // How many commands a filter leaves, since not all may fit.
+        let match_count = match self.matches().len() {
+            _ if self.command_filter.is_empty() || self.arg_completion.is_some() => Line::from(""),
+            0 => Line::from(""),
+            1 => Line::from(" 1 match ").dim().left_aligned(),
+            n => Line::from(format!(" {n} matches ")).dim().left_aligned(),
+        };
+
         let table = Table::new(
             rows,
             [Constraint::Length(FIRST_COLUMN_WIDTH), Constraint::Min(10)],
@@ -189,9 +584,414 @@ impl WidgetRef for CommandPopup {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(BorderType::Rounded),
+                .border_type(BorderType::Rounded)
+                .title_top(match_count)
+                .title_bottom(help_affordance)
+                .title_alignment(Alignment::Right),
         );
 
         table.render(area, buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn render_lines(popup: &CommandPopup, width: u16) -> (Buffer, Vec<String>) {
+        let area = Rect::new(
+            0,
+            0,
+            width,
+            popup.calculate_required_height(&Rect::default()),
+        );
+        let mut buf = Buffer::empty(area);
+        popup.render_ref(area, &mut buf);
+        let lines = (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| buf[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect();
+        (buf, lines)
+    }
+
+    fn ranked_names(filter: &str) -> Vec<&'static str> {
+        let commands = built_in_slash_commands();
+        rank_commands(filter, commands.values())
+            .into_iter()
+            .map(|(cmd, _)| cmd.command())
+            .collect()
+    }
+
+    #[test]
+    fn empty_filter_lists_all_commands_alphabetically() {
+        assert_eq!(
+            ranked_names(""),
+            vec![
+                "ask",
+                "code",
+                "edit",
+                "help",
+                "model",
+                "new",
+                "quit",
+                "selection",
+                "toggle-mouse-mode"
+            ]
+        );
+    }
+
+    #[test]
+    fn fuzzy_filter_matches_subsequence() {
+        assert_eq!(ranked_names("tmm"), vec!["toggle-mouse-mode"]);
+        assert_eq!(ranked_names("qt"), vec!["quit"]);
+        assert!(ranked_names("xyz").is_empty());
+    }
+
+    #[test]
+    fn prefix_matches_rank_ahead_of_fuzzy_matches() {
+        // "t" is a prefix of "toggle-mouse-mode" but only a subsequence of
+        // "edit", "quit" and "selection", so the prefix match wins despite
+        // sorting later.
+        assert_eq!(ranked_names("t")[0], "toggle-mouse-mode");
+        assert_eq!(ranked_names("t").len(), 4);
+
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/t".to_string());
+        assert_eq!(
+            popup.selected_command(),
+            Some(&SlashCommand::ToggleMouseMode)
+        );
+    }
+
+    #[test]
+    fn aliases_select_the_canonical_command() {
+        assert_eq!(ranked_names("q")[0], "quit");
+        assert_eq!(ranked_names("exit"), vec!["quit"]);
+
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/q".to_string());
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Quit));
+    }
+
+    #[test]
+    fn render_shows_dimmed_descriptions() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        let (buf, lines) = render_lines(&popup, 60);
+
+        assert_eq!(
+            lines,
+            vec![
+                "╭──────────────────────────────────────────────────────────╮",
+                "│Prompt                                                    │",
+                "│/ask                Ask a question without changing any fi│",
+                "│/edit               Ask for changes to be made.           │",
+                "│Insert                                                    │",
+                "│/code               Insert a fenced code block.           │",
+                "╰─────────────────────────────────────── ? /help <command> ╯",
+            ]
+        );
+        // Description column (after the border and the first column) is
+        // dimmed for unselected rows.
+        let desc_x = 1 + FIRST_COLUMN_WIDTH;
+        assert!(buf[(desc_x, 2)].modifier.contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn fully_typed_command_without_arguments_has_no_usage_line() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/quit".to_string());
+        assert_eq!(popup.usage_hint(), None);
+        assert_eq!(popup.calculate_required_height(&Rect::default()), 3);
+    }
+
+    #[test]
+    fn matched_characters_are_bold() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/tmm".to_string());
+        let (buf, lines) = render_lines(&popup, 40);
+        assert!(lines[1].starts_with("│/toggle-mouse-mode"));
+
+        // Skip the border and the leading '/'.
+        let bold: Vec<usize> = (0.."toggle-mouse-mode".len())
+            .filter(|i| buf[(2 + *i as u16, 1)].modifier.contains(Modifier::BOLD))
+            .collect();
+        assert_eq!(bold, vec![0, 7, 13]);
+    }
+
+    #[test]
+    fn fully_typed_model_command_lists_configured_models() {
+        let models = vec![
+            "o3".to_string(),
+            "o4-mini".to_string(),
+            "gpt-4.1".to_string(),
+        ];
+        let mut popup = CommandPopup::new(models);
+
+        popup.on_composer_text_change("/model".to_string());
+        assert_eq!(popup.selected_argument(), None);
+
+        popup.on_composer_text_change("/model ".to_string());
+        let (_, lines) = render_lines(&popup, 30);
+        assert_eq!(
+            lines[1..4],
+            [
+                "│ o3                         │",
+                "│ o4-mini                    │",
+                "│ gpt-4.1                    │",
+            ]
+        );
+        assert_eq!(popup.selected_argument(), Some("o3"));
+        popup.move_down();
+        assert_eq!(popup.selected_argument(), Some("o4-mini"));
+
+        popup.on_composer_text_change("/model mini".to_string());
+        assert_eq!(popup.selected_argument(), Some("o4-mini"));
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Model));
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        let count = popup.matches().len();
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Ask));
+
+        for _ in 0..count {
+            popup.move_down();
+        }
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Ask));
+
+        popup.move_up();
+        assert_eq!(
+            popup.selected_command(),
+            Some(&SlashCommand::ToggleMouseMode)
+        );
+    }
+
+    #[test]
+    fn longest_common_prefix_of_names() {
+        assert_eq!(longest_common_prefix(&["model", "mode", "modify"]), "mod");
+        assert_eq!(longest_common_prefix(&["new", "quit"]), "");
+        assert_eq!(longest_common_prefix(&["ask"]), "ask");
+        assert_eq!(longest_common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn only_registered_commands_are_offered() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.set_commands(&[SlashCommand::Quit, SlashCommand::Model]);
+        popup.on_composer_text_change("/".to_string());
+        let names: Vec<&str> = popup.matches().iter().map(|c| c.command()).collect();
+        assert_eq!(names, vec!["model", "quit"]);
+    }
+
+    #[derive(Clone, Copy)]
+    struct Synthetic(&'static str);
+
+    impl Rankable for Synthetic {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn aliases(&self) -> &'static [&'static str] {
+            &[]
+        }
+    }
+
+    #[test]
+    fn incremental_filtering_of_many_commands() {
+        let words = ["build", "check", "deploy", "format", "lint"];
+        let commands: Vec<Synthetic> = (0..5000)
+            .map(|i| {
+                let name = format!("{}-{i}", words[i % words.len()]);
+                Synthetic(Box::leak(name.into_boxed_str()))
+            })
+            .collect();
+        let names = |matches: &[(Synthetic, Vec<usize>)]| -> Vec<&str> {
+            matches.iter().map(|(c, _)| c.0).collect()
+        };
+
+        let start = std::time::Instant::now();
+        let mut previous: Option<String> = Some(String::new());
+        let mut matches = rank_commands("", commands.iter());
+        for filter in ["d", "de", "dep", "dep1", "dep12", "dep1", "lint-4"] {
+            let found: Vec<Synthetic> = matches.iter().map(|(cmd, _)| *cmd).collect();
+            matches = refine_matches(previous.as_deref(), &found, filter, commands.iter());
+            previous = Some(filter.to_string());
+            // Narrowing must agree with a full rescan.
+            assert_eq!(
+                names(&matches),
+                names(&rank_commands(filter, commands.iter())),
+                "filter {filter:?}"
+            );
+        }
+        assert_eq!(names(&matches)[0], "lint-4");
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn bare_slash_lists_every_command() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        let names: Vec<&str> = popup.matches().iter().map(|c| c.command()).collect();
+        let mut all: Vec<SlashCommand> = built_in_slash_commands().into_values().collect();
+        all.sort_by_key(|cmd| (cmd.category(), cmd.command()));
+        let all: Vec<&str> = all.into_iter().map(|c| c.command()).collect();
+        assert_eq!(names, all);
+        assert!(!popup.is_ambiguous());
+    }
+
+    #[test]
+    fn unmatched_query_renders_a_dimmed_placeholder_row() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/xyz".to_string());
+        assert_eq!(popup.calculate_required_height(&Rect::default()), 3);
+
+        let (buf, lines) = render_lines(&popup, 60);
+        assert_eq!(
+            lines[1],
+            "│                    No matching commands                  │"
+        );
+        let x = 1 + FIRST_COLUMN_WIDTH;
+        assert!(
+            buf[(x, 1)]
+                .modifier
+                .contains(Modifier::DIM | Modifier::ITALIC)
+        );
+    }
+
+    #[test]
+    fn selection_scrolls_into_view_in_a_short_area() {
+        let models: Vec<String> = (0..30).map(|i| format!("model-{i:02}")).collect();
+        let mut popup = CommandPopup::new(models);
+        popup.on_composer_text_change("/model ".to_string());
+        assert_eq!(popup.entry_count(), 30);
+
+        // Five rows: two borders, a usage line and two entries.
+        let area = Rect::new(0, 0, 40, 5);
+        let rendered = |popup: &CommandPopup| -> Vec<String> {
+            let mut buf = Buffer::empty(area);
+            popup.render_ref(area, &mut buf);
+            (0..area.height)
+                .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+                .collect()
+        };
+        assert!(rendered(&popup)[1].contains("model-00"));
+
+        for _ in 0..10 {
+            popup.move_down();
+            rendered(&popup);
+        }
+        assert_eq!(popup.selected_argument(), Some("model-10"));
+        let lines = rendered(&popup);
+        assert!(lines[2].contains("model-10"), "{lines:?}");
+        assert!(lines[1].contains("model-09"), "{lines:?}");
+
+        // Moving back up scrolls the other way.
+        for _ in 0..5 {
+            popup.move_up();
+        }
+        assert!(rendered(&popup)[1].contains("model-05"));
+    }
+
+    #[test]
+    fn bare_slash_groups_commands_under_category_headers() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        let (buf, lines) = render_lines(&popup, 40);
+        assert_eq!(lines[1], "│Prompt                                │");
+        assert!(buf[(1, 1)].modifier.contains(Modifier::DIM));
+
+        // Moving down from the last command of a group skips the next
+        // header, and scrolling keeps that header in view.
+        popup.move_down();
+        popup.move_down();
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Code));
+        popup.move_down();
+        popup.move_down();
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Model));
+        let (_, lines) = render_lines(&popup, 40);
+        assert!(lines[4].starts_with("│Session"), "{lines:?}");
+        assert!(lines[5].starts_with("│/model"), "{lines:?}");
+
+        // Typing a filter ranks the matches without headers.
+        popup.on_composer_text_change("/mo".to_string());
+        let (_, lines) = render_lines(&popup, 40);
+        assert!(lines[1].starts_with("│/model"), "{lines:?}");
+    }
+
+    #[test]
+    fn selection_follows_the_command_while_narrowing() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        popup.move_down();
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Edit));
+
+        // "d" ranks other commands ahead of /edit, which stays selected.
+        popup.on_composer_text_change("/d".to_string());
+        assert_ne!(ranked_names("d")[0], "edit");
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Edit));
+        popup.on_composer_text_change("/dt".to_string());
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Edit));
+
+        // Once it no longer matches, another command is selected.
+        popup.on_composer_text_change("/dtx".to_string());
+        assert_eq!(popup.selected_command(), None);
+        popup.on_composer_text_change("/q".to_string());
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Quit));
+    }
+
+    #[test]
+    fn default_selection_moves_to_the_best_match() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Ask));
+
+        // /ask still matches "s", but the user never picked it.
+        popup.on_composer_text_change("/s".to_string());
+        assert_eq!(ranked_names("s")[0], "selection");
+        assert_eq!(popup.selected_command(), Some(&SlashCommand::Selection));
+    }
+
+    #[test]
+    fn match_count_is_shown_while_filtering() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        let (_, lines) = render_lines(&popup, 40);
+        assert!(!lines[0].contains("match"), "{lines:?}");
+
+        popup.on_composer_text_change("/mo".to_string());
+        let (_, lines) = render_lines(&popup, 40);
+        let count = popup.matches().len();
+        assert!(count > 1);
+        assert!(
+            lines[0].starts_with(&format!("╭ {count} matches ")),
+            "{lines:?}"
+        );
+
+        popup.on_composer_text_change("/quit".to_string());
+        let (_, lines) = render_lines(&popup, 40);
+        assert!(lines[0].starts_with("╭ 1 match "), "{lines:?}");
+    }
+
+    #[test]
+    fn matches_reflect_the_query() {
+        let mut popup = CommandPopup::new(Vec::new());
+        popup.on_composer_text_change("/".to_string());
+        assert_eq!(popup.matches().len(), built_in_slash_commands().len());
+
+        popup.on_composer_text_change("/mo".to_string());
+        assert_eq!(popup.matches()[0], SlashCommand::Model);
+        assert!(popup.matches().contains(&SlashCommand::ToggleMouseMode));
+
+        popup.on_composer_text_change("/quit".to_string());
+        assert_eq!(popup.matches(), [SlashCommand::Quit]);
+        popup.on_composer_text_change("/xyz".to_string());
+        assert!(popup.matches().is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Constraint;
+use ratatui::layout::Rect;
+use ratatui::style::Modifier;
+use ratatui::style::Stylize;
+use ratatui::widgets::Block;
+use ratatui::widgets::BorderType;
+use ratatui::widgets::Borders;
+use ratatui::widgets::Cell;
+use ratatui::widgets::Row;
+use ratatui::widgets::Table;
+use ratatui::widgets::Widget;
+use ratatui::widgets::WidgetRef;
+
+use super::chat_composer_theme::ComposerTheme;
+use crate::fuzzy_match::fuzzy_match;
+
+const MAX_POPUP_ROWS: usize = 5;
+/// Upper bound on the number of completions kept for a query.
+const MAX_RESULTS: usize = 50;
+/// Upper bound on the number of files indexed under the root so that very
+/// large workspaces do not stall the UI.
+const MAX_INDEXED_FILES: usize = 10_000;
+
+/// Popup that completes `@path` mentions against files under a root
+/// directory. The directory is only scanned the first time a query is run.
+pub(crate) struct FileSearchPopup {
+    root: PathBuf,
+    /// Relative paths (using `/` separators) of every indexed file. `None`
+    /// until the first query.
+    index: Option<Vec<String>>,
+    query: String,
+    matches: Vec<String>,
+    selected_idx: Option<usize>,
+    theme: ComposerTheme,
+}
+
+impl FileSearchPopup {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            index: None,
+            query: String::new(),
+            matches: Vec::new(),
+            selected_idx: None,
+            theme: ComposerTheme::default(),
+        }
+    }
+
+    /// Use `theme` for the entries instead of the default colors.
+    pub(crate) fn with_theme(mut self, theme: ComposerTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Start from a listing of the root made earlier, if any, instead of
+    /// scanning it again.
+    pub(crate) fn with_index(mut self, index: Option<Vec<String>>) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Hand over the listing of the root so a later popup can reuse it.
+    pub(crate) fn take_index(&mut self) -> Option<Vec<String>> {
+        self.index.take()
+    }
+
+    /// Drop the listing of the root, which has changed, and rescan it for
+    /// the current query.
+    pub(crate) fn invalidate(&mut self) {
+        self.index = None;
+        let query = std::mem::take(&mut self.query);
+        self.on_query_change(&query);
+    }
+
+    /// Update the completions for `query`, the text after the `@`.
+    pub(crate) fn on_query_change(&mut self, query: &str) {
+        if self.index.is_some() && self.query == query {
+            return;
+        }
+        self.query = query.to_string();
+
+        let root = &self.root;
+        let index = self.index.get_or_insert_with(|| scan_files(root));
+
+        let mut scored: Vec<(i32, &String)> = index
+            .iter()
+            .filter_map(|path| fuzzy_match(query, path).map(|m| (m.score, path)))
+            .collect();
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.len().cmp(&b.len()))
+                .then_with(|| a.cmp(b))
+        });
+        self.matches = scored
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, path)| path.clone())
+            .collect();
+
+        self.selected_idx = if self.matches.is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+    }
+
+    /// Preferred height: at most `MAX_POPUP_ROWS` entries plus the border.
+    pub(crate) fn calculate_required_height(&self, _area: &Rect) -> u16 {
+        self.matches.len().clamp(1, MAX_POPUP_ROWS) as u16 + 2
+    }
+
+    pub(crate) fn move_up(&mut self) {
+        if let Some(idx) = self.selected_idx {
+            self.selected_idx = Some(idx.saturating_sub(1));
+        }
+    }
+
+    pub(crate) fn move_down(&mut self) {
+        if let Some(idx) = self.selected_idx {
+            if idx + 1 < self.matches.len() {
+                self.selected_idx = Some(idx + 1);
+            }
+        }
+    }
+
+    /// Currently highlighted completion, if any.
+    pub(crate) fn selected_path(&self) -> Option<&str> {
+        self.selected_idx
+            .and_then(|idx| self.matches.get(idx))
+            .map(String::as_str)
+    }
+}
+
+/// Walk `root` and collect relative file paths, skipping hidden entries.
+fn scan_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                let relative = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push(relative);
+                if files.len() >= MAX_INDEXED_FILES {
+                    return files;
+                }
+            }
+        }
+    }
+    files
+}
+
+impl WidgetRef for FileSearchPopup {
+    fn render_ref(&self, area: Rect, buf: &mut Buffer) {
+        let mut rows: Vec<Row> = Vec::new();
+        if self.matches.is_empty() {
+            rows.push(Row::new(vec![
+                Cell::from("No matching files").add_modifier(Modifier::ITALIC),
+            ]));
+        } else {
+            let path_style = self.theme.popup_item;
+            for (idx, path) in self.matches.iter().take(MAX_POPUP_ROWS).enumerate() {
+                let style = if Some(idx) == self.selected_idx {
+                    path_style.patch(self.theme.popup_selected)
+                } else {
+                    path_style
+                };
+                rows.push(Row::new(vec![Cell::from(format!("@{path}")).style(style)]));
+            }
+        }
+
+        let table = Table::new(rows, [Constraint::Min(10)]).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        );
+        table.render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::expect_used)]
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn completes_nested_paths_and_skips_hidden_entries() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/bottom")).expect("mkdir");
+        std::fs::create_dir_all(root.join(".git")).expect("mkdir");
+        for file in [
+            "src/main.rs",
+            "src/lib.rs",
+            "src/bottom/mod.rs",
+            ".git/main",
+        ] {
+            std::fs::write(root.join(file), "").expect("write file");
+        }
+
+        let mut popup = FileSearchPopup::new(root.to_path_buf());
+        popup.on_query_change("src/ma");
+        assert_eq!(popup.matches, vec!["src/main.rs".to_string()]);
+        assert_eq!(popup.selected_path(), Some("src/main.rs"));
+
+        popup.on_query_change("mod");
+        assert_eq!(popup.selected_path(), Some("src/bottom/mod.rs"));
+
+        popup.on_query_change("");
+        assert_eq!(popup.matches.len(), 3);
+    }
+
+    #[test]
+    fn new_files_appear_after_invalidation() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let root = dir.path();
+        std::fs::write(root.join("old.rs"), "").expect("write file");
+
+        let mut popup = FileSearchPopup::new(root.to_path_buf());
+        popup.on_query_change("rs");
+        assert_eq!(popup.matches, vec!["old.rs".to_string()]);
+
+        // The listing is cached, so the new file only shows up once the
+        // popup is told the root changed.
+        std::fs::write(root.join("new.rs"), "").expect("write file");
+        popup.on_query_change("r");
+        popup.on_query_change("rs");
+        assert_eq!(popup.matches, vec!["old.rs".to_string()]);
+
+        popup.invalidate();
+        assert_eq!(
+            popup.matches,
+            vec!["new.rs".to_string(), "old.rs".to_string()]
+        );
+
+        // A listing handed to a new popup is reused as is.
+        let index = popup.take_index();
+        std::fs::write(root.join("newer.rs"), "").expect("write file");
+        let mut popup = FileSearchPopup::new(root.to_path_buf()).with_index(index);
+        popup.on_query_change("newer");
+        assert!(popup.matches.is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+//! Background watch of the `@path` completion root, so a cached listing is
+//! refreshed once files are added, removed or renamed.
+//!
+//! The watch polls directory modification times (which change whenever an
+//! entry is created, removed or renamed) rather than relying on platform
+//! notification APIs.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How often the directory tree is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the tree must stay unchanged before a change is reported, so a
+/// checkout or build touching many files is reported once.
+const DEBOUNCE: Duration = Duration::from_secs(1);
+/// Upper bound on the directories checked per poll so that very large
+/// workspaces do not keep a core busy.
+const MAX_WATCHED_DIRS: usize = 2_000;
+
+/// Watches the directories under a root from a background thread, which
+/// stops when the watcher is dropped.
+pub(crate) struct FileWatcher {
+    changed: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FileWatcher {
+    pub(crate) fn spawn(root: PathBuf) -> Self {
+        Self::spawn_with(root, POLL_INTERVAL, DEBOUNCE)
+    }
+
+    fn spawn_with(root: PathBuf, poll_interval: Duration, debounce: Duration) -> Self {
+        let changed = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher = Self {
+            changed: Arc::clone(&changed),
+            stop: Arc::clone(&stop),
+        };
+        std::thread::spawn(move || {
+            let mut last = fingerprint(&root);
+            // When the tree last changed, while that change is unreported.
+            let mut pending: Option<Instant> = None;
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(poll_interval);
+                let current = fingerprint(&root);
+                if current != last {
+                    last = current;
+                    pending = Some(Instant::now());
+                } else if pending.is_some_and(|since| since.elapsed() >= debounce) {
+                    changed.store(true, Ordering::Relaxed);
+                    pending = None;
+                }
+            }
+        });
+        watcher
+    }
+
+    /// Whether the tree changed since the last call.
+    pub(crate) fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Hash of the path and modification time of every non-hidden directory
+/// under `root`.
+fn fingerprint(root: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut pending = vec![root.to_path_buf()];
+    let mut visited = 0;
+    while let Some(dir) = pending.pop() {
+        visited += 1;
+        if visited > MAX_WATCHED_DIRS {
+            break;
+        }
+        dir.hash(&mut hasher);
+        if let Ok(modified) = std::fs::metadata(&dir).and_then(|m| m.modified()) {
+            modified.hash(&mut hasher);
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        dirs.sort();
+        pending.extend(dirs);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn creating_a_file_is_reported_once() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        std::fs::create_dir_all(dir.path().join("src")).expect("mkdir");
+        let watcher = FileWatcher::spawn_with(
+            dir.path().to_path_buf(),
+            Duration::from_millis(10),
+            Duration::from_millis(30),
+        );
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!watcher.take_changed());
+
+        std::fs::write(dir.path().join("src/new.rs"), "").expect("write file");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watcher.take_changed() {
+            assert!(Instant::now() < deadline, "change was never reported");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!watcher.take_changed());
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+/// File extensions treated as attachable images.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+/// If `pasted` is the path of an existing image file, return it.
+///
+/// Dragging a file onto most terminals "pastes" its path, so this recognises
+/// the usual decorations: surrounding quotes, a `file://` URL prefix, and
+/// backslash-escaped spaces.
+pub(crate) fn pasted_image_path(pasted: &str) -> Option<PathBuf> {
+    parse_image_path(pasted).filter(|path| path.is_file())
+}
+
+/// If `pasted` is the absolute path of an existing file (of any type),
+/// return it. Recognises the same decorations as [`pasted_image_path`].
+/// Relative paths are ignored so that pasting a lone word that happens to
+/// name a file in the working directory still inserts the word.
+pub(crate) fn pasted_file_path(pasted: &str) -> Option<PathBuf> {
+    parse_pasted_path(pasted).filter(|path| path.is_absolute() && path.is_file())
+}
+
+/// Syntactic half of [`pasted_image_path`]; does not touch the filesystem.
+fn parse_image_path(pasted: &str) -> Option<PathBuf> {
+    let path = parse_pasted_path(pasted)?;
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    IMAGE_EXTENSIONS
+        .contains(&extension.as_str())
+        .then_some(path)
+}
+
+/// Strip the decorations terminals add to a dragged-in path. Returns `None`
+/// for text that cannot be a single path.
+fn parse_pasted_path(pasted: &str) -> Option<PathBuf> {
+    let trimmed = pasted.trim();
+    if trimmed.is_empty() || trimmed.contains('\n') {
+        return None;
+    }
+
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|q| {
+            trimmed
+                .strip_prefix(*q)
+                .and_then(|rest| rest.strip_suffix(*q))
+        })
+        .unwrap_or(trimmed);
+    let path = match unquoted.strip_prefix("file://") {
+        Some(rest) => rest.replace("%20", " "),
+        None => unquoted.replace("\\ ", " "),
+    };
+
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::expect_used)]
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn recognises_decorated_image_paths() {
+        let parsed = |s: &str| parse_image_path(s).map(|p| p.display().to_string());
+        assert_eq!(parsed("/tmp/shot.png"), Some("/tmp/shot.png".into()));
+        assert_eq!(parsed("  '/tmp/a b.JPG'\n"), Some("/tmp/a b.JPG".into()));
+        assert_eq!(parsed("/tmp/a\\ b.jpeg"), Some("/tmp/a b.jpeg".into()));
+        assert_eq!(parsed("file:///tmp/a%20b.gif"), Some("/tmp/a b.gif".into()));
+
+        assert_eq!(parsed("/tmp/notes.txt"), None);
+        assert_eq!(parsed("look at this.png please"), None);
+        assert_eq!(parsed("/tmp/a.png\n/tmp/b.png"), None);
+        assert_eq!(parsed("hello"), None);
+    }
+
+    #[test]
+    fn only_existing_files_are_attached() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let image = dir.path().join("shot.png");
+        assert_eq!(pasted_image_path(&image.display().to_string()), None);
+
+        std::fs::write(&image, b"png").expect("write image");
+        assert_eq!(pasted_image_path(&image.display().to_string()), Some(image));
+    }
+
+    #[test]
+    fn any_existing_file_is_a_file_path() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, b"notes").expect("write file");
+        assert_eq!(
+            pasted_file_path(&format!("'{}' ", notes.display())),
+            Some(notes)
+        );
+
+        assert_eq!(pasted_file_path("just some words"), None);
+        assert_eq!(
+            pasted_file_path(&dir.path().join("missing.txt").display().to_string()),
+            None
+        );
+        // Directories are not attached.
+        assert_eq!(pasted_file_path(&dir.path().display().to_string()), None);
+        assert_eq!(pasted_file_path("Cargo.toml"), None);
+    }
+}
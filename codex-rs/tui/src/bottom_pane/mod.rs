@@ -4,26 +4,55 @@ use bottom_pane_view::BottomPaneView;
 use bottom_pane_view::ConditionalUpdate;
 use crossterm::event::KeyEvent;
 use ratatui::buffer::Buffer;
+use ratatui::layout::Constraint;
+use ratatui::layout::Layout;
 use ratatui::layout::Rect;
 use ratatui::widgets::WidgetRef;
 
 use crate::app_event::AppEvent;
 use crate::app_event_sender::AppEventSender;
+use crate::slash_command::SlashCommand;
 use crate::user_approval_widget::ApprovalRequest;
 
 mod approval_modal_view;
 mod bottom_pane_view;
 mod chat_composer;
+mod chat_composer_column;
+mod chat_composer_draft;
+mod chat_composer_highlight;
 mod chat_composer_history;
+mod chat_composer_jump;
+mod chat_composer_line_cache;
+mod chat_composer_template;
+mod chat_composer_text_changed;
+mod chat_composer_theme;
+mod chat_composer_undo;
+mod chat_composer_wrap;
+mod clipboard;
 mod command_popup;
+mod file_search_popup;
+mod file_watcher;
+mod image_paste;
+mod parsed_prompt;
+mod paste_sanitize;
 mod status_indicator_view;
+mod vim_mode;
 
 pub(crate) use chat_composer::ChatComposer;
+pub(crate) use chat_composer::ChatComposerConfig;
 pub(crate) use chat_composer::InputResult;
+pub(crate) use chat_composer::SubmitFilter;
+pub(crate) use chat_composer::TokenEstimator;
+pub(crate) use chat_composer_template::TemplateResolver;
+pub(crate) use chat_composer_theme::ComposerTheme;
 
 use approval_modal_view::ApprovalModalView;
 use status_indicator_view::StatusIndicatorView;
 
+/// Rows taken by the status indicator shown above the composer while a task
+/// runs.
+const STATUS_INDICATOR_HEIGHT: u16 = 3;
+
 /// Pane displayed in the lower half of the chat UI.
 pub(crate) struct BottomPane<'a> {
     /// Composer is retained even when a BottomPaneView is displayed so the
@@ -33,24 +62,39 @@ pub(crate) struct BottomPane<'a> {
     /// If present, this is displayed instead of the `composer`.
     active_view: Option<Box<dyn BottomPaneView<'a> + 'a>>,
 
+    /// Shown above the composer while a task is running. The composer stays
+    /// focused so messages can be queued and Esc can interrupt the agent.
+    status_view: Option<StatusIndicatorView>,
+
     app_event_tx: AppEventSender,
     has_input_focus: bool,
-    is_task_running: bool,
+    /// Disable the composer, rather than having it queue messages, while a
+    /// task is running.
+    disable_input_while_busy: bool,
 }
 
 pub(crate) struct BottomPaneParams {
     pub(crate) app_event_tx: AppEventSender,
     pub(crate) has_input_focus: bool,
+    pub(crate) disable_input_while_busy: bool,
+    pub(crate) composer_config: ChatComposerConfig,
+    pub(crate) composer_theme: ComposerTheme,
 }
 
 impl BottomPane<'_> {
     pub fn new(params: BottomPaneParams) -> Self {
         Self {
-            composer: ChatComposer::new(params.has_input_focus, params.app_event_tx.clone()),
+            composer: ChatComposer::new(
+                params.has_input_focus,
+                params.app_event_tx.clone(),
+                params.composer_config,
+                params.composer_theme,
+            ),
             active_view: None,
+            status_view: None,
             app_event_tx: params.app_event_tx,
             has_input_focus: params.has_input_focus,
-            is_task_running: false,
+            disable_input_while_busy: params.disable_input_while_busy,
         }
     }
 
@@ -60,28 +104,49 @@ impl BottomPane<'_> {
             view.handle_key_event(self, key_event);
             if !view.is_complete() {
                 self.active_view = Some(view);
-            } else if self.is_task_running {
-                let height = self.composer.calculate_required_height(&Rect::default());
-                self.active_view = Some(Box::new(StatusIndicatorView::new(
-                    self.app_event_tx.clone(),
-                    height,
-                )));
             }
             self.request_redraw();
             InputResult::None
         } else {
             let (input_result, needs_redraw) = self.composer.handle_key_event(key_event);
-            if needs_redraw {
+            // The layout measures the composer on every frame, so a change
+            // in height only needs a redraw to take effect.
+            if needs_redraw || self.composer.height_changed() {
                 self.request_redraw();
             }
             input_result
         }
     }
 
-    /// Update the status indicator text (only when the `StatusIndicatorView` is
-    /// active).
+    /// Insert pasted text into the composer. Pastes are ignored while a
+    /// `BottomPaneView` is active since views do not accept free-form text.
+    pub fn handle_paste(&mut self, pasted: String) {
+        if self.active_view.is_none() && self.composer.handle_paste(pasted) {
+            self.request_redraw();
+        }
+    }
+
+    /// Copy from the composer to the clipboard; see
+    /// [`ChatComposer::copy_to_clipboard`]. Returns false when nothing was
+    /// copied.
+    pub(crate) fn copy_from_composer(&mut self) -> bool {
+        self.active_view.is_none() && self.composer.copy_to_clipboard()
+    }
+
+    /// Whether the composer holds a message that has not been sent.
+    pub(crate) fn has_unsent_content(&self) -> bool {
+        self.composer.has_unsent_content()
+    }
+
+    /// Whether key events reach the composer rather than a view shown in its
+    /// place.
+    pub(crate) fn is_composer_visible(&self) -> bool {
+        self.active_view.is_none()
+    }
+
+    /// Update the status indicator text (only while a task is running).
     pub(crate) fn update_status_text(&mut self, text: String) {
-        if let Some(view) = &mut self.active_view {
+        if let Some(view) = &mut self.status_view {
             match view.update_status_text(text) {
                 ConditionalUpdate::NeedsRedraw => {
                     self.request_redraw();
@@ -93,6 +158,50 @@ impl BottomPane<'_> {
         }
     }
 
+    /// Replace the hint shown in the composer while it is empty.
+    pub(crate) fn set_composer_placeholder(&mut self, text: impl Into<String>) {
+        self.composer.set_placeholder(text);
+        self.request_redraw();
+    }
+
+    /// Check or rewrite every message before the composer submits it; see
+    /// [`ChatComposer::set_submit_filter`].
+    pub(crate) fn set_submit_filter(&mut self, filter: SubmitFilter) {
+        self.composer.set_submit_filter(filter);
+    }
+
+    /// Count the draft's tokens with `estimator`; see
+    /// [`ChatComposer::set_token_estimator`].
+    pub(crate) fn set_token_estimator(&mut self, estimator: TokenEstimator) {
+        self.composer.set_token_estimator(estimator);
+    }
+
+    /// Expand `:name:` tokens in submitted messages with `resolver`.
+    pub(crate) fn set_template_resolver(&mut self, resolver: Box<dyn TemplateResolver>) {
+        self.composer.set_template_resolver(resolver);
+    }
+
+    /// Offer only `commands` in the composer's slash-command popup.
+    pub(crate) fn set_commands(&mut self, commands: Vec<SlashCommand>) {
+        self.composer.set_commands(commands);
+    }
+
+    /// Bound the rows of text the composer shows; `None` keeps the default
+    /// for that bound.
+    pub(crate) fn set_composer_row_limits(&mut self, min: Option<usize>, max: Option<usize>) {
+        self.composer.set_row_limits(
+            min.unwrap_or(chat_composer::MIN_TEXTAREA_ROWS),
+            max.unwrap_or(chat_composer::MAX_TEXTAREA_ROWS),
+        );
+        self.request_redraw();
+    }
+
+    /// Show how many messages are waiting for the current task to finish.
+    pub(crate) fn set_queued_messages(&mut self, count: usize) {
+        self.composer.set_queued_messages(count);
+        self.request_redraw();
+    }
+
     /// Update the UI to reflect whether this `BottomPane` has input focus.
     pub(crate) fn set_input_focus(&mut self, has_focus: bool) {
         self.has_input_focus = has_focus;
@@ -100,32 +209,31 @@ impl BottomPane<'_> {
     }
 
     pub fn set_task_running(&mut self, running: bool) {
-        self.is_task_running = running;
-
-        match (running, self.active_view.is_some()) {
-            (true, false) => {
-                // Show status indicator overlay.
-                let height = self.composer.calculate_required_height(&Rect::default());
-                self.active_view = Some(Box::new(StatusIndicatorView::new(
-                    self.app_event_tx.clone(),
-                    height,
-                )));
-                self.request_redraw();
-            }
-            (false, true) => {
-                if let Some(mut view) = self.active_view.take() {
-                    if view.should_hide_when_task_is_done() {
-                        // Leave self.active_view as None.
-                        self.request_redraw();
-                    } else {
-                        // Preserve the view.
-                        self.active_view = Some(view);
-                    }
+        self.composer.set_busy(running);
+        if self.disable_input_while_busy {
+            self.composer.set_enabled(!running);
+        }
+
+        if running && self.status_view.is_none() {
+            self.status_view = Some(StatusIndicatorView::new(
+                self.app_event_tx.clone(),
+                STATUS_INDICATOR_HEIGHT,
+            ));
+            self.request_redraw();
+        } else if !running && self.status_view.take().is_some() {
+            self.request_redraw();
+        }
+
+        if !running {
+            if let Some(mut view) = self.active_view.take() {
+                if view.should_hide_when_task_is_done() {
+                    // Leave self.active_view as None.
+                    self.request_redraw();
+                } else {
+                    // Preserve the view.
+                    self.active_view = Some(view);
                 }
             }
-            _ => {
-                // No change.
-            }
         }
     }
 
@@ -154,7 +262,11 @@ impl BottomPane<'_> {
         if let Some(view) = &self.active_view {
             view.calculate_required_height(area)
         } else {
-            self.composer.calculate_required_height(area)
+            let status_height = self
+                .status_view
+                .as_ref()
+                .map_or(0, |view| view.calculate_required_height(area));
+            status_height + self.composer.calculate_required_height(area)
         }
     }
 
@@ -162,9 +274,18 @@ impl BottomPane<'_> {
         self.app_event_tx.send(AppEvent::Redraw)
     }
 
-    /// Returns true when the slash-command popup inside the composer is visible.
-    pub(crate) fn is_command_popup_visible(&self) -> bool {
-        self.active_view.is_none() && self.composer.is_command_popup_visible()
+    /// Replace the composer text with what was saved in the external editor.
+    pub(crate) fn on_external_editor_response(&mut self, text: String) {
+        if self.composer.on_external_editor_response(text) {
+            self.request_redraw();
+        }
+    }
+
+    /// Insert the text fetched for `/selection` into the composer.
+    pub(crate) fn on_selection_response(&mut self, selection: Option<String>) {
+        if self.composer.on_selection_response(selection) {
+            self.request_redraw();
+        }
     }
 
     // --- History helpers ---
@@ -194,8 +315,125 @@ impl WidgetRef for &BottomPane<'_> {
         // Show BottomPaneView if present.
         if let Some(ov) = &self.active_view {
             ov.render(area, buf);
+        } else if let Some(status) = &self.status_view {
+            let [status_rect, composer_rect] = Layout::vertical([
+                Constraint::Length(status.calculate_required_height(&area)),
+                Constraint::Min(0),
+            ])
+            .areas(area);
+            status.render(status_rect, buf);
+            (&self.composer).render_ref(composer_rect, buf);
         } else {
             (&self.composer).render_ref(area, buf);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyCode;
+    use crossterm::event::KeyModifiers;
+    use std::sync::mpsc::Receiver;
+    use std::sync::mpsc::channel;
+
+    fn make_pane() -> (BottomPane<'static>, Receiver<AppEvent>) {
+        make_pane_with(false)
+    }
+
+    fn make_pane_with(disable_input_while_busy: bool) -> (BottomPane<'static>, Receiver<AppEvent>) {
+        let (tx, rx) = channel::<AppEvent>();
+        let pane = BottomPane::new(BottomPaneParams {
+            app_event_tx: AppEventSender::new(tx),
+            has_input_focus: true,
+            disable_input_while_busy,
+            composer_config: ChatComposerConfig::default(),
+            composer_theme: ComposerTheme::default(),
+        });
+        (pane, rx)
+    }
+
+    fn press(pane: &mut BottomPane, code: KeyCode) -> InputResult {
+        pane.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn composer_takes_keys_while_a_task_runs() {
+        let (mut pane, rx) = make_pane();
+        pane.set_task_running(true);
+        assert!(pane.is_composer_visible());
+
+        for ch in "next".chars() {
+            press(&mut pane, KeyCode::Char(ch));
+        }
+        let result = press(&mut pane, KeyCode::Enter);
+        assert!(matches!(result, InputResult::Queued(text) if text == "next"));
+        assert!(
+            rx.try_iter()
+                .any(|e| matches!(e, AppEvent::QueueMessage { text, .. } if text == "next"))
+        );
+
+        // The status indicator sits above the composer rather than over it.
+        let area = Rect::new(0, 0, 40, 10);
+        let composer_height = pane.composer.calculate_required_height(&area);
+        assert_eq!(
+            pane.calculate_required_height(&area),
+            STATUS_INDICATOR_HEIGHT + composer_height
+        );
+
+        pane.set_task_running(false);
+        assert_eq!(pane.calculate_required_height(&area), composer_height);
+    }
+
+    #[test]
+    fn esc_interrupts_the_running_task() {
+        let (mut pane, rx) = make_pane();
+        pane.set_task_running(true);
+        press(&mut pane, KeyCode::Esc);
+        assert!(rx.try_iter().any(|e| matches!(e, AppEvent::InterruptAgent)));
+
+        pane.set_task_running(false);
+        press(&mut pane, KeyCode::Esc);
+        assert!(!rx.try_iter().any(|e| matches!(e, AppEvent::InterruptAgent)));
+    }
+
+    #[test]
+    fn queued_count_is_visible_while_a_task_runs() {
+        let (mut pane, _rx) = make_pane();
+        pane.set_task_running(true);
+        pane.set_queued_messages(2);
+
+        let area = Rect::new(
+            0,
+            0,
+            40,
+            pane.calculate_required_height(&Rect::new(0, 0, 40, 20)),
+        );
+        let mut buf = Buffer::empty(area);
+        (&pane).render_ref(area, &mut buf);
+        let rendered: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(rendered.contains("Working"));
+        assert!(rendered.contains("2 queued"));
+    }
+
+    #[test]
+    fn submit_is_ignored_while_busy_when_configured() {
+        let (mut pane, rx) = make_pane_with(true);
+        pane.set_task_running(true);
+        for ch in "wait".chars() {
+            press(&mut pane, KeyCode::Char(ch));
+        }
+        assert!(matches!(
+            press(&mut pane, KeyCode::Enter),
+            InputResult::None
+        ));
+        assert!(
+            !rx.try_iter()
+                .any(|e| matches!(e, AppEvent::QueueMessage { .. }))
+        );
+
+        pane.set_task_running(false);
+        let result = press(&mut pane, KeyCode::Enter);
+        assert!(matches!(result, InputResult::Submitted { text, .. } if text == "wait"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Structured form of a submitted message, for integrations that want more
+//! than the flat text (see `ChatComposerConfig::parsed_submissions`).
+
+use std::path::PathBuf;
+
+use crate::slash_command::PromptMode;
+
+/// A submitted message split into its parts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedPrompt {
+    /// The prompt as `InputResult::Submitted` would report it.
+    pub text: String,
+    /// Mode chosen with a leading prefix such as `/ask`, which is not part
+    /// of `text` or `segments`.
+    pub mode: PromptMode,
+    /// `text` split into prose, `@path` mentions and fenced code blocks.
+    pub segments: Vec<PromptSegment>,
+    pub image_paths: Vec<PathBuf>,
+    pub file_paths: Vec<PathBuf>,
+    /// The text was also left in the composer to be reused.
+    pub keep_draft: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSegment {
+    /// Prose between the other segments, verbatim.
+    Text(String),
+    /// An `@path` mention, without the `@`.
+    FileMention(String),
+    /// The contents of a ``` fence. The line breaks around the fence are
+    /// not kept. A fence left open runs to the end of the text.
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+    },
+}
+
+/// Split `text` into segments. Mentions follow the composer's rule: an `@`
+/// at the start of a word followed by at least one more character, outside
+/// of code blocks.
+pub(crate) fn parse_segments(text: &str) -> Vec<PromptSegment> {
+    let mut segments = Vec::new();
+    let mut prose: Vec<&str> = Vec::new();
+    // Language and lines of the open fence, if any.
+    let mut fence: Option<(Option<String>, Vec<&str>)> = None;
+    for line in text.split('\n') {
+        if let Some(info) = line.trim_start().strip_prefix("```") {
+            match fence.take() {
+                Some((language, code)) => segments.push(PromptSegment::CodeBlock {
+                    language,
+                    code: code.join("\n"),
+                }),
+                None => {
+                    push_prose(&prose.join("\n"), &mut segments);
+                    prose.clear();
+                    let language = info.trim();
+                    fence = Some((
+                        (!language.is_empty()).then(|| language.to_string()),
+                        Vec::new(),
+                    ));
+                }
+            }
+        } else if let Some((_, code)) = &mut fence {
+            code.push(line);
+        } else {
+            prose.push(line);
+        }
+    }
+    match fence {
+        Some((language, code)) => segments.push(PromptSegment::CodeBlock {
+            language,
+            code: code.join("\n"),
+        }),
+        None => push_prose(&prose.join("\n"), &mut segments),
+    }
+    segments
+}
+
+/// Append `prose` as text and mention segments.
+fn push_prose(prose: &str, segments: &mut Vec<PromptSegment>) {
+    let mut text_start = 0;
+    let mut chars = prose.char_indices().peekable();
+    let mut prev_is_space = true;
+    while let Some((i, c)) = chars.next() {
+        let starts_mention = c == '@'
+            && prev_is_space
+            && chars.peek().is_some_and(|(_, next)| !next.is_whitespace());
+        prev_is_space = c.is_whitespace();
+        if !starts_mention {
+            continue;
+        }
+        let end = prose[i..]
+            .find(char::is_whitespace)
+            .map_or(prose.len(), |len| i + len);
+        if text_start < i {
+            segments.push(PromptSegment::Text(prose[text_start..i].to_string()));
+        }
+        segments.push(PromptSegment::FileMention(prose[i + 1..end].to_string()));
+        while chars.peek().is_some_and(|(j, _)| *j < end) {
+            chars.next();
+        }
+        prev_is_space = false;
+        text_start = end;
+    }
+    if text_start < prose.len() {
+        segments.push(PromptSegment::Text(prose[text_start..].to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn prose_mentions_and_fences_become_segments() {
+        let text = "explain @src/main.rs please\n```rust\nfn main() {}\n```\nthanks @ all";
+        assert_eq!(
+            parse_segments(text),
+            vec![
+                PromptSegment::Text("explain ".to_string()),
+                PromptSegment::FileMention("src/main.rs".to_string()),
+                PromptSegment::Text(" please".to_string()),
+                PromptSegment::CodeBlock {
+                    language: Some("rust".to_string()),
+                    code: "fn main() {}".to_string(),
+                },
+                PromptSegment::Text("thanks @ all".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn mentions_inside_fences_and_words_are_text() {
+        assert_eq!(
+            parse_segments("mail a@b.c\n```\n@decorator\n"),
+            vec![
+                PromptSegment::Text("mail a@b.c".to_string()),
+                PromptSegment::CodeBlock {
+                    language: None,
+                    code: "@decorator\n".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+/// ESC, which introduces every 7-bit escape sequence.
+const ESC: char = '\u{1b}';
+/// BEL, one of the two OSC terminators.
+const BEL: char = '\u{7}';
+
+/// Remove terminal escape sequences and control characters from pasted text,
+/// keeping newlines and tabs.
+///
+/// Text copied out of another terminal can carry colour codes (CSI), window
+/// title or hyperlink sequences (OSC) and stray control bytes that would
+/// otherwise end up in the prompt. Printable text, including literal
+/// backslash escapes such as `\x1b[0m`, is kept verbatim.
+pub(crate) fn sanitize_pasted_text(pasted: &str) -> String {
+    let mut out = String::with_capacity(pasted.len());
+    let mut chars = pasted.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\n' | '\t' => out.push(ch),
+            ESC => match chars.next() {
+                Some('[') => skip_csi(&mut chars),
+                // OSC, DCS, SOS, PM and APC all run until a string terminator.
+                Some(']' | 'P' | 'X' | '^' | '_') => skip_string(&mut chars),
+                // Two-character sequences such as `ESC c` or `ESC =`.
+                Some(_) | None => {}
+            },
+            '\u{9b}' => skip_csi(&mut chars),
+            '\u{9d}' => skip_string(&mut chars),
+            ch if ch.is_control() => {}
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Replace typographic quotes and dashes, as produced by word processors and
+/// some editors, with their ASCII counterparts so pasted prose can be used
+/// in code: curly quotes become `'` / `"`, an en dash `-` and an em dash
+/// `--`.
+pub(crate) fn ascii_punctuation(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' => out.push('\''),
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' => out.push('"'),
+            '\u{2013}' => out.push('-'),
+            '\u{2014}' => out.push_str("--"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Skip the parameters and final byte of a CSI sequence.
+fn skip_csi(chars: &mut impl Iterator<Item = char>) {
+    for ch in chars {
+        if ('\u{40}'..='\u{7e}').contains(&ch) {
+            break;
+        }
+    }
+}
+
+/// Skip the body of a string sequence up to and including BEL or `ESC \`.
+fn skip_string(chars: &mut std::iter::Peekable<impl Iterator<Item = char>>) {
+    while let Some(ch) = chars.next() {
+        match ch {
+            BEL | '\u{9c}' => break,
+            ESC => {
+                if chars.peek() == Some(&'\\') {
+                    chars.next();
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn strips_csi_sequences() {
+        assert_eq!(
+            sanitize_pasted_text("\u{1b}[1;31merror\u{1b}[0m: bad\u{1b}[2K"),
+            "error: bad"
+        );
+        assert_eq!(sanitize_pasted_text("\u{9b}32mok"), "ok");
+    }
+
+    #[test]
+    fn strips_osc_sequences() {
+        // Window title terminated by BEL, hyperlink terminated by ST.
+        assert_eq!(
+            sanitize_pasted_text(
+                "\u{1b}]0;title\u{7}see \u{1b}]8;;https://example.com\u{1b}\\docs\u{1b}]8;;\u{1b}\\"
+            ),
+            "see docs"
+        );
+    }
+
+    #[test]
+    fn strips_control_bytes_but_keeps_newlines_and_tabs() {
+        assert_eq!(
+            sanitize_pasted_text("a\u{0}b\u{7}c\u{8}\td\ne\u{1b}="),
+            "abc\td\ne"
+        );
+    }
+
+    #[test]
+    fn typographic_punctuation_becomes_ascii() {
+        assert_eq!(
+            ascii_punctuation("\u{201c}curly\u{201d} isn\u{2019}t 1\u{2013}2 \u{2014} so"),
+            "\"curly\" isn't 1-2 -- so"
+        );
+        assert_eq!(
+            ascii_punctuation("plain \"ascii\" - text"),
+            "plain \"ascii\" - text"
+        );
+    }
+
+    #[test]
+    fn keeps_literal_backslash_escapes() {
+        let text = r"printf '\x1b[0m\n\t' \e[1m \033]";
+        assert_eq!(sanitize_pasted_text(text), text);
+    }
+}
@@ -0,0 +1,138 @@
+use tui_textarea::CursorMove;
+use tui_textarea::Input;
+use tui_textarea::Key;
+use tui_textarea::TextArea;
+
+/// Editing mode of the composer when Vim keybindings are enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VimMode {
+    Normal,
+    Insert,
+}
+
+/// What the composer should do after offering an input to [`VimState`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum VimOutcome {
+    /// The input was handled (possibly editing the textarea).
+    Consumed,
+    /// The input switched between Normal and Insert mode.
+    ModeChanged,
+    /// Not a Vim binding; handle the input as usual.
+    PassThrough,
+}
+
+/// Minimal modal editing: Normal-mode motions and edits on top of the
+/// regular (Insert-mode) composer behaviour.
+pub(crate) struct VimState {
+    mode: VimMode,
+    /// First key of a two-key command such as `dd`.
+    pending: Option<char>,
+}
+
+impl VimState {
+    /// Start in Insert mode so the composer can be typed into immediately.
+    pub fn new() -> Self {
+        Self {
+            mode: VimMode::Insert,
+            pending: None,
+        }
+    }
+
+    pub fn mode(&self) -> VimMode {
+        self.mode
+    }
+
+    pub fn handle_input(&mut self, input: &Input, textarea: &mut TextArea) -> VimOutcome {
+        match self.mode {
+            VimMode::Insert => match input {
+                Input { key: Key::Esc, .. } => {
+                    // Like Vim, leaving Insert mode moves the cursor onto the
+                    // last inserted character.
+                    textarea.move_cursor(CursorMove::Back);
+                    self.mode = VimMode::Normal;
+                    VimOutcome::ModeChanged
+                }
+                _ => VimOutcome::PassThrough,
+            },
+            VimMode::Normal => match input {
+                Input {
+                    key: Key::Char(c),
+                    ctrl: false,
+                    alt: false,
+                    ..
+                } => self.handle_normal_char(*c, textarea),
+                _ => {
+                    self.pending = None;
+                    VimOutcome::PassThrough
+                }
+            },
+        }
+    }
+
+    fn handle_normal_char(&mut self, c: char, textarea: &mut TextArea) -> VimOutcome {
+        if let Some(prev) = self.pending.take() {
+            if prev == 'd' && c == 'd' {
+                delete_current_line(textarea);
+            }
+            return VimOutcome::Consumed;
+        }
+
+        match c {
+            'h' => textarea.move_cursor(CursorMove::Back),
+            'j' => textarea.move_cursor(CursorMove::Down),
+            'k' => textarea.move_cursor(CursorMove::Up),
+            'l' => textarea.move_cursor(CursorMove::Forward),
+            'w' => textarea.move_cursor(CursorMove::WordForward),
+            'b' => textarea.move_cursor(CursorMove::WordBack),
+            '0' => textarea.move_cursor(CursorMove::Head),
+            '$' => textarea.move_cursor(CursorMove::End),
+            'x' => {
+                textarea.delete_next_char();
+            }
+            'd' => self.pending = Some('d'),
+            'i' => return self.enter_insert(),
+            'a' => {
+                textarea.move_cursor(CursorMove::Forward);
+                return self.enter_insert();
+            }
+            'o' => {
+                textarea.move_cursor(CursorMove::End);
+                textarea.insert_newline();
+                return self.enter_insert();
+            }
+            // `:` and every other key are ignored so stray keystrokes never
+            // insert text while in Normal mode.
+            _ => {}
+        }
+        VimOutcome::Consumed
+    }
+
+    fn enter_insert(&mut self) -> VimOutcome {
+        self.mode = VimMode::Insert;
+        VimOutcome::ModeChanged
+    }
+}
+
+/// Remove the line under the cursor, joining its neighbours.
+fn delete_current_line(textarea: &mut TextArea) {
+    let (row, _) = textarea.cursor();
+    let line_count = textarea.lines().len();
+    textarea.move_cursor(CursorMove::Head);
+    // At the end of a line `delete_line_by_end` would join the next line, so
+    // only call it when there is text to remove.
+    if textarea
+        .lines()
+        .get(row)
+        .is_some_and(|line| !line.is_empty())
+    {
+        textarea.delete_line_by_end();
+    }
+    if row + 1 < line_count {
+        // Remove the newline that follows so the next line moves up.
+        textarea.delete_next_char();
+    } else if row > 0 {
+        // Last line: remove the newline before it instead.
+        textarea.delete_newline();
+    }
+    textarea.move_cursor(CursorMove::Head);
+}
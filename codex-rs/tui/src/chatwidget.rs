@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -26,16 +27,22 @@ use ratatui::layout::Layout;
 use ratatui::layout::Rect;
 use ratatui::widgets::Widget;
 use ratatui::widgets::WidgetRef;
+use strum::IntoEnumIterator;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::mpsc::unbounded_channel;
 
 use crate::app_event::AppEvent;
+use crate::app_event::ScrollDirection;
 use crate::app_event_sender::AppEventSender;
 use crate::bottom_pane::BottomPane;
 use crate::bottom_pane::BottomPaneParams;
+use crate::bottom_pane::ChatComposerConfig;
+use crate::bottom_pane::ComposerTheme;
 use crate::bottom_pane::InputResult;
 use crate::conversation_history_widget::ConversationHistoryWidget;
 use crate::history_cell::PatchEventType;
+use crate::slash_command::PromptMode;
+use crate::slash_command::SlashCommand;
 use crate::user_approval_widget::ApprovalRequest;
 
 pub(crate) struct ChatWidget<'a> {
@@ -46,6 +53,15 @@ pub(crate) struct ChatWidget<'a> {
     input_focus: InputFocus,
     config: Config,
     initial_user_message: Option<UserMessage>,
+    /// Messages submitted while a task was running, sent in order as tasks
+    /// finish.
+    queued_user_messages: VecDeque<UserMessage>,
+    /// Large pastes received via `AppEvent::AttachText`, and not withdrawn
+    /// again, that have not been sent yet.
+    pasted_texts: Vec<PastedText>,
+    /// Set by a Ctrl+D that would have quit over an unsent message, so that
+    /// a second one quits anyway.
+    quit_over_draft: bool,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -57,6 +73,12 @@ enum InputFocus {
 struct UserMessage {
     text: String,
     image_paths: Vec<PathBuf>,
+    /// Non-image files; the agent is told their paths.
+    file_paths: Vec<PathBuf>,
+    /// Intent chosen with a `/ask` or `/edit` prefix.
+    mode: PromptMode,
+    /// Large pastes whose placeholder appears in `text`.
+    pasted_texts: Vec<PastedText>,
 }
 
 impl From<String> for UserMessage {
@@ -64,15 +86,34 @@ impl From<String> for UserMessage {
         Self {
             text,
             image_paths: Vec::new(),
+            file_paths: Vec::new(),
+            mode: PromptMode::Default,
+            pasted_texts: Vec::new(),
         }
     }
 }
 
+/// A large paste the composer replaced with a placeholder such as
+/// `[pasted 2000 lines]`. Its content is sent as a separate input item
+/// instead of being part of the prompt text.
+#[derive(Clone)]
+struct PastedText {
+    placeholder: String,
+    name: String,
+    content: String,
+}
+
 fn create_initial_user_message(text: String, image_paths: Vec<PathBuf>) -> Option<UserMessage> {
     if text.is_empty() && image_paths.is_empty() {
         None
     } else {
-        Some(UserMessage { text, image_paths })
+        Some(UserMessage {
+            text,
+            image_paths,
+            file_paths: Vec::new(),
+            mode: PromptMode::Default,
+            pasted_texts: Vec::new(),
+        })
     }
 }
 
@@ -117,13 +158,41 @@ impl ChatWidget<'_> {
             }
         });
 
-        Self {
+        let mut widget = Self {
             app_event_tx: app_event_tx.clone(),
             codex_op_tx,
             conversation_history: ConversationHistoryWidget::new(),
             bottom_pane: BottomPane::new(BottomPaneParams {
                 app_event_tx,
                 has_input_focus: true,
+                disable_input_while_busy: config.tui.disable_input_while_busy,
+                composer_config: ChatComposerConfig {
+                    submit_mode: config.tui.submit_mode,
+                    esc_behavior: config.tui.esc_behavior,
+                    history_nav_keys: config.tui.history_nav_keys,
+                    dot_repeat: config.tui.dot_repeat,
+                    file_search_root: Some(config.cwd.clone()),
+                    draft_path: Some(config.codex_home.join("draft")),
+                    vim_mode: config.tui.vim_mode,
+                    jump_keys: config.tui.jump_keys,
+                    highlight_code_blocks: config.tui.highlight_code_blocks,
+                    auto_indent: config.tui.auto_indent,
+                    continue_lists: config.tui.continue_lists,
+                    tab_width: config.tui.tab_width,
+                    paste_attachment_lines: config.tui.paste_attachment_lines,
+                    ascii_paste_punctuation: config.tui.ascii_paste_punctuation,
+                    watch_files: config.tui.watch_files,
+                    max_chars: config.tui.max_chars,
+                    over_limit: config.tui.over_limit,
+                    show_word_count: config.tui.show_word_count,
+                    parsed_submissions: config.tui.parsed_submissions,
+                    popup_placement: config.tui.popup_placement,
+                    models: std::iter::once(&config.model)
+                        .chain(config.tui.models.iter().filter(|m| **m != config.model))
+                        .cloned()
+                        .collect(),
+                },
+                composer_theme: ComposerTheme::from_env(),
             }),
             input_focus: InputFocus::BottomPane,
             config,
@@ -131,16 +200,63 @@ impl ChatWidget<'_> {
                 initial_prompt.unwrap_or_default(),
                 initial_images,
             ),
+            queued_user_messages: VecDeque::new(),
+            pasted_texts: Vec::new(),
+            quit_over_draft: false,
+        };
+        widget.bottom_pane.set_composer_row_limits(
+            widget.config.tui.min_composer_rows,
+            widget.config.tui.max_composer_rows,
+        );
+        widget
+            .bottom_pane
+            .set_submit_filter(Box::new(crate::api_key_check::reject_api_keys));
+        if let Some(chars_per_token) = widget.config.tui.chars_per_token.filter(|n| *n > 0) {
+            widget
+                .bottom_pane
+                .set_token_estimator(Box::new(move |text: &str| {
+                    text.chars().count().div_ceil(chars_per_token)
+                }));
+        }
+        if !widget.config.tui.templates.is_empty() {
+            let templates = widget.config.tui.templates.clone();
+            widget
+                .bottom_pane
+                .set_template_resolver(Box::new(move |name: &str| templates.get(name).cloned()));
+        }
+        // Without a helper to read it, `/selection` could only ever fail.
+        if !crate::selection::can_read_selection() {
+            widget.bottom_pane.set_commands(
+                SlashCommand::iter()
+                    .filter(|cmd| *cmd != SlashCommand::Selection)
+                    .collect(),
+            );
+        }
+        widget
+    }
+
+    /// Move input focus to the composer.
+    pub(crate) fn focus_composer(&mut self) {
+        if self.input_focus == InputFocus::BottomPane {
+            return;
         }
+        self.input_focus = InputFocus::BottomPane;
+        self.conversation_history.set_input_focus(false);
+        self.bottom_pane.set_input_focus(true);
+        self.request_redraw();
     }
 
     pub(crate) fn handle_key_event(&mut self, key_event: KeyEvent) {
-        // Special-case <Tab>: normally toggles focus between history and bottom panes.
-        // However, when the slash-command popup is visible we forward the key
-        // to the bottom pane so it can handle auto-completion.
-        if matches!(key_event.code, crossterm::event::KeyCode::Tab)
-            && !self.bottom_pane.is_command_popup_visible()
-        {
+        self.quit_over_draft = false;
+        // Shift+Tab toggles focus between the history and bottom panes, as
+        // does Tab from the history pane. In the composer Tab is left to the
+        // bottom pane, which indents or completes with it.
+        let toggles_focus = match key_event.code {
+            crossterm::event::KeyCode::BackTab => true,
+            crossterm::event::KeyCode::Tab => self.input_focus == InputFocus::HistoryPane,
+            _ => false,
+        };
+        if toggles_focus {
             self.input_focus = match self.input_focus {
                 InputFocus::HistoryPane => InputFocus::BottomPane,
                 InputFocus::BottomPane => InputFocus::HistoryPane,
@@ -161,22 +277,162 @@ impl ChatWidget<'_> {
                 }
             }
             InputFocus::BottomPane => match self.bottom_pane.handle_key_event(key_event) {
-                InputResult::Submitted(text) => {
-                    self.submit_user_message(text.into());
+                InputResult::Submitted {
+                    text,
+                    image_paths,
+                    file_paths,
+                    mode,
+                    keep_draft,
+                } => self.submit_from_composer(text, image_paths, file_paths, mode, keep_draft),
+                InputResult::SubmittedParsed(parsed) => {
+                    tracing::debug!("submitted message segments: {:?}", parsed.segments);
+                    self.submit_from_composer(
+                        parsed.text,
+                        parsed.image_paths,
+                        parsed.file_paths,
+                        parsed.mode,
+                        parsed.keep_draft,
+                    )
+                }
+                InputResult::Queued(text) => {
+                    tracing::debug!("queued a {}-char message", text.chars().count());
+                }
+                InputResult::Undelivered => {
+                    tracing::debug!("composer action was not delivered");
                 }
                 InputResult::None => {}
             },
         }
     }
 
+    fn submit_from_composer(
+        &mut self,
+        text: String,
+        image_paths: Vec<PathBuf>,
+        file_paths: Vec<PathBuf>,
+        mode: PromptMode,
+        keep_draft: bool,
+    ) {
+        let pasted_texts = if keep_draft {
+            // The placeholders are still in the composer.
+            self.pasted_texts.clone()
+        } else {
+            std::mem::take(&mut self.pasted_texts)
+        };
+        self.submit_user_message(UserMessage {
+            text,
+            image_paths,
+            file_paths,
+            mode,
+            pasted_texts,
+        });
+    }
+
+    /// Forward bracketed-paste text to the composer when it has focus.
+    pub(crate) fn handle_paste(&mut self, pasted: String) {
+        if self.input_focus == InputFocus::BottomPane {
+            self.bottom_pane.handle_paste(pasted);
+        }
+    }
+
+    /// Hold a message submitted while the agent is busy until the current
+    /// task finishes.
+    pub(crate) fn queue_user_message(
+        &mut self,
+        text: String,
+        image_paths: Vec<PathBuf>,
+        file_paths: Vec<PathBuf>,
+        mode: PromptMode,
+    ) {
+        let pasted_texts = std::mem::take(&mut self.pasted_texts);
+        self.queued_user_messages.push_back(UserMessage {
+            text,
+            image_paths,
+            file_paths,
+            mode,
+            pasted_texts,
+        });
+        self.bottom_pane
+            .set_queued_messages(self.queued_user_messages.len());
+    }
+
+    /// Hold a large paste until the next message is submitted. The composer
+    /// withdraws it with [`Self::detach_pasted_text`] if its placeholder is
+    /// removed first.
+    pub(crate) fn attach_pasted_text(
+        &mut self,
+        content: String,
+        suggested_name: String,
+        placeholder: String,
+    ) {
+        self.pasted_texts.push(PastedText {
+            placeholder,
+            name: suggested_name,
+            content,
+        });
+    }
+
+    /// Drop a paste whose placeholder is no longer in the composer.
+    pub(crate) fn detach_pasted_text(&mut self, suggested_name: &str) {
+        self.pasted_texts
+            .retain(|pasted| pasted.name != suggested_name);
+    }
+
+    /// Send the oldest queued message, if any, now that the agent is idle.
+    fn submit_next_queued_message(&mut self) {
+        if let Some(user_message) = self.queued_user_messages.pop_front() {
+            self.bottom_pane
+                .set_queued_messages(self.queued_user_messages.len());
+            self.submit_user_message(user_message);
+        }
+    }
+
     fn submit_user_message(&mut self, user_message: UserMessage) {
-        let UserMessage { text, image_paths } = user_message;
+        let UserMessage {
+            text,
+            image_paths,
+            file_paths,
+            mode,
+            pasted_texts,
+        } = user_message;
         let mut items: Vec<InputItem> = Vec::new();
 
+        match mode {
+            PromptMode::Ask => items.push(InputItem::Text {
+                text: "This is a question only: answer it without modifying any files.".to_string(),
+            }),
+            PromptMode::Edit => items.push(InputItem::Text {
+                text: "Make the requested changes in the workspace.".to_string(),
+            }),
+            PromptMode::Default => {}
+        }
+
         if !text.is_empty() {
             items.push(InputItem::Text { text: text.clone() });
         }
 
+        for PastedText {
+            placeholder,
+            name,
+            content,
+        } in pasted_texts
+        {
+            items.push(InputItem::Text {
+                text: format!("{placeholder} ({name}):\n{content}"),
+            });
+        }
+
+        if !file_paths.is_empty() {
+            let list = file_paths
+                .iter()
+                .map(|path| format!("- {}", path.display()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            items.push(InputItem::Text {
+                text: format!("Attached files:\n{list}"),
+            });
+        }
+
         for path in image_paths {
             items.push(InputItem::LocalImage { path });
         }
@@ -191,15 +447,6 @@ impl ChatWidget<'_> {
                 tracing::error!("failed to send message: {e}");
             });
 
-        // Persist the text to cross-session message history.
-        if !text.is_empty() {
-            self.codex_op_tx
-                .send(Op::AddToHistory { text: text.clone() })
-                .unwrap_or_else(|e| {
-                    tracing::error!("failed to send AddHistory op: {e}");
-                });
-        }
-
         // Only show text portion in conversation history for now.
         if !text.is_empty() {
             self.conversation_history.add_user_message(text);
@@ -219,10 +466,16 @@ impl ChatWidget<'_> {
                 // composer can navigate through past messages.
                 self.bottom_pane
                     .set_history_metadata(event.history_log_id, event.history_entry_count);
+                self.bottom_pane
+                    .set_composer_placeholder(format!("send a message to {}", event.model));
 
                 if let Some(user_message) = self.initial_user_message.take() {
                     // If the user provided an initial message, add it to the
-                    // conversation history.
+                    // conversation history. Messages from the composer are
+                    // recorded via `AppEvent::RecordHistory` instead.
+                    if !user_message.text.is_empty() {
+                        self.record_history(user_message.text.clone());
+                    }
                     self.submit_user_message(user_message);
                 }
 
@@ -248,11 +501,13 @@ impl ChatWidget<'_> {
                 last_agent_message: _,
             }) => {
                 self.bottom_pane.set_task_running(false);
+                self.submit_next_queued_message();
                 self.request_redraw();
             }
             EventMsg::Error(ErrorEvent { message }) => {
                 self.conversation_history.add_error(message);
                 self.bottom_pane.set_task_running(false);
+                self.submit_next_queued_message();
             }
             EventMsg::ExecApprovalRequest(ExecApprovalRequestEvent {
                 command,
@@ -366,6 +621,41 @@ impl ChatWidget<'_> {
         }
     }
 
+    /// Show a short message from the UI in the conversation history.
+    pub(crate) fn show_notice(&mut self, message: String) {
+        self.conversation_history.add_background_event(message);
+        self.request_redraw();
+    }
+
+    /// Show the help of `command`, or a summary of all commands.
+    pub(crate) fn show_command_help(&mut self, command: Option<SlashCommand>) {
+        let text = match command {
+            Some(command) => command.help_text(),
+            None => SlashCommand::iter()
+                .map(|cmd| format!("/{} — {}", cmd.command(), cmd.description()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        self.conversation_history.add_background_event(text);
+        self.request_redraw();
+    }
+
+    /// Report the model used by this session in the conversation history.
+    /// `requested` is the text typed after `/model`; the model is fixed for
+    /// the session, so a requested name is only echoed back.
+    pub(crate) fn show_current_model(&mut self, requested: &str) {
+        let message = if requested.is_empty() {
+            format!("Current model: {}", self.config.model)
+        } else {
+            format!(
+                "Current model: {}. Switching to `{requested}` mid-session is not supported.",
+                self.config.model
+            )
+        };
+        self.conversation_history.add_background_event(message);
+        self.request_redraw();
+    }
+
     /// Update the live log preview while a task is running.
     pub(crate) fn update_latest_log(&mut self, line: String) {
         // Forward only if we are currently showing the status indicator.
@@ -376,6 +666,11 @@ impl ChatWidget<'_> {
         self.app_event_tx.send(AppEvent::Redraw);
     }
 
+    pub(crate) fn scroll_transcript(&mut self, direction: ScrollDirection) {
+        self.conversation_history.scroll_page(direction);
+        self.request_redraw();
+    }
+
     pub(crate) fn handle_scroll_delta(&mut self, scroll_delta: i32) {
         // If the user is trying to scroll exactly one line, we let them, but
         // otherwise we assume they are trying to scroll in larger increments.
@@ -389,6 +684,49 @@ impl ChatWidget<'_> {
         self.request_redraw();
     }
 
+    /// Persist `text` to cross-session message history.
+    pub(crate) fn record_history(&self, text: String) {
+        self.codex_op_tx
+            .send(Op::AddToHistory { text })
+            .unwrap_or_else(|e| {
+                tracing::error!("failed to send AddHistory op: {e}");
+            });
+    }
+
+    pub(crate) fn on_external_editor_response(&mut self, text: String) {
+        self.bottom_pane.on_external_editor_response(text);
+    }
+
+    pub(crate) fn on_selection_response(&mut self, selection: Option<String>) {
+        self.bottom_pane.on_selection_response(selection);
+    }
+
+    /// Ctrl+C copies from the focused composer when it has something to
+    /// copy and interrupts the agent otherwise.
+    pub(crate) fn handle_ctrl_c(&mut self) {
+        if self.input_focus == InputFocus::BottomPane && self.bottom_pane.copy_from_composer() {
+            return;
+        }
+        self.submit_op(Op::Interrupt);
+    }
+
+    /// Ctrl+D edits the composer while it is shown and has focus; it quits
+    /// from there only once the composer is empty. Anywhere else it quits,
+    /// after a second press if the composer holds an unsent message.
+    pub(crate) fn handle_ctrl_d(&mut self, key_event: KeyEvent) {
+        if self.input_focus == InputFocus::BottomPane && self.bottom_pane.is_composer_visible() {
+            self.handle_key_event(key_event);
+        } else if self.bottom_pane.has_unsent_content() && !self.quit_over_draft {
+            self.show_notice(
+                "The composer has an unsent message; press Ctrl+D again to quit anyway."
+                    .to_string(),
+            );
+            self.quit_over_draft = true;
+        } else {
+            self.app_event_tx.send(AppEvent::ExitRequest);
+        }
+    }
+
     /// Forward an `Op` directly to codex.
     pub(crate) fn submit_op(&self, op: Op) {
         if let Err(e) = self.codex_op_tx.send(op) {
@@ -1,3 +1,4 @@
+use crate::app_event::ScrollDirection;
 use crate::cell_widget::CellWidget;
 use crate::history_cell::CommandOutput;
 use crate::history_cell::HistoryCell;
@@ -74,6 +75,14 @@ impl ConversationHistoryWidget {
         }
     }
 
+    /// Scroll by one full viewport height.
+    pub(crate) fn scroll_page(&mut self, direction: ScrollDirection) {
+        match direction {
+            ScrollDirection::Up => self.scroll_page_up(),
+            ScrollDirection::Down => self.scroll_page_down(),
+        }
+    }
+
     /// Negative delta scrolls up; positive delta scrolls down.
     pub(crate) fn scroll(&mut self, delta: i32) {
         match delta.cmp(&0) {
@@ -0,0 +1,53 @@
+//! Editing the composer text in the user's `$VISUAL` / `$EDITOR`, opened
+//! with Ctrl+X Ctrl+E.
+
+use std::io;
+use std::process::Command;
+
+/// Editor used when neither `VISUAL` nor `EDITOR` is set.
+const FALLBACK_EDITOR: &str = "vi";
+
+/// Program and arguments to run, e.g. `["code", "--wait"]`.
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|command| !command.is_empty())
+        .unwrap_or_else(|| vec![FALLBACK_EDITOR.to_string()])
+}
+
+/// Open `text` in the editor and return what it was saved as. Blocks until
+/// the editor exits, so the caller must hand over the terminal first.
+pub(crate) fn edit_text(text: &str) -> io::Result<String> {
+    let path = std::env::temp_dir().join(format!("codex-prompt-{}.md", std::process::id()));
+    std::fs::write(&path, text)?;
+    let command = editor_command();
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status();
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    let status = status?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {status}",
+            command[0]
+        )));
+    }
+    let mut edited = edited?;
+    // Most editors end the file with a newline the text did not have.
+    if edited.ends_with('\n') && !text.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+    Ok(edited)
+}
@@ -0,0 +1,124 @@
+//! Lightweight fzf-style subsequence matcher used by the composer popups.
+
+/// Points awarded for every query character that is matched.
+const SCORE_MATCH: i32 = 16;
+/// Bonus when a match immediately follows the previous match.
+const BONUS_CONSECUTIVE: i32 = 8;
+/// Bonus when a match starts a "word" (start of string or after a separator).
+const BONUS_BOUNDARY: i32 = 8;
+/// Penalty for every unmatched character inside the matched span.
+const PENALTY_GAP: i32 = 1;
+
+/// Outcome of a successful [`fuzzy_match`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i32,
+    /// Char indices (not byte offsets) into the candidate that matched the
+    /// query, in ascending order.
+    pub indices: Vec<usize>,
+}
+
+/// Match `query` against `candidate` as a case-insensitive subsequence.
+/// Returns `None` when not every query character can be found in order.
+///
+/// Like fzf's v1 algorithm, the leftmost occurrence of the full subsequence is
+/// found first and then tightened by scanning backwards from its end, so that
+/// e.g. `cmt` prefers the `c..m..t` run with the fewest gaps.
+pub(crate) fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().map(fold_case).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars.iter().copied().map(fold_case).collect();
+
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            indices: Vec::new(),
+        });
+    }
+
+    // Forward pass: find where the leftmost complete match ends.
+    let mut qi = 0;
+    let mut end = None;
+    for (ci, c) in lower.iter().enumerate() {
+        if *c == query[qi] {
+            qi += 1;
+            if qi == query.len() {
+                end = Some(ci);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // Backward pass: find the latest start that still matches up to `end`.
+    let mut qi = query.len();
+    let mut start = end;
+    for ci in (0..=end).rev() {
+        if lower[ci] == query[qi - 1] {
+            qi -= 1;
+            if qi == 0 {
+                start = ci;
+                break;
+            }
+        }
+    }
+
+    // Final forward pass within [start, end] to collect indices.
+    let mut indices = Vec::with_capacity(query.len());
+    let mut qi = 0;
+    for (ci, c) in lower.iter().enumerate().take(end + 1).skip(start) {
+        if qi < query.len() && *c == query[qi] {
+            indices.push(ci);
+            qi += 1;
+        }
+    }
+
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    for &ci in &indices {
+        score += SCORE_MATCH;
+        if prev.is_some_and(|p| p + 1 == ci) {
+            score += BONUS_CONSECUTIVE;
+        }
+        if ci == 0 || matches!(chars[ci - 1], '-' | '_' | ' ' | '/' | '.') {
+            score += BONUS_BOUNDARY;
+        }
+        prev = Some(ci);
+    }
+    let span = end - start + 1;
+    score -= (span - indices.len()) as i32 * PENALTY_GAP;
+
+    Some(FuzzyMatch { score, indices })
+}
+
+/// Single-char lowercase mapping so query and candidate indices stay aligned.
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::expect_used)]
+    use super::*;
+
+    #[test]
+    fn subsequence_is_required() {
+        assert!(fuzzy_match("cmp", "compact").is_some());
+        assert!(fuzzy_match("cpm", "compact").is_none());
+        assert!(fuzzy_match("", "anything").is_some());
+    }
+
+    #[test]
+    fn tighter_match_scores_higher() {
+        let commit = fuzzy_match("cmt", "commit").expect("commit matches");
+        let comment = fuzzy_match("cmt", "comment").expect("comment matches");
+        assert!(commit.score > comment.score);
+    }
+
+    #[test]
+    fn indices_are_char_based() {
+        let m = fuzzy_match("ab", "héab").expect("matches");
+        assert_eq!(m.indices, vec![2, 3]);
+    }
+}
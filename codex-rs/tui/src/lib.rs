@@ -19,6 +19,7 @@ use tracing_appender::non_blocking;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::prelude::*;
 
+mod api_key_check;
 mod app;
 mod app_event;
 mod app_event_sender;
@@ -29,6 +30,8 @@ mod citation_regex;
 mod cli;
 mod conversation_history_widget;
 mod exec_command;
+mod external_editor;
+mod fuzzy_match;
 mod git_warning_screen;
 mod history_cell;
 mod log_layer;
@@ -36,6 +39,7 @@ mod login_screen;
 mod markdown;
 mod mouse_capture;
 mod scroll_event_helper;
+mod selection;
 mod slash_command;
 mod status_indicator_widget;
 mod text_block;
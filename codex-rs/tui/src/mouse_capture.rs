@@ -38,6 +38,10 @@ impl MouseCapture {
         Ok(())
     }
 
+    pub(crate) fn is_active(&self) -> bool {
+        self.mouse_capture_is_active
+    }
+
     pub(crate) fn toggle(&mut self) -> Result<()> {
         self.set_active(!self.mouse_capture_is_active)
     }
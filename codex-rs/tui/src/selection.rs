@@ -0,0 +1,36 @@
+//! Reading the text the user has selected on screen, for `/selection`.
+
+use std::process::Command;
+
+/// Helpers that print the primary selection (X11 / Wayland), tried in order.
+const SELECTION_READERS: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--primary", "--no-newline"]),
+    ("xclip", &["-o", "-selection", "primary"]),
+    ("xsel", &["--primary", "--output"]),
+];
+
+/// Whether one of the helpers that read the selection is on `PATH`.
+pub(crate) fn can_read_selection() -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        SELECTION_READERS
+            .iter()
+            .any(|(program, _)| dir.join(program).is_file())
+    })
+}
+
+/// The current selection, or `None` when it is empty or no helper to read
+/// it is installed. Runs external programs, so call it off the UI thread.
+pub(crate) fn current_selection() -> Option<String> {
+    SELECTION_READERS.iter().find_map(|(program, args)| {
+        let output = Command::new(program).args(*args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout)
+            .ok()
+            .filter(|text| !text.is_empty())
+    })
+}
@@ -6,13 +6,32 @@ use strum_macros::EnumIter;
 use strum_macros::EnumString;
 use strum_macros::IntoStaticStr;
 
+/// Languages offered when completing the argument of `/code`. Any other
+/// language can still be typed.
+const CODE_LANGUAGES: &[&str] = &[
+    "bash",
+    "diff",
+    "javascript",
+    "json",
+    "python",
+    "rust",
+    "toml",
+    "typescript",
+];
+
 /// Commands that can be invoked by starting a message with a leading slash.
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, EnumIter, AsRefStr, IntoStaticStr,
 )]
 #[strum(serialize_all = "kebab-case")]
 pub enum SlashCommand {
+    Ask,
+    Edit,
+    Code,
     New,
+    Model,
+    Help,
+    Selection,
     ToggleMouseMode,
     Quit,
 }
@@ -21,7 +40,13 @@ impl SlashCommand {
     /// User-visible description shown in the popup.
     pub fn description(self) -> &'static str {
         match self {
+            SlashCommand::Ask => "Ask a question without changing any files.",
+            SlashCommand::Edit => "Ask for changes to be made.",
+            SlashCommand::Code => "Insert a fenced code block.",
             SlashCommand::New => "Start a new chat.",
+            SlashCommand::Model => "Show the current model.",
+            SlashCommand::Help => "Show help for a command.",
+            SlashCommand::Selection => "Insert the text currently selected on screen.",
             SlashCommand::ToggleMouseMode => {
                 "Toggle mouse mode (enable for scrolling, disable for text selection)"
             }
@@ -29,6 +54,103 @@ impl SlashCommand {
         }
     }
 
+    /// Group the command is listed under in the popup.
+    pub fn category(self) -> CommandCategory {
+        match self {
+            SlashCommand::Ask | SlashCommand::Edit => CommandCategory::Prompt,
+            SlashCommand::Code | SlashCommand::Selection => CommandCategory::Insert,
+            SlashCommand::New | SlashCommand::Model | SlashCommand::Quit => {
+                CommandCategory::Session
+            }
+            SlashCommand::Help | SlashCommand::ToggleMouseMode => CommandCategory::Interface,
+        }
+    }
+
+    /// Alternative names accepted when typing the command, e.g. `/q` for
+    /// `/quit`. The canonical name is always what ends up in the composer.
+    pub fn aliases(self) -> &'static [&'static str] {
+        match self {
+            SlashCommand::Quit => &["q", "exit"],
+            SlashCommand::Ask
+            | SlashCommand::Edit
+            | SlashCommand::Code
+            | SlashCommand::New
+            | SlashCommand::Model
+            | SlashCommand::Help
+            | SlashCommand::Selection
+            | SlashCommand::ToggleMouseMode => &[],
+        }
+    }
+
+    /// Argument synopsis shown once the command has been fully typed, e.g.
+    /// `/model <name>`. `None` for commands that take no arguments.
+    pub fn usage(self) -> Option<&'static str> {
+        match self {
+            SlashCommand::Ask => Some("/ask <question>"),
+            SlashCommand::Edit => Some("/edit <request>"),
+            SlashCommand::Code => Some("/code <language>"),
+            SlashCommand::Model => Some("/model <name>"),
+            SlashCommand::Help => Some("/help <command>"),
+            SlashCommand::New
+            | SlashCommand::Selection
+            | SlashCommand::ToggleMouseMode
+            | SlashCommand::Quit => None,
+        }
+    }
+
+    /// Values offered when completing this command's argument. `models` are
+    /// the model names known to the session.
+    pub fn arg_completions(self, models: &[String]) -> Vec<String> {
+        match self {
+            SlashCommand::Model => models.to_vec(),
+            SlashCommand::Help => SlashCommand::iter()
+                .map(|cmd| cmd.command().to_string())
+                .collect(),
+            SlashCommand::Code => CODE_LANGUAGES.iter().map(|l| l.to_string()).collect(),
+            SlashCommand::Ask
+            | SlashCommand::Edit
+            | SlashCommand::New
+            | SlashCommand::Selection
+            | SlashCommand::ToggleMouseMode
+            | SlashCommand::Quit => Vec::new(),
+        }
+    }
+
+    /// Mode given to a prompt prefixed with this command, e.g. `/ask ...`.
+    /// Such commands are never dispatched; the rest of the text is submitted
+    /// as the prompt.
+    pub fn prompt_mode(self) -> Option<PromptMode> {
+        match self {
+            SlashCommand::Ask => Some(PromptMode::Ask),
+            SlashCommand::Edit => Some(PromptMode::Edit),
+            SlashCommand::Code
+            | SlashCommand::New
+            | SlashCommand::Model
+            | SlashCommand::Help
+            | SlashCommand::Selection
+            | SlashCommand::ToggleMouseMode
+            | SlashCommand::Quit => None,
+        }
+    }
+
+    /// The command called `name` or with `name` as an alias.
+    pub fn lookup(name: &str) -> Option<SlashCommand> {
+        SlashCommand::iter().find(|cmd| cmd.command() == name || cmd.aliases().contains(&name))
+    }
+
+    /// Detailed help shown for `/help <command>`.
+    pub fn help_text(self) -> String {
+        let mut text = format!("/{} — {}", self.command(), self.description());
+        if let Some(usage) = self.usage() {
+            text.push_str(&format!("\nUsage: {usage}"));
+        }
+        if !self.aliases().is_empty() {
+            let aliases: Vec<String> = self.aliases().iter().map(|a| format!("/{a}")).collect();
+            text.push_str(&format!("\nAliases: {}", aliases.join(", ")));
+        }
+        text
+    }
+
     /// Command string without the leading '/'. Provided for compatibility with
     /// existing code that expects a method named `command()`.
     pub fn command(self) -> &'static str {
@@ -36,7 +158,124 @@ impl SlashCommand {
     }
 }
 
+/// Groups of commands, in the order the popup lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommandCategory {
+    Prompt,
+    Insert,
+    Session,
+    Interface,
+}
+
+impl CommandCategory {
+    /// Header shown above the group in the popup.
+    pub fn label(self) -> &'static str {
+        match self {
+            CommandCategory::Prompt => "Prompt",
+            CommandCategory::Insert => "Insert",
+            CommandCategory::Session => "Session",
+            CommandCategory::Interface => "Interface",
+        }
+    }
+}
+
+/// Intent of a prompt, chosen with a mode prefix such as `/ask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptMode {
+    /// No prefix: the agent decides.
+    #[default]
+    Default,
+    /// Read-only question; no files should be changed.
+    Ask,
+    /// Changes to the workspace are expected.
+    Edit,
+}
+
+/// Command name and argument text of `text`, which starts with a slash:
+/// `/model gpt-4o` gives `("model", Some("gpt-4o"))`. The arguments are all
+/// that follows the whitespace ending the name, untrimmed, and `None` when
+/// the name is all there is. The text is only split at char boundaries, so
+/// any input is safe.
+pub fn split_command_line(text: &str) -> Option<(&str, Option<&str>)> {
+    let rest = text.strip_prefix('/')?.trim_start();
+    Some(match rest.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, Some(args)),
+        None => (rest, None),
+    })
+}
+
+/// Split a leading mode prefix off `text`: `/ask how does X work` becomes
+/// `(PromptMode::Ask, "how does X work")`. Text without a recognised prefix,
+/// or with nothing after it, is returned unchanged with the default mode.
+pub fn parse_prompt_mode(text: &str) -> (PromptMode, &str) {
+    let parsed = split_command_line(text).and_then(|(name, prompt)| {
+        let prompt = prompt?.trim_start();
+        if prompt.is_empty() {
+            return None;
+        }
+        let mode = SlashCommand::lookup(name)?.prompt_mode()?;
+        Some((mode, prompt))
+    });
+    parsed.unwrap_or((PromptMode::Default, text))
+}
+
 /// Return all built-in commands in a HashMap keyed by their command string.
 pub fn built_in_slash_commands() -> HashMap<&'static str, SlashCommand> {
     SlashCommand::iter().map(|c| (c.command(), c)).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn mode_prefix_is_split_off() {
+        assert_eq!(
+            parse_prompt_mode("/ask how does X work"),
+            (PromptMode::Ask, "how does X work")
+        );
+        assert_eq!(
+            parse_prompt_mode("/edit  rename foo\nto bar"),
+            (PromptMode::Edit, "rename foo\nto bar")
+        );
+        assert_eq!(parse_prompt_mode("/ask "), (PromptMode::Default, "/ask "));
+        assert_eq!(
+            parse_prompt_mode("/model o3"),
+            (PromptMode::Default, "/model o3")
+        );
+        assert_eq!(parse_prompt_mode("ask me"), (PromptMode::Default, "ask me"));
+    }
+
+    #[test]
+    fn help_text_lists_usage_and_aliases() {
+        assert_eq!(
+            SlashCommand::Model.help_text(),
+            "/model — Show the current model.\nUsage: /model <name>"
+        );
+        assert_eq!(
+            SlashCommand::Quit.help_text(),
+            "/quit — Exit the application.\nAliases: /q, /exit"
+        );
+        assert_eq!(SlashCommand::lookup("exit"), Some(SlashCommand::Quit));
+    }
+
+    #[test]
+    fn command_line_splits_at_char_boundaries() {
+        assert_eq!(
+            split_command_line("/model ñandú-模型 ✓"),
+            Some(("model", Some("ñandú-模型 ✓")))
+        );
+        assert_eq!(split_command_line("/é"), Some(("é", None)));
+        assert_eq!(
+            split_command_line("/模型\u{3000}x"),
+            Some(("模型", Some("x")))
+        );
+        assert_eq!(split_command_line("/ new "), Some(("new", Some(""))));
+        assert_eq!(split_command_line("ñ /model"), None);
+        assert_eq!(
+            parse_prompt_mode("/ask ¿qué hace esto?"),
+            (PromptMode::Ask, "¿qué hace esto?")
+        );
+    }
+}
@@ -34,9 +34,7 @@ pub(crate) struct StatusIndicatorWidget {
     /// time).
     text: String,
 
-    /// Height in terminal rows – matches the height of the textarea at the
-    /// moment the task started so the UI does not jump when we toggle between
-    /// input mode and loading mode.
+    /// Height in terminal rows.
     height: u16,
 
     frame_idx: Arc<AtomicUsize>,
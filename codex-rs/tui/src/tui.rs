@@ -39,6 +39,23 @@ fn set_panic_hook() {
     }));
 }
 
+/// Hand the terminal to another program, such as an external editor, until
+/// [`resume`] is called.
+pub fn suspend() -> Result<()> {
+    execute!(stdout(), DisableBracketedPaste)?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+    Ok(())
+}
+
+/// Take the terminal back after [`suspend`] and schedule a full repaint.
+pub fn resume(terminal: &mut Tui) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    execute!(stdout(), EnableBracketedPaste)?;
+    terminal.clear()
+}
+
 /// Restore the terminal to its original state
 pub fn restore() -> Result<()> {
     // We are shutting down, and we cannot reference the `MouseCapture`, so we